@@ -0,0 +1,401 @@
+use gltf::animation::util::ReadOutputs;
+
+use super::{JointPose, Skin};
+use crate::{
+    asset_info::{source_path, AssetInfo},
+    gltf_import::import_gltf,
+    AssetLoader, Vfs,
+};
+
+/// The property of a node a channel animates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelProperty {
+    Translation,
+    Rotation,
+    Scale,
+    MorphWeights,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    /// Keep each keyframe's value until the next one
+    Step,
+    /// Hermite splines, with in and out tangents stored around each keyframe's value
+    CubicSpline,
+}
+
+/// The keyframes of one property of one node
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationChannel {
+    pub node: usize,
+    pub property: ChannelProperty,
+    pub interpolation: Interpolation,
+    /// Keyframe times in seconds, increasing
+    pub times: Vec<f32>,
+    /// `components` floats per keyframe, or per tangent and keyframe for cubic splines,
+    /// rotations are quaternions stored as x, y, z, w
+    pub values: Vec<f32>,
+    pub components: usize,
+}
+
+impl AnimationChannel {
+    /// The channel's value at the time, held at the first and last keyframes outside of them
+    pub fn sample(&self, time: f32) -> Vec<f32> {
+        let mut value = vec![0.0; self.components];
+        self.sample_into(time, &mut value);
+
+        value
+    }
+
+    /// Like `sample`, without allocating, `value` takes `components` floats
+    pub fn sample_into(&self, time: f32, value: &mut [f32]) {
+        let components = self.components;
+        // Cubic splines store an in tangent, the value and an out tangent per keyframe
+        let stride = match self.interpolation {
+            Interpolation::CubicSpline => 3 * components,
+            _ => components,
+        };
+        let offset = match self.interpolation {
+            Interpolation::CubicSpline => components,
+            _ => 0,
+        };
+        let key = |index: usize, part: usize| {
+            let start = index * stride + part;
+            &self.values[start..start + components]
+        };
+
+        let next = self.times.partition_point(|&key_time| key_time <= time);
+
+        if next == 0 || next == self.times.len() || self.interpolation == Interpolation::Step {
+            value.copy_from_slice(key(next.saturating_sub(1), offset));
+            return;
+        }
+
+        let previous = next - 1;
+        let delta = self.times[next] - self.times[previous];
+        let t = match delta > 0.0 {
+            true => (time - self.times[previous]) / delta,
+            false => 0.0,
+        };
+
+        match self.interpolation {
+            Interpolation::Linear if self.property == ChannelProperty::Rotation => {
+                let rotation = slerp(key(previous, 0), key(next, 0), t);
+                value.copy_from_slice(&rotation);
+            }
+            Interpolation::Linear => {
+                for ((value, a), b) in value.iter_mut().zip(key(previous, 0)).zip(key(next, 0)) {
+                    *value = a + (b - a) * t;
+                }
+            }
+            Interpolation::CubicSpline => {
+                let (t2, t3) = (t * t, t * t * t);
+                let value_a = key(previous, components);
+                let out_tangent_a = key(previous, 2 * components);
+                let in_tangent_b = key(next, 0);
+                let value_b = key(next, components);
+
+                for (i, value) in value.iter_mut().enumerate() {
+                    *value = (2.0 * t3 - 3.0 * t2 + 1.0) * value_a[i]
+                        + (t3 - 2.0 * t2 + t) * delta * out_tangent_a[i]
+                        + (-2.0 * t3 + 3.0 * t2) * value_b[i]
+                        + (t3 - t2) * delta * in_tangent_b[i];
+                }
+
+                if self.property == ChannelProperty::Rotation {
+                    normalize(value);
+                }
+            }
+            Interpolation::Step => unreachable!(),
+        }
+    }
+}
+
+// Spherical interpolation along the shorter way around
+fn slerp(a: &[f32], b: &[f32], t: f32) -> [f32; 4] {
+    let mut dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let sign = match dot < 0.0 {
+        true => -1.0,
+        false => 1.0,
+    };
+    dot *= sign;
+
+    // Nearly equal rotations would divide by almost zero, blending them linearly is exact enough
+    let (weight_a, weight_b) = match dot > 0.9995 {
+        true => (1.0 - t, t),
+        false => {
+            let angle = dot.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        }
+    };
+
+    let mut result = [0.0; 4];
+    for (i, result) in result.iter_mut().enumerate() {
+        *result = a[i] * weight_a + b[i] * weight_b * sign;
+    }
+    normalize(&mut result);
+
+    result
+}
+
+fn normalize(value: &mut [f32]) {
+    let length = value.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if length > 0.0 {
+        value.iter_mut().for_each(|x| *x /= length);
+    }
+}
+
+/// A glTF animation, loaded with ids like `character.glb#animation0`
+pub struct AnimationClip {
+    pub asset_info: AssetInfo,
+    pub name: Option<String>,
+    /// Seconds until the last keyframe of any channel
+    pub duration: f32,
+    pub channels: Vec<AnimationChannel>,
+}
+
+impl AssetLoader for AnimationClip {
+    type Settings = ();
+    type Data = (Option<String>, Vec<AnimationChannel>);
+
+    const EXTENSIONS: &'static [&'static str] = &["gltf", "glb"];
+
+    fn unloaded(asset_info: AssetInfo, _settings: ()) -> AnimationClip {
+        AnimationClip {
+            asset_info,
+            name: None,
+            duration: 0.0,
+            channels: vec![],
+        }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+
+    fn asset_info_mut(&mut self) -> &mut AssetInfo {
+        &mut self.asset_info
+    }
+
+    fn settings(&self) {}
+
+    fn read(
+        id: &str,
+        _settings: &(),
+        vfs: &Vfs,
+    ) -> Result<(Option<String>, Vec<AnimationChannel>), String> {
+        let (gltf, buffers) = import_gltf(source_path(id), vfs)?;
+
+        let animation = match id
+            .split_once("#animation")
+            .and_then(|(_, index)| index.parse().ok())
+            .and_then(|index| gltf.animations().nth(index))
+        {
+            Some(animation) => animation,
+            None => return Err(format!("Invalid glTF animation id: {}", id)),
+        };
+
+        let mut channels = vec![];
+
+        for channel in animation.channels() {
+            let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+
+            let times: Vec<f32> = reader
+                .read_inputs()
+                .map_or_else(Vec::new, |inputs| inputs.collect());
+
+            let (property, values): (ChannelProperty, Vec<f32>) = match reader.read_outputs() {
+                Some(ReadOutputs::Translations(translations)) => (
+                    ChannelProperty::Translation,
+                    translations.flatten().collect(),
+                ),
+                Some(ReadOutputs::Rotations(rotations)) => (
+                    ChannelProperty::Rotation,
+                    rotations.into_f32().flatten().collect(),
+                ),
+                Some(ReadOutputs::Scales(scales)) => {
+                    (ChannelProperty::Scale, scales.flatten().collect())
+                }
+                Some(ReadOutputs::MorphTargetWeights(weights)) => {
+                    (ChannelProperty::MorphWeights, weights.into_f32().collect())
+                }
+                None => return Err(format!("{} channel {} has no values", id, channel.index())),
+            };
+
+            let interpolation = match channel.sampler().interpolation() {
+                gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                gltf::animation::Interpolation::Step => Interpolation::Step,
+                gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+            };
+
+            let values_per_keyframe = match interpolation {
+                Interpolation::CubicSpline => 3 * times.len(),
+                _ => times.len(),
+            };
+
+            // Morph weights have one value per target, which only the output count tells
+            let components = match property {
+                ChannelProperty::Translation | ChannelProperty::Scale => 3,
+                ChannelProperty::Rotation => 4,
+                ChannelProperty::MorphWeights => values.len() / values_per_keyframe.max(1),
+            };
+
+            if times.is_empty()
+                || components == 0
+                || values.len() != values_per_keyframe * components
+            {
+                return Err(format!(
+                    "{} channel {} has {} values for {} keyframes",
+                    id,
+                    channel.index(),
+                    values.len(),
+                    times.len()
+                ));
+            }
+
+            channels.push(AnimationChannel {
+                node: channel.target().node().index(),
+                property,
+                interpolation,
+                times,
+                values,
+                components,
+            });
+        }
+
+        Ok((animation.name().map(|name| name.to_owned()), channels))
+    }
+
+    fn store(&mut self, (name, channels): (Option<String>, Vec<AnimationChannel>)) {
+        self.duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last())
+            .fold(0.0, |duration: f32, &time| duration.max(time));
+        self.name = name;
+        self.channels = channels;
+    }
+}
+
+impl AnimationClip {
+    pub fn gltf_animation_id(path: &str, animation_index: usize) -> String {
+        format!("{}#animation{}", path, animation_index)
+    }
+
+    /// Set the poses of the skin's joints the clip animates to their values at the time,
+    /// other joints keep their pose
+    pub fn sample_pose(&self, skin: &Skin, time: f32, poses: &mut [JointPose]) {
+        let mut value = [0.0; 4];
+
+        for channel in &self.channels {
+            let pose = match skin.joint_of_node(channel.node) {
+                Some(joint) => &mut poses[joint],
+                None => continue,
+            };
+
+            match channel.property {
+                ChannelProperty::Translation => {
+                    channel.sample_into(time, &mut value[..3]);
+                    pose.translation = glm::vec3(value[0], value[1], value[2]);
+                }
+                ChannelProperty::Rotation => {
+                    channel.sample_into(time, &mut value);
+                    pose.rotation = glm::quat(value[0], value[1], value[2], value[3]);
+                }
+                ChannelProperty::Scale => {
+                    channel.sample_into(time, &mut value[..3]);
+                    pose.scale = glm::vec3(value[0], value[1], value[2]);
+                }
+                ChannelProperty::MorphWeights => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(
+        property: ChannelProperty,
+        interpolation: Interpolation,
+        values: Vec<f32>,
+    ) -> AnimationChannel {
+        AnimationChannel {
+            node: 0,
+            property,
+            interpolation,
+            times: vec![1.0, 3.0],
+            components: match property {
+                ChannelProperty::Rotation => 4,
+                _ => 1,
+            },
+            values,
+        }
+    }
+
+    #[test]
+    fn test_linear_and_step_sampling() {
+        let linear = channel(
+            ChannelProperty::MorphWeights,
+            Interpolation::Linear,
+            vec![2.0, 4.0],
+        );
+        assert_eq!(linear.sample(0.0), [2.0]);
+        assert_eq!(linear.sample(2.0), [3.0]);
+        assert_eq!(linear.sample(3.0), [4.0]);
+        assert_eq!(linear.sample(9.0), [4.0]);
+
+        let step = channel(
+            ChannelProperty::MorphWeights,
+            Interpolation::Step,
+            vec![2.0, 4.0],
+        );
+        assert_eq!(step.sample(2.9), [2.0]);
+        assert_eq!(step.sample(3.0), [4.0]);
+    }
+
+    #[test]
+    fn test_cubic_spline_sampling() {
+        // In tangent, value and out tangent per keyframe, flat tangents ease in and out
+        let flat = channel(
+            ChannelProperty::MorphWeights,
+            Interpolation::CubicSpline,
+            vec![0.0, 2.0, 0.0, 0.0, 4.0, 0.0],
+        );
+        assert_eq!(flat.sample(1.0), [2.0]);
+        assert_eq!(flat.sample(2.0), [3.0]);
+        assert!(flat.sample(1.5)[0] < 2.5);
+        assert_eq!(flat.sample(3.0), [4.0]);
+
+        // Tangents of the slope between the keyframes make a straight line
+        let straight = channel(
+            ChannelProperty::MorphWeights,
+            Interpolation::CubicSpline,
+            vec![1.0, 2.0, 1.0, 1.0, 4.0, 1.0],
+        );
+        assert!((straight.sample(1.5)[0] - 2.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_rotation_takes_shorter_way() {
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        // A quarter turn around z, the second key stored with the opposite sign
+        let rotation = channel(
+            ChannelProperty::Rotation,
+            Interpolation::Linear,
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, -half, -half],
+        );
+
+        let sampled = rotation.sample(2.0);
+        let eighth_turn =
+            glm::quat_angle_axis(std::f32::consts::FRAC_PI_4, &glm::vec3(0.0, 0.0, 1.0));
+        let expected = [eighth_turn.i, eighth_turn.j, eighth_turn.k, eighth_turn.w];
+
+        for (sampled, expected) in sampled.iter().zip(expected) {
+            assert!((sampled - expected).abs() < 1e-5);
+        }
+    }
+}
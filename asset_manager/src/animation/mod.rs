@@ -0,0 +1,137 @@
+mod clip;
+mod skin;
+
+pub use clip::{AnimationChannel, AnimationClip, ChannelProperty, Interpolation};
+pub use skin::{Joint, JointPose, Skin};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_support::temp_dir, AssetInfo, AssetLoader, AssetStatus, GltfScene, Vfs};
+
+    // The shoulder is moved and the hand turned by the animation, the hand is listed first
+    const SKINNED: &str = r#"{
+        "asset": { "version": "2.0" },
+        "buffers": [{ "uri": "skinned.bin", "byteLength": 192 }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 8 },
+            { "buffer": 0, "byteOffset": 8, "byteLength": 24 },
+            { "buffer": 0, "byteOffset": 32, "byteLength": 32 },
+            { "buffer": 0, "byteOffset": 64, "byteLength": 128 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR",
+              "min": [0.0], "max": [1.0] },
+            { "bufferView": 1, "componentType": 5126, "count": 2, "type": "VEC3" },
+            { "bufferView": 2, "componentType": 5126, "count": 2, "type": "VEC4" },
+            { "bufferView": 3, "componentType": 5126, "count": 2, "type": "MAT4" }
+        ],
+        "meshes": [{ "primitives": [] }],
+        "skins": [{ "joints": [2, 1], "inverseBindMatrices": 3 }],
+        "animations": [{
+            "name": "wave",
+            "channels": [
+                { "sampler": 0, "target": { "node": 1, "path": "translation" } },
+                { "sampler": 1, "target": { "node": 2, "path": "rotation" } }
+            ],
+            "samplers": [
+                { "input": 0, "output": 1, "interpolation": "LINEAR" },
+                { "input": 0, "output": 2, "interpolation": "STEP" }
+            ]
+        }],
+        "nodes": [
+            { "name": "armature", "translation": [0.0, 0.0, 5.0], "children": [1] },
+            { "name": "shoulder", "children": [2] },
+            { "name": "hand", "translation": [0.0, 1.0, 0.0] },
+            { "name": "body", "mesh": 0, "skin": 0 }
+        ],
+        "scenes": [{ "nodes": [0, 3] }],
+        "scene": 0
+    }"#;
+
+    fn buffer() -> Vec<u8> {
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let floats: Vec<f32> = [
+            // Times
+            vec![0.0, 1.0],
+            // Shoulder translations
+            vec![0.0, 0.0, 0.0, 0.0, 2.0, 0.0],
+            // Hand rotations, a quarter turn around z
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, half, half],
+            // Inverse bind matrices, the hand was bound one up from the shoulder
+            vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            vec![0.0, 0.0, 1.0, 0.0, 0.0, -1.0, 0.0, 1.0],
+            glm::Mat4::identity().as_slice().to_vec(),
+        ]
+        .concat();
+
+        floats
+            .iter()
+            .flat_map(|float| float.to_le_bytes())
+            .collect()
+    }
+
+    fn unloaded<T: AssetLoader<Settings = ()>>(id: &str) -> T {
+        T::unloaded(
+            AssetInfo {
+                id: id.to_owned(),
+                status: AssetStatus::Unloaded,
+            },
+            (),
+        )
+    }
+
+    #[test]
+    fn test_import_skin_and_animation() {
+        let directory = temp_dir("animation");
+        std::fs::write(directory.join("skinned.gltf"), SKINNED).unwrap();
+        std::fs::write(directory.join("skinned.bin"), buffer()).unwrap();
+        let path = directory.join("skinned.gltf");
+        let path = path.to_str().unwrap();
+
+        let vfs = Vfs::new();
+
+        let scene = GltfScene::read(path, &vfs).unwrap();
+        let skin_id = scene.nodes[1].skin.clone().unwrap();
+        assert_eq!(skin_id, Skin::gltf_skin_id(path, 0));
+        assert_eq!(
+            scene.animations,
+            [AnimationClip::gltf_animation_id(path, 0)]
+        );
+
+        let mut skin: Skin = unloaded(&skin_id);
+        skin.store(Skin::read(&skin_id, &(), &vfs).unwrap());
+
+        assert_eq!(skin.joints[0].name.as_deref(), Some("hand"));
+        assert_eq!(skin.joints[0].parent, Some(1));
+        assert_eq!(skin.joints[1].parent, None);
+        assert_eq!(
+            skin.skeleton_transform,
+            glm::translation(&glm::vec3(0.0, 0.0, 5.0))
+        );
+
+        // In the rest pose the hand is where it was bound, so skinning only adds the armature
+        let matrices = skin.skinning_matrices(&skin.rest_pose());
+        assert_eq!(matrices[0].column(3).xyz(), glm::vec3(0.0, 0.0, 5.0));
+
+        let mut clip: AnimationClip = unloaded(&scene.animations[0]);
+        clip.store(AnimationClip::read(&scene.animations[0], &(), &vfs).unwrap());
+
+        assert_eq!(clip.name.as_deref(), Some("wave"));
+        assert_eq!(clip.duration, 1.0);
+        assert_eq!(clip.channels[1].interpolation, Interpolation::Step);
+
+        let mut poses = skin.rest_pose();
+        clip.sample_pose(&skin, 0.5, &mut poses);
+        assert_eq!(poses[1].translation, glm::vec3(0.0, 1.0, 0.0));
+        assert_eq!(poses[0].rotation, glm::quat_identity());
+        assert_eq!(poses[0].translation, glm::vec3(0.0, 1.0, 0.0));
+
+        clip.sample_pose(&skin, 1.0, &mut poses);
+        assert_eq!(poses[0].rotation.w, std::f32::consts::FRAC_1_SQRT_2);
+
+        assert!(Skin::read(&Skin::gltf_skin_id(path, 1), &(), &vfs).is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
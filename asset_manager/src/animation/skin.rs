@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+
+use crate::{
+    asset_info::{source_path, AssetInfo},
+    gltf_import::import_gltf,
+    AssetLoader, Vfs,
+};
+
+/// A transform made of its parts, which unlike a matrix can be blended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JointPose {
+    pub translation: glm::Vec3,
+    pub rotation: glm::Quat,
+    pub scale: glm::Vec3,
+}
+
+impl JointPose {
+    pub fn matrix(&self) -> glm::Mat4 {
+        glm::translation(&self.translation)
+            * glm::quat_to_mat4(&self.rotation)
+            * glm::scaling(&self.scale)
+    }
+
+    /// Blend towards the other pose, 0 keeps this pose and 1 gives the other
+    pub fn blend(&self, other: &JointPose, weight: f32) -> JointPose {
+        JointPose {
+            translation: glm::lerp(&self.translation, &other.translation, weight),
+            rotation: blend_rotation(&self.rotation, &other.rotation, weight),
+            scale: glm::lerp(&self.scale, &other.scale, weight),
+        }
+    }
+
+    /// Add how far `target` is from `reference` on top of this pose, scaled by the weight
+    pub fn add_difference(
+        &self,
+        reference: &JointPose,
+        target: &JointPose,
+        weight: f32,
+    ) -> JointPose {
+        let rotation = target.rotation * glm::quat_inverse(&reference.rotation);
+        let scale = target.scale.zip_map(&reference.scale, |target, reference| {
+            match reference != 0.0 {
+                true => target / reference,
+                false => 1.0,
+            }
+        });
+
+        JointPose {
+            translation: self.translation + (target.translation - reference.translation) * weight,
+            rotation: blend_rotation(&glm::quat_identity(), &rotation, weight) * self.rotation,
+            scale: self
+                .scale
+                .component_mul(&glm::lerp(&glm::vec3(1.0, 1.0, 1.0), &scale, weight)),
+        }
+    }
+}
+
+// Normalized linear blending along the shorter way around, close enough to slerp for the
+// small steps between animation poses and cheaper
+fn blend_rotation(from: &glm::Quat, to: &glm::Quat, weight: f32) -> glm::Quat {
+    let to = match glm::quat_dot(from, to) < 0.0 {
+        true => -to,
+        false => *to,
+    };
+
+    glm::quat_normalize(&(from * (1.0 - weight) + to * weight))
+}
+
+impl Default for JointPose {
+    fn default() -> Self {
+        JointPose {
+            translation: glm::Vec3::zeros(),
+            rotation: glm::quat_identity(),
+            scale: glm::vec3(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// One joint of a skin, in the order the skinned vertices' joint indices refer to them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joint {
+    pub name: Option<String>,
+    /// The glTF node of the joint, which animation channels target
+    pub node: usize,
+    /// The joint this one moves with, `None` for the root joints
+    pub parent: Option<usize>,
+    /// The joint's transform relative to its parent while nothing animates it
+    pub rest_pose: JointPose,
+    /// Moves a vertex from mesh space into the joint's space at the time the mesh was bound
+    pub inverse_bind_matrix: glm::Mat4,
+}
+
+/// The joints of a glTF skin, loaded with ids like `character.glb#skin0`
+pub struct Skin {
+    pub asset_info: AssetInfo,
+    pub joints: Vec<Joint>,
+    /// The transform of the nodes above the root joints, like an armature node
+    pub skeleton_transform: glm::Mat4,
+}
+
+impl AssetLoader for Skin {
+    type Settings = ();
+    type Data = (Vec<Joint>, glm::Mat4);
+
+    const EXTENSIONS: &'static [&'static str] = &["gltf", "glb"];
+
+    fn unloaded(asset_info: AssetInfo, _settings: ()) -> Skin {
+        Skin {
+            asset_info,
+            joints: vec![],
+            skeleton_transform: glm::Mat4::identity(),
+        }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+
+    fn asset_info_mut(&mut self) -> &mut AssetInfo {
+        &mut self.asset_info
+    }
+
+    fn settings(&self) {}
+
+    fn read(id: &str, _settings: &(), vfs: &Vfs) -> Result<(Vec<Joint>, glm::Mat4), String> {
+        let (gltf, buffers) = import_gltf(source_path(id), vfs)?;
+
+        let skin = match id
+            .split_once("#skin")
+            .and_then(|(_, index)| index.parse().ok())
+            .and_then(|index| gltf.skins().nth(index))
+        {
+            Some(skin) => skin,
+            None => return Err(format!("Invalid glTF skin id: {}", id)),
+        };
+
+        let mut node_parents = HashMap::new();
+        for node in gltf.nodes() {
+            for child in node.children() {
+                node_parents.insert(child.index(), node.index());
+            }
+        }
+
+        let joint_nodes: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+
+        let inverse_bind_matrices: Vec<glm::Mat4> = skin
+            .reader(|buffer| Some(&buffers[buffer.index()]))
+            .read_inverse_bind_matrices()
+            .map_or_else(Vec::new, |matrices| matrices.map(glm::Mat4::from).collect());
+
+        let joints = skin
+            .joints()
+            .enumerate()
+            .map(|(index, node)| {
+                let (translation, rotation, scale) = node.transform().decomposed();
+
+                Joint {
+                    name: node.name().map(|name| name.to_owned()),
+                    node: node.index(),
+                    parent: node_parents
+                        .get(&node.index())
+                        .and_then(|parent| joint_nodes.iter().position(|node| node == parent)),
+                    rest_pose: JointPose {
+                        translation: glm::Vec3::from(translation),
+                        rotation: glm::quat(rotation[0], rotation[1], rotation[2], rotation[3]),
+                        scale: glm::Vec3::from(scale),
+                    },
+                    // Missing inverse bind matrices are identity matrices
+                    inverse_bind_matrix: inverse_bind_matrices
+                        .get(index)
+                        .copied()
+                        .unwrap_or_else(glm::Mat4::identity),
+                }
+            })
+            .collect::<Vec<Joint>>();
+
+        // Walk up from the first root joint through the nodes that aren't joints
+        let mut skeleton_transform = glm::Mat4::identity();
+        let root = joints.iter().find(|joint| joint.parent.is_none());
+        let mut ancestor = root.and_then(|root| node_parents.get(&root.node));
+
+        while let Some(&node) = ancestor {
+            let matrix = gltf.nodes().nth(node).unwrap().transform().matrix();
+            skeleton_transform = glm::Mat4::from(matrix) * skeleton_transform;
+            ancestor = node_parents.get(&node);
+        }
+
+        Ok((joints, skeleton_transform))
+    }
+
+    fn store(&mut self, (joints, skeleton_transform): (Vec<Joint>, glm::Mat4)) {
+        self.joints = joints;
+        self.skeleton_transform = skeleton_transform;
+    }
+}
+
+impl Skin {
+    pub fn gltf_skin_id(path: &str, skin_index: usize) -> String {
+        format!("{}#skin{}", path, skin_index)
+    }
+
+    /// The joint of the glTF node, to find the joint an animation channel moves
+    pub fn joint_of_node(&self, node: usize) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.node == node)
+    }
+
+    pub fn rest_pose(&self) -> Vec<JointPose> {
+        self.joints.iter().map(|joint| joint.rest_pose).collect()
+    }
+
+    /// Every joint's transform in mesh space, from one pose per joint relative to its parent
+    pub fn global_matrices(&self, poses: &[JointPose]) -> Vec<glm::Mat4> {
+        let mut matrices: Vec<Option<glm::Mat4>> = vec![None; self.joints.len()];
+
+        // glTF doesn't order joints, so parents are found first where they come later
+        fn global(
+            joint: usize,
+            skin: &Skin,
+            poses: &[JointPose],
+            matrices: &mut [Option<glm::Mat4>],
+        ) -> glm::Mat4 {
+            if let Some(matrix) = matrices[joint] {
+                return matrix;
+            }
+
+            let parent = match skin.joints[joint].parent {
+                Some(parent) => global(parent, skin, poses, matrices),
+                None => skin.skeleton_transform,
+            };
+
+            let matrix = parent * poses[joint].matrix();
+            matrices[joint] = Some(matrix);
+
+            matrix
+        }
+
+        (0..self.joints.len())
+            .map(|joint| global(joint, self, poses, &mut matrices))
+            .collect()
+    }
+
+    /// The matrices that move skinned vertices from where they were bound to the pose,
+    /// one per joint, weighted by the vertices' joint weights
+    pub fn skinning_matrices(&self, poses: &[JointPose]) -> Vec<glm::Mat4> {
+        self.global_matrices(poses)
+            .iter()
+            .zip(&self.joints)
+            .map(|(global, joint)| global * joint.inverse_bind_matrix)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AssetStatus;
+
+    fn arm() -> Skin {
+        let joint = |node, parent, translation| Joint {
+            name: None,
+            node,
+            parent,
+            rest_pose: JointPose {
+                translation,
+                ..Default::default()
+            },
+            inverse_bind_matrix: glm::Mat4::identity(),
+        };
+
+        // The hand comes first, before its parent
+        let mut skin = Skin::unloaded(
+            AssetInfo {
+                id: "arm.gltf#skin0".to_owned(),
+                status: AssetStatus::Unloaded,
+            },
+            (),
+        );
+        skin.joints = vec![
+            joint(2, Some(1), glm::vec3(0.0, 1.0, 0.0)),
+            joint(1, None, glm::vec3(0.0, 2.0, 0.0)),
+        ];
+
+        skin
+    }
+
+    #[test]
+    fn test_global_matrices_follow_parents() {
+        let skin = arm();
+        let mut poses = skin.rest_pose();
+
+        let matrices = skin.global_matrices(&poses);
+        assert_eq!(matrices[0].column(3).xyz(), glm::vec3(0.0, 3.0, 0.0));
+        assert_eq!(matrices[1].column(3).xyz(), glm::vec3(0.0, 2.0, 0.0));
+
+        // Turning the shoulder a quarter turn around z swings the hand to -x
+        poses[1].rotation =
+            glm::quat_angle_axis(std::f32::consts::FRAC_PI_2, &glm::vec3(0.0, 0.0, 1.0));
+        let hand = skin.global_matrices(&poses)[0].column(3).xyz();
+        assert!((hand - glm::vec3(-1.0, 2.0, 0.0)).norm() < 1e-5);
+
+        assert_eq!(skin.joint_of_node(1), Some(1));
+        assert_eq!(skin.joint_of_node(0), None);
+    }
+
+    #[test]
+    fn test_blend_and_add_difference() {
+        let z = glm::vec3(0.0, 0.0, 1.0);
+        let rest = JointPose::default();
+        let turned = JointPose {
+            translation: glm::vec3(2.0, 0.0, 0.0),
+            rotation: glm::quat_angle_axis(std::f32::consts::FRAC_PI_2, &z),
+            scale: glm::vec3(2.0, 2.0, 2.0),
+        };
+
+        let half = rest.blend(&turned, 0.5);
+        assert_eq!(half.translation, glm::vec3(1.0, 0.0, 0.0));
+        assert_eq!(half.scale, glm::vec3(1.5, 1.5, 1.5));
+        let eighth_turn = glm::quat_angle_axis(std::f32::consts::FRAC_PI_4, &z);
+        assert!((half.rotation.coords - eighth_turn.coords).norm() < 1e-5);
+
+        // Adding the whole difference from the rest pose twice moves and turns twice as far
+        let twice = turned.add_difference(&rest, &turned, 1.0);
+        assert_eq!(twice.translation, glm::vec3(4.0, 0.0, 0.0));
+        assert_eq!(twice.scale, glm::vec3(4.0, 4.0, 4.0));
+        let half_turn = glm::quat_angle_axis(std::f32::consts::PI, &z);
+        assert!((twice.rotation.coords - half_turn.coords).norm() < 1e-5);
+
+        assert_eq!(turned.add_difference(&rest, &turned, 0.0), turned);
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::{Arc, Mutex};
+
+use crate::Handle;
+
+/// Something that happened to an asset, published once per change
+pub enum AssetEvent<T> {
+    Loaded(Handle<T>),
+    Failed(Handle<T>),
+    Reloaded(Handle<T>),
+    Unloaded(Handle<T>),
+}
+
+impl<T> AssetEvent<T> {
+    pub fn handle(&self) -> Handle<T> {
+        match self {
+            AssetEvent::Loaded(handle)
+            | AssetEvent::Failed(handle)
+            | AssetEvent::Reloaded(handle)
+            | AssetEvent::Unloaded(handle) => *handle,
+        }
+    }
+}
+
+// Implemented by hand so they don't require the same traits on `T`
+
+impl<T> Clone for AssetEvent<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for AssetEvent<T> {}
+
+impl<T> PartialEq for AssetEvent<T> {
+    fn eq(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+            && self.handle() == other.handle()
+    }
+}
+
+impl<T> Eq for AssetEvent<T> {}
+
+impl<T> std::fmt::Debug for AssetEvent<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetEvent::Loaded(handle) => write!(f, "Loaded({:?})", handle),
+            AssetEvent::Failed(handle) => write!(f, "Failed({:?})", handle),
+            AssetEvent::Reloaded(handle) => write!(f, "Reloaded({:?})", handle),
+            AssetEvent::Unloaded(handle) => write!(f, "Unloaded({:?})", handle),
+        }
+    }
+}
+
+/// Events pushed from the loader threads, waiting to be drained by the owner of the asset manager
+pub struct EventQueue<T> {
+    events: Arc<Mutex<Vec<AssetEvent<T>>>>,
+}
+
+impl<T> EventQueue<T> {
+    pub fn new() -> EventQueue<T> {
+        EventQueue {
+            events: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    pub fn push(&self, event: AssetEvent<T>) {
+        self.events.lock().unwrap().push(event);
+    }
+
+    pub fn drain(&self) -> Vec<AssetEvent<T>> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+}
+
+impl<T> Clone for EventQueue<T> {
+    fn clone(&self) -> Self {
+        EventQueue {
+            events: self.events.clone(),
+        }
+    }
+}
+
+impl<T> Default for EventQueue<T> {
+    fn default() -> Self {
+        EventQueue::new()
+    }
+}
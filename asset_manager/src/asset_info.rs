@@ -11,3 +11,8 @@ pub struct AssetInfo {
     pub id: String,
     pub status: AssetStatus,
 }
+
+/// The file an asset id refers to, assets read from part of a file add `#` and the part name
+pub fn source_path(id: &str) -> &str {
+    id.split('#').next().unwrap_or(id)
+}
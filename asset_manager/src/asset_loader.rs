@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use crate::{
+    asset_info::{source_path, AssetInfo},
+    AssetManager, Vfs,
+};
+
+/// A kind of asset the asset manager can load, cache and reload
+///
+/// Loading happens in two steps: `read` and `optimize` run on a loader thread without holding
+/// the asset's lock, then `store` moves the result into the asset. The asset manager takes care of the
+/// status, the events and keeping the last good version when a reload fails.
+pub trait AssetLoader: Sized + Send + 'static {
+    /// How the asset should be read, like the color space of a texture
+    type Settings: Clone + Send + 'static;
+    /// The result of reading the asset, before it is stored
+    type Data: Send;
+
+    /// Lowercase extensions, without the dot, of the files this loader reads
+    /// Ids of parts of a file, like `scene.gltf#mesh0`, are matched by the file's extension
+    const EXTENSIONS: &'static [&'static str];
+
+    /// The asset before anything has been read
+    fn unloaded(asset_info: AssetInfo, settings: Self::Settings) -> Self;
+
+    fn asset_info(&self) -> &AssetInfo;
+
+    fn asset_info_mut(&mut self) -> &mut AssetInfo;
+
+    fn settings(&self) -> Self::Settings;
+
+    /// Files should be read through the virtual filesystem, so ids like `assets://a.png` work
+    fn read(id: &str, settings: &Self::Settings, vfs: &Vfs) -> Result<Self::Data, String>;
+
+    fn store(&mut self, data: Self::Data);
+
+    /// Prepare the data for use after `read`, on the same loader thread, like reordering a mesh
+    /// for the GPU's caches. Returning true stores the asset as `AssetStatus::Optimized`.
+    fn optimize(_data: &mut Self::Data, _settings: &Self::Settings) -> bool {
+        false
+    }
+
+    /// Other assets this one refers to, loaded by the asset manager's next update
+    fn dependencies(_data: &Self::Data) -> Vec<Dependency> {
+        vec![]
+    }
+
+    /// Whether the id has one of the loader's extensions
+    fn supports(id: &str) -> bool {
+        has_extension(id, Self::EXTENSIONS)
+    }
+}
+
+/// Whether the file the id refers to has one of the lowercase extensions
+pub(crate) fn has_extension(id: &str, extensions: &[&str]) -> bool {
+    let extension = match Path::new(source_path(id)).extension() {
+        Some(extension) => extension.to_string_lossy().to_ascii_lowercase(),
+        None => return false,
+    };
+
+    extensions.contains(&extension.as_str())
+}
+
+type LoadDependency = Box<dyn FnOnce(&mut AssetManager) + Send>;
+
+/// An asset another asset needs, reported by `AssetLoader::dependencies`
+pub struct Dependency {
+    id: String,
+    load: LoadDependency,
+}
+
+impl Dependency {
+    pub fn new<T: AssetLoader>(id: &str) -> Dependency
+    where
+        T::Settings: Default,
+    {
+        Dependency::with_settings::<T>(id, T::Settings::default())
+    }
+
+    pub fn with_settings<T: AssetLoader>(id: &str, settings: T::Settings) -> Dependency {
+        let closure_id = id.to_owned();
+
+        Dependency {
+            id: id.to_owned(),
+            load: Box::new(move |asset_manager: &mut AssetManager| {
+                asset_manager.load_with_settings::<T>(
+                    &closure_id,
+                    settings,
+                    crate::LoadPriority::Normal,
+                );
+            }),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub(crate) fn load(self, asset_manager: &mut AssetManager) {
+        (self.load)(asset_manager)
+    }
+}
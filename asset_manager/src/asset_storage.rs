@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::Handle;
+
+struct Slot<T> {
+    generation: u32,
+    id: String,
+    asset: Option<Arc<Mutex<T>>>,
+}
+
+/// Generational storage for one kind of asset
+///
+/// Paths are only used to find an existing handle at load time, everything
+/// after that goes through the handle.
+pub struct AssetStorage<T> {
+    slots: Vec<Slot<T>>,
+    free_slots: Vec<u32>,
+    ids: HashMap<String, Handle<T>>,
+}
+
+impl<T> AssetStorage<T> {
+    pub fn new() -> AssetStorage<T> {
+        AssetStorage {
+            slots: vec![],
+            free_slots: vec![],
+            ids: HashMap::new(),
+        }
+    }
+
+    pub fn handle(&self, id: &str) -> Option<Handle<T>> {
+        self.ids.get(id).copied()
+    }
+
+    pub fn insert(&mut self, id: &str, asset: T) -> (Handle<T>, Arc<Mutex<T>>) {
+        let asset = Arc::new(Mutex::new(asset));
+
+        let handle = match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.id = id.to_owned();
+                slot.asset = Some(asset.clone());
+
+                Handle::new(index, slot.generation)
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    id: id.to_owned(),
+                    asset: Some(asset.clone()),
+                });
+
+                Handle::new(self.slots.len() as u32 - 1, 0)
+            }
+        };
+
+        self.ids.insert(id.to_owned(), handle);
+
+        (handle, asset)
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> Option<Arc<Mutex<T>>> {
+        match self.slots.get(handle.index() as usize) {
+            Some(slot) if slot.generation == handle.generation() => slot.asset.clone(),
+            _ => None,
+        }
+    }
+
+    /// Remove the asset, invalidating every outstanding handle to it
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<Arc<Mutex<T>>> {
+        let slot = match self.slots.get_mut(handle.index() as usize) {
+            Some(slot) if slot.generation == handle.generation() => slot,
+            _ => return None,
+        };
+
+        let asset = slot.asset.take();
+
+        slot.generation = slot.generation.wrapping_add(1);
+        self.ids.remove(&slot.id);
+        self.free_slots.push(handle.index());
+
+        asset
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &Arc<Mutex<T>>)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.asset
+                .as_ref()
+                .map(|asset| (Handle::new(index as u32, slot.generation), asset))
+        })
+    }
+}
+
+impl<T> Default for AssetStorage<T> {
+    fn default() -> Self {
+        AssetStorage::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_get() {
+        let mut storage = AssetStorage::new();
+
+        let (handle, _) = storage.insert("a", 1);
+
+        assert_eq!(*storage.get(handle).unwrap().lock().unwrap(), 1);
+        assert_eq!(storage.handle("a"), Some(handle));
+    }
+
+    #[test]
+    fn test_removed_handle_is_stale() {
+        let mut storage = AssetStorage::new();
+
+        let (old_handle, _) = storage.insert("a", 1);
+        storage.remove(old_handle);
+
+        let (new_handle, _) = storage.insert("b", 2);
+
+        assert_eq!(old_handle.index(), new_handle.index());
+        assert!(storage.get(old_handle).is_none());
+        assert!(storage.handle("a").is_none());
+        assert_eq!(*storage.get(new_handle).unwrap().lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_iter_skips_removed() {
+        let mut storage = AssetStorage::new();
+
+        let (a, _) = storage.insert("a", 1);
+        let (b, _) = storage.insert("b", 2);
+        storage.remove(a);
+
+        let handles: Vec<Handle<i32>> = storage.iter().map(|(handle, _)| handle).collect();
+
+        assert_eq!(handles, vec![b]);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{vfs::relative_id, Vfs};
+
+/// Read a glTF or GLB file through the virtual filesystem, without its buffers
+pub fn open_gltf(path: &str, vfs: &Vfs) -> Result<gltf::Gltf, String> {
+    parse_gltf(path, &vfs.read(path)?)
+}
+
+fn parse_gltf(path: &str, bytes: &[u8]) -> Result<gltf::Gltf, String> {
+    match gltf::Gltf::from_slice(bytes) {
+        Ok(gltf) => Ok(gltf),
+        Err(e) => Err(format!("{}: {}", path, e)),
+    }
+}
+
+/// Read a glTF or GLB file and every buffer it uses
+pub fn import_gltf(
+    path: &str,
+    vfs: &Vfs,
+) -> Result<(gltf::Document, Vec<gltf::buffer::Data>), String> {
+    import_gltf_slice(path, &vfs.read(path)?, vfs)
+}
+
+/// Like `import_gltf`, for a file that has already been read
+pub fn import_gltf_slice(
+    path: &str,
+    bytes: &[u8],
+    vfs: &Vfs,
+) -> Result<(gltf::Document, Vec<gltf::buffer::Data>), String> {
+    let gltf::Gltf { document, mut blob } = parse_gltf(path, bytes)?;
+
+    let buffers = read_buffers(path, &document, &mut blob, vfs)?;
+
+    Ok((document, buffers))
+}
+
+/// Read every buffer of the document, external buffers are looked up relative to the file
+pub fn read_buffers(
+    path: &str,
+    document: &gltf::Document,
+    blob: &mut Option<Vec<u8>>,
+    vfs: &Vfs,
+) -> Result<Vec<gltf::buffer::Data>, String> {
+    let mut buffers = vec![];
+
+    for buffer in document.buffers() {
+        let data = match buffer.source() {
+            gltf::buffer::Source::Uri(uri) if is_external(&buffer) => {
+                gltf::buffer::Data(vfs.read(&relative_id(path, &percent_decode(uri)))?)
+            }
+            // The GLB binary chunk and data URIs need no file access
+            source => match gltf::buffer::Data::from_source_and_blob(source, None, blob) {
+                Ok(data) => data,
+                Err(e) => return Err(format!("{} buffer {}: {}", path, buffer.index(), e)),
+            },
+        };
+
+        if data.0.len() < buffer.length() {
+            return Err(format!(
+                "{} buffer {} is {} bytes, expected {}",
+                path,
+                buffer.index(),
+                data.0.len(),
+                buffer.length()
+            ));
+        }
+
+        buffers.push(data);
+    }
+
+    Ok(buffers)
+}
+
+/// Whether the buffer is a file of its own, rather than the GLB binary chunk or a data URI
+pub fn is_external(buffer: &gltf::Buffer) -> bool {
+    matches!(buffer.source(), gltf::buffer::Source::Uri(uri) if !uri.starts_with("data:"))
+}
+
+/// glTF URIs escape characters like spaces, file names don't
+pub fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("my%20model.bin"), "my model.bin");
+        assert_eq!(percent_decode("100%.bin"), "100%.bin");
+        assert_eq!(percent_decode("plain.bin"), "plain.bin");
+    }
+}
@@ -0,0 +1,223 @@
+use gltf::{camera::Projection, khr_lights_punctual::Kind};
+
+use crate::{gltf_import::open_gltf, AnimationClip, Mesh, Skin, Vfs};
+
+/// The node hierarchy of a glTF file, read without loading any of its buffers
+pub struct GltfScene {
+    /// The root nodes of the file's default scene
+    pub nodes: Vec<SceneNode>,
+    /// Mesh asset id of every mesh in the file, nodes draw their part of it, see
+    /// `Mesh::gltf_meshes_id`
+    pub meshes: String,
+    /// Animation clip asset ids of the file, see `AnimationClip::gltf_animation_id`
+    pub animations: Vec<String>,
+}
+
+pub struct SceneNode {
+    pub name: Option<String>,
+    pub translation: glm::Vec3,
+    pub rotation: glm::Quat,
+    pub scale: glm::Vec3,
+    /// The glTF mesh of the node, its part of the scene's `meshes`, see `SubMesh::part`
+    pub mesh: Option<usize>,
+    /// Skin asset id of the node's mesh, see `Skin::gltf_skin_id`
+    pub skin: Option<String>,
+    pub camera: Option<SceneCamera>,
+    pub light: Option<SceneLight>,
+    pub children: Vec<SceneNode>,
+}
+
+pub enum SceneCamera {
+    Perspective {
+        /// Missing when the camera should use the aspect ratio of the window
+        aspect_ratio: Option<f32>,
+        yfov: f32,
+        znear: f32,
+        /// Missing for an infinite projection
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+/// A light from the KHR_lights_punctual extension
+pub struct SceneLight {
+    pub kind: SceneLightKind,
+    pub color: glm::Vec3,
+    pub intensity: f32,
+    /// Missing when the light has no cutoff distance
+    pub range: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SceneLightKind {
+    Directional,
+    Point,
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+impl GltfScene {
+    pub fn read(path: &str, vfs: &Vfs) -> Result<GltfScene, String> {
+        let gltf = match open_gltf(path, vfs) {
+            Ok(gltf) => gltf,
+            Err(e) => return Err("Failed to read glTF scene: ".to_owned() + &e),
+        };
+
+        let scene = match gltf.default_scene().or_else(|| gltf.scenes().next()) {
+            Some(scene) => scene,
+            None => return Err(format!("glTF file has no scene: {}", path)),
+        };
+
+        Ok(GltfScene {
+            nodes: scene
+                .nodes()
+                .map(|node| GltfScene::read_node(path, &node))
+                .collect(),
+            meshes: Mesh::gltf_meshes_id(path),
+            animations: gltf
+                .animations()
+                .map(|animation| AnimationClip::gltf_animation_id(path, animation.index()))
+                .collect(),
+        })
+    }
+
+    fn read_node(path: &str, node: &gltf::Node) -> SceneNode {
+        let (translation, rotation, scale) = node.transform().decomposed();
+
+        SceneNode {
+            name: node.name().map(|name| name.to_owned()),
+            translation: glm::Vec3::from(translation),
+            rotation: glm::quat(rotation[0], rotation[1], rotation[2], rotation[3]),
+            scale: glm::Vec3::from(scale),
+            mesh: node.mesh().map(|mesh| mesh.index()),
+            skin: node
+                .skin()
+                .map(|skin| Skin::gltf_skin_id(path, skin.index())),
+            camera: node.camera().map(|camera| GltfScene::read_camera(&camera)),
+            light: node.light().map(|light| GltfScene::read_light(&light)),
+            children: node
+                .children()
+                .map(|child| GltfScene::read_node(path, &child))
+                .collect(),
+        }
+    }
+
+    fn read_camera(camera: &gltf::Camera) -> SceneCamera {
+        match camera.projection() {
+            Projection::Perspective(perspective) => SceneCamera::Perspective {
+                aspect_ratio: perspective.aspect_ratio(),
+                yfov: perspective.yfov(),
+                znear: perspective.znear(),
+                zfar: perspective.zfar(),
+            },
+            Projection::Orthographic(orthographic) => SceneCamera::Orthographic {
+                xmag: orthographic.xmag(),
+                ymag: orthographic.ymag(),
+                znear: orthographic.znear(),
+                zfar: orthographic.zfar(),
+            },
+        }
+    }
+
+    fn read_light(light: &gltf::khr_lights_punctual::Light) -> SceneLight {
+        let kind = match light.kind() {
+            Kind::Directional => SceneLightKind::Directional,
+            Kind::Point => SceneLightKind::Point,
+            Kind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => SceneLightKind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            },
+        };
+
+        SceneLight {
+            kind,
+            color: glm::Vec3::from(light.color()),
+            intensity: light.intensity(),
+            range: light.range(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_path;
+
+    const SCENE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": {
+            "KHR_lights_punctual": {
+                "lights": [{ "type": "spot", "color": [1.0, 0.5, 0.0], "intensity": 2.0,
+                             "spot": { "innerConeAngle": 0.1, "outerConeAngle": 0.5 } }]
+            }
+        },
+        "cameras": [{ "type": "perspective", "perspective": { "yfov": 1.0, "znear": 0.1 } }],
+        "meshes": [{ "primitives": [] }],
+        "nodes": [
+            { "name": "root", "translation": [1.0, 2.0, 3.0], "children": [1, 2] },
+            { "name": "camera", "camera": 0 },
+            { "name": "lamp", "extensions": { "KHR_lights_punctual": { "light": 0 } },
+              "children": [3] },
+            { "name": "bulb", "mesh": 0, "scale": [2.0, 2.0, 2.0] }
+        ],
+        "scenes": [{ "nodes": [0] }],
+        "scene": 0
+    }"#;
+
+    #[test]
+    fn test_read_keeps_hierarchy() {
+        let path = temp_path("read-keeps-hierarchy.gltf");
+        std::fs::write(&path, SCENE).unwrap();
+        let path = path.to_str().unwrap();
+
+        let scene = GltfScene::read(path, &Vfs::new()).unwrap();
+
+        assert_eq!(scene.nodes.len(), 1);
+
+        let root = &scene.nodes[0];
+        assert_eq!(root.name.as_deref(), Some("root"));
+        assert_eq!(root.translation, glm::vec3(1.0, 2.0, 3.0));
+        assert_eq!(root.children.len(), 2);
+        assert!(root.mesh.is_none());
+
+        let camera = &root.children[0];
+        assert!(matches!(
+            camera.camera,
+            Some(SceneCamera::Perspective { zfar: None, .. })
+        ));
+
+        let lamp = &root.children[1];
+        let light = lamp.light.as_ref().unwrap();
+        assert_eq!(
+            light.kind,
+            SceneLightKind::Spot {
+                inner_cone_angle: 0.1,
+                outer_cone_angle: 0.5,
+            }
+        );
+        assert_eq!(light.color, glm::vec3(1.0, 0.5, 0.0));
+
+        let bulb = &lamp.children[0];
+        assert_eq!(bulb.mesh, Some(0));
+        assert_eq!(scene.meshes, Mesh::gltf_meshes_id(path));
+        assert_eq!(bulb.scale, glm::vec3(2.0, 2.0, 2.0));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_missing_file() {
+        assert!(GltfScene::read("does/not/exist.glb", &Vfs::new()).is_err());
+    }
+}
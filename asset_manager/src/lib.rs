@@ -1,124 +1,697 @@
 extern crate nalgebra_glm as glm;
 
+mod animation;
+mod asset_event;
 mod asset_info;
+mod asset_loader;
+mod asset_storage;
+mod gltf_import;
+mod gltf_scene;
+mod handle;
+mod material;
 mod mesh;
 mod sound;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+mod texture;
+mod vfs;
+mod watcher;
+mod worker_pool;
 
 use std::{
+    any::{Any, TypeId},
     collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
-    thread::spawn,
 };
 
-use asset_info::AssetInfo;
+use asset_event::EventQueue;
+use asset_storage::AssetStorage;
+use config::Config;
+use log::warn;
+use vfs::resolve_mount_path;
+use watcher::AssetWatcher;
+use worker_pool::WorkerPool;
 
-pub use mesh::{Mesh, Vertex};
-use sound::Sound;
+pub use animation::{
+    AnimationChannel, AnimationClip, ChannelProperty, Interpolation, Joint, JointPose, Skin,
+};
+pub use asset_event::AssetEvent;
+pub use asset_info::{AssetInfo, AssetStatus};
+pub use asset_loader::{AssetLoader, Dependency};
+pub use gltf_scene::{GltfScene, SceneCamera, SceneLight, SceneLightKind, SceneNode};
+pub use handle::Handle;
+pub use material::{AlphaMode, PbrMaterial, TextureRef};
+pub use mesh::{
+    average_cache_miss_ratio, builtin_mesh, optimize_mesh, optimize_overdraw,
+    optimize_vertex_cache, optimize_vertex_fetch, quantize_vertices, simplify_mesh, Aabb,
+    BoundingSphere, CookedMesh, Indices, Mesh, MeshLod, MeshSettings, MeshStats, SubMesh, Vertex,
+    VertexAttribute, VertexFormat, BUILTIN_SCHEME,
+};
+pub use sound::{
+    Music, MusicControl, MusicSettings, MusicStream, MusicTrack, Sound, SoundBuffer, SoundSource,
+};
+pub use texture::{generate_mips, ColorSpace, Texture, TextureFormat};
+pub use vfs::{pack_directory, write_pak, PakArchive, Vfs};
+pub use worker_pool::LoadPriority;
+
+/// What load jobs share with the asset manager
+#[derive(Clone)]
+struct LoadContext {
+    vfs: Arc<Vfs>,
+    dependencies: Arc<Mutex<Vec<Dependency>>>,
+}
+
+/// The storage and events of one kind of asset
+struct Assets<T> {
+    storage: Arc<Mutex<AssetStorage<T>>>,
+    events: EventQueue<T>,
+}
+
+impl<T> Clone for Assets<T> {
+    fn clone(&self) -> Self {
+        Assets {
+            storage: self.storage.clone(),
+            events: self.events.clone(),
+        }
+    }
+}
+
+impl<T: AssetLoader> Assets<T> {
+    fn new() -> Assets<T> {
+        Assets {
+            storage: Arc::new(Mutex::new(AssetStorage::new())),
+            events: EventQueue::new(),
+        }
+    }
+
+    fn submit_load(
+        &self,
+        loader: &WorkerPool,
+        context: &LoadContext,
+        handle: Handle<T>,
+        asset: Arc<Mutex<T>>,
+        priority: LoadPriority,
+        reloading: bool,
+    ) {
+        let events = self.events.clone();
+        let context = context.clone();
+
+        loader.submit(priority, move || {
+            if !load_asset(&asset, &context) {
+                events.push(AssetEvent::Failed(handle));
+            } else if reloading {
+                events.push(AssetEvent::Reloaded(handle));
+            } else {
+                events.push(AssetEvent::Loaded(handle));
+            }
+        });
+    }
+}
+
+/// The parts of `Assets<T>` that don't depend on `T`, so every kind can be kept in one map
+trait AnyAssets: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    /// Start reloading every asset read from the file
+    fn reload_file(&self, path: &Path, loader: &WorkerPool, context: &LoadContext);
+}
+
+impl<T: AssetLoader> AnyAssets for Assets<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn reload_file(&self, path: &Path, loader: &WorkerPool, context: &LoadContext) {
+        let changed: Vec<(Handle<T>, Arc<Mutex<T>>)> = self
+            .storage
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, asset)| {
+                let asset = asset.lock().unwrap();
+                context.vfs.is_same_file(&asset.asset_info().id, path)
+            })
+            .map(|(handle, asset)| (handle, asset.clone()))
+            .collect();
+
+        for (handle, asset) in changed {
+            self.submit_load(loader, context, handle, asset, LoadPriority::High, true);
+        }
+    }
+}
+
+/// Read the asset and store the result, returning whether it succeeded
+/// The lock is only taken to read the id and store the result, not during IO and decoding
+/// When reloading, a failure keeps the last good version
+/// A loader that panics fails the load, instead of taking the loader thread down with it and
+/// poisoning the asset's lock
+fn load_asset<T: AssetLoader>(asset: &Mutex<T>, context: &LoadContext) -> bool {
+    let (id, settings) = {
+        let asset = asset.lock().unwrap();
+        (asset.asset_info().id.clone(), asset.settings())
+    };
+
+    let data = match T::supports(&id) {
+        true => panic::catch_unwind(AssertUnwindSafe(|| {
+            T::read(&id, &settings, &context.vfs).map(|mut data| {
+                let optimized = T::optimize(&mut data, &settings);
+                (data, optimized)
+            })
+        }))
+        .unwrap_or_else(|panic| {
+            Err(format!(
+                "{}: loader panicked: {}",
+                id,
+                panic_message(&*panic)
+            ))
+        }),
+        false => Err(format!("{}: no loader for this file type", id)),
+    };
+
+    let mut asset = asset.lock().unwrap();
+
+    let data = data.and_then(|(data, optimized)| {
+        let dependencies = T::dependencies(&data);
+
+        // The guard outlives the unwind, so a panicking store doesn't poison the lock either
+        panic::catch_unwind(AssertUnwindSafe(|| asset.store(data)))
+            .map(|()| (dependencies, optimized))
+            .map_err(|panic| format!("{}: loader panicked: {}", id, panic_message(&*panic)))
+    });
+
+    match data {
+        Ok((dependencies, optimized)) => {
+            context.dependencies.lock().unwrap().extend(dependencies);
+
+            asset.asset_info_mut().status = match optimized {
+                true => AssetStatus::Optimized,
+                false => AssetStatus::Loaded,
+            };
+
+            true
+        }
+        Err(e) if asset.asset_info().status != AssetStatus::Unloaded => {
+            warn!("Failed to reload asset, keeping last version: {}", e);
+            false
+        }
+        Err(e) => {
+            asset.asset_info_mut().status = AssetStatus::Invalid;
+
+            warn!("Failed to load asset: {}", e);
+            false
+        }
+    }
+}
+
+// The message a panic was started with, for the log
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => panic
+            .downcast_ref::<String>()
+            .map_or("unknown panic", String::as_str),
+    }
+}
 
 pub struct AssetManager {
-    meshes: Arc<Mutex<HashMap<String, Arc<Mutex<Mesh>>>>>,
-    sounds: Arc<Mutex<HashMap<String, Arc<Mutex<Sound>>>>>,
+    assets: HashMap<TypeId, Box<dyn AnyAssets>>,
+    loader: WorkerPool,
+    context: LoadContext,
+    watcher: Option<AssetWatcher>,
+    changed_files: Vec<PathBuf>,
 }
 
 impl AssetManager {
-    pub fn new() -> AssetManager {
+    pub fn new(config: &Config) -> AssetManager {
+        let watcher = if config.assets.hot_reload {
+            // Found like the directories they are mounted from, which they usually are
+            let roots: Vec<PathBuf> = config
+                .assets
+                .hot_reload_roots
+                .iter()
+                .map(|root| resolve_mount_path(root))
+                .collect();
+
+            match AssetWatcher::new(&roots) {
+                Ok(watcher) => Some(watcher),
+                Err(e) => {
+                    warn!("Hot reloading disabled: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         AssetManager {
-            meshes: Arc::new(Mutex::new(HashMap::new())),
-            sounds: Arc::new(Mutex::new(HashMap::new())),
+            assets: HashMap::new(),
+            loader: WorkerPool::new(config.assets.loader_threads),
+            context: LoadContext {
+                vfs: Arc::new(Vfs::from_config(config)),
+                dependencies: Arc::new(Mutex::new(vec![])),
+            },
+            watcher,
+            changed_files: vec![],
         }
     }
 
-    pub fn iter_meshes_mut(&mut self) -> Arc<Mutex<HashMap<String, Arc<Mutex<Mesh>>>>> {
-        self.meshes.clone()
-    }
+    /// Start loading the dependencies reported by finished loads,
+    /// and reloading any assets that changed on disk since the last update
+    pub fn update(&mut self) {
+        let dependencies = std::mem::take(&mut *self.context.dependencies.lock().unwrap());
 
-    pub fn get_mesh(&mut self, name: &String) -> Arc<Mutex<Mesh>> {
-        let existing = {
-            let meshes_binding = self.meshes.lock().unwrap();
+        for dependency in dependencies {
+            dependency.load(self);
+        }
 
-            meshes_binding.get(name).cloned()
+        let changed = match &self.watcher {
+            Some(watcher) => watcher.poll(),
+            None => return,
         };
 
-        match existing {
-            Some(mesh) => mesh,
-            None => self.insert_mesh(name),
+        for path in &changed {
+            for assets in self.assets.values() {
+                assets.reload_file(path, &self.loader, &self.context);
+            }
         }
+
+        self.changed_files.extend(changed);
     }
 
-    fn insert_mesh(&mut self, name: &str) -> Arc<Mutex<Mesh>> {
-        let asset_info = AssetInfo {
-            id: name.to_owned(),
-            status: asset_info::AssetStatus::Unloaded,
-        };
+    /// The virtual filesystem assets are read from, more directories and archives can be mounted
+    pub fn vfs(&self) -> &Vfs {
+        &self.context.vfs
+    }
 
-        let mesh_info = Mesh {
-            asset_info,
-            gpu_info: None,
-            vertices: vec![],
-            vertex_count: 0,
-        };
+    /// Take the canonical paths of every watched file that changed since the last call,
+    /// so resources the asset manager doesn't own (like shaders) can be reloaded too
+    pub fn take_changed_files(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.changed_files)
+    }
+
+    /// Get the handle for the asset with the id, loading it if it hasn't been requested before
+    pub fn load<T: AssetLoader>(&mut self, id: &str) -> Handle<T>
+    where
+        T::Settings: Default,
+    {
+        self.load_with_settings(id, T::Settings::default(), LoadPriority::Normal)
+    }
 
-        let mesh = Arc::new(Mutex::new(mesh_info));
+    /// Like `load`, the settings are only used if the asset hasn't been requested before
+    pub fn load_with_settings<T: AssetLoader>(
+        &mut self,
+        id: &str,
+        settings: T::Settings,
+        priority: LoadPriority,
+    ) -> Handle<T> {
+        let assets = self.assets_mut::<T>().clone();
 
-        self.meshes
+        let existing = assets.storage.lock().unwrap().handle(id);
+        if let Some(handle) = existing {
+            return handle;
+        }
+
+        let asset_info = AssetInfo {
+            id: id.to_owned(),
+            status: AssetStatus::Unloaded,
+        };
+
+        let (handle, asset) = assets
+            .storage
             .lock()
             .unwrap()
-            .insert(name.to_owned(), mesh.clone());
+            .insert(id, T::unloaded(asset_info, settings));
 
-        let closure_mesh = mesh.clone();
-        spawn(move || {
-            let mut mesh_binding = closure_mesh.lock().unwrap();
+        assets.submit_load(&self.loader, &self.context, handle, asset, priority, false);
 
-            mesh_binding.load();
-        });
+        handle
+    }
 
-        mesh.clone()
+    pub fn get<T: AssetLoader>(&self, handle: Handle<T>) -> Option<Arc<Mutex<T>>> {
+        self.assets::<T>()?.storage.lock().unwrap().get(handle)
     }
 
-    pub fn get_audio(&mut self, name: &String) -> Arc<Mutex<Sound>> {
-        let existing = {
-            let sounds_binding = self.sounds.lock().unwrap();
+    /// Every asset of the kind, in no particular order
+    pub fn all<T: AssetLoader>(&self) -> Vec<Arc<Mutex<T>>> {
+        match self.assets::<T>() {
+            Some(assets) => assets
+                .storage
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, asset)| asset.clone())
+                .collect(),
+            None => vec![],
+        }
+    }
 
-            sounds_binding.get(name).cloned()
-        };
+    /// Remove the asset, invalidating its handle
+    /// The caller is responsible for freeing any GPU data the asset still holds
+    pub fn unload<T: AssetLoader>(&mut self, handle: Handle<T>) -> Option<Arc<Mutex<T>>> {
+        let assets = self.assets::<T>()?;
+        let asset = assets.storage.lock().unwrap().remove(handle);
 
-        match existing {
-            Some(sound) => sound,
-            None => self.insert_audio(name),
+        if asset.is_some() {
+            assets.events.push(AssetEvent::Unloaded(handle));
         }
-    }
 
-    fn insert_audio(&mut self, name: &str) -> Arc<Mutex<Sound>> {
-        let asset_info = AssetInfo {
-            id: name.to_owned(),
-            status: asset_info::AssetStatus::Unloaded,
-        };
+        asset
+    }
 
-        let sound_info = Sound {
-            asset_info,
-            source: None,
-        };
+    /// Take every event of the kind published since the last call
+    pub fn drain_events<T: AssetLoader>(&mut self) -> Vec<AssetEvent<T>> {
+        match self.assets::<T>() {
+            Some(assets) => assets.events.drain(),
+            None => vec![],
+        }
+    }
 
-        let sound = Arc::new(Mutex::new(sound_info));
+    fn assets<T: AssetLoader>(&self) -> Option<&Assets<T>> {
+        self.assets
+            .get(&TypeId::of::<T>())
+            .and_then(|assets| assets.as_any().downcast_ref())
+    }
 
-        self.sounds
-            .lock()
+    fn assets_mut<T: AssetLoader>(&mut self) -> &Assets<T> {
+        self.assets
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Assets::<T>::new()))
+            .as_any()
+            .downcast_ref()
             .unwrap()
-            .insert(name.to_owned(), sound.clone());
+    }
 
-        let closure_sound = sound.clone();
-        spawn(move || {
-            let mut sound_binding = closure_sound.lock().unwrap();
+    /// Take every mesh event published since the last call
+    pub fn drain_mesh_events(&mut self) -> Vec<AssetEvent<Mesh>> {
+        self.drain_events()
+    }
 
-            sound_binding.load();
-        });
+    /// Take every sound event published since the last call
+    pub fn drain_audio_events(&mut self) -> Vec<AssetEvent<Sound>> {
+        self.drain_events()
+    }
+
+    /// Take every texture event published since the last call
+    pub fn drain_texture_events(&mut self) -> Vec<AssetEvent<Texture>> {
+        self.drain_events()
+    }
 
-        sound.clone()
+    pub fn meshes(&self) -> Vec<Arc<Mutex<Mesh>>> {
+        self.all()
+    }
+
+    /// Get the handle for the mesh at the path, loading it if it hasn't been requested before
+    pub fn load_mesh(&mut self, name: &str) -> Handle<Mesh> {
+        self.load(name)
+    }
+
+    pub fn load_mesh_with_priority(&mut self, name: &str, priority: LoadPriority) -> Handle<Mesh> {
+        self.load_with_settings(name, MeshSettings::default(), priority)
+    }
+
+    pub fn get_mesh(&self, handle: Handle<Mesh>) -> Option<Arc<Mutex<Mesh>>> {
+        self.get(handle)
+    }
+
+    /// What optimizing the mesh changed, `None` until it has been optimized
+    pub fn mesh_stats(&self, handle: Handle<Mesh>) -> Option<MeshStats> {
+        self.get_mesh(handle)
+            .and_then(|mesh| mesh.lock().unwrap().stats)
+    }
+
+    /// Remove the mesh, invalidating its handle
+    /// The caller is responsible for freeing any GPU data the mesh still holds
+    pub fn unload_mesh(&mut self, handle: Handle<Mesh>) -> Option<Arc<Mutex<Mesh>>> {
+        self.unload(handle)
+    }
+
+    /// Get the handle for the sound at the path, loading it if it hasn't been requested before
+    pub fn load_audio(&mut self, name: &str) -> Handle<Sound> {
+        self.load(name)
+    }
+
+    pub fn load_audio_with_priority(
+        &mut self,
+        name: &str,
+        priority: LoadPriority,
+    ) -> Handle<Sound> {
+        self.load_with_settings(name, (), priority)
+    }
+
+    pub fn get_audio(&self, handle: Handle<Sound>) -> Option<Arc<Mutex<Sound>>> {
+        self.get(handle)
+    }
+
+    pub fn unload_audio(&mut self, handle: Handle<Sound>) -> Option<Arc<Mutex<Sound>>> {
+        self.unload(handle)
+    }
+
+    /// Get the handle for the music at the path, loading it if it hasn't been requested before
+    /// Only its format is read, the music is decoded while it plays
+    pub fn load_music(&mut self, name: &str, settings: MusicSettings) -> Handle<Music> {
+        self.load_with_settings(name, settings, LoadPriority::Normal)
+    }
+
+    pub fn get_music(&self, handle: Handle<Music>) -> Option<Arc<Mutex<Music>>> {
+        self.get(handle)
+    }
+
+    pub fn unload_music(&mut self, handle: Handle<Music>) -> Option<Arc<Mutex<Music>>> {
+        self.unload(handle)
+    }
+
+    pub fn textures(&self) -> Vec<Arc<Mutex<Texture>>> {
+        self.all()
+    }
+
+    /// Get the handle for the texture at the path, loading it if it hasn't been requested before
+    /// PNG and JPEG colors are read in the given color space, KTX2 files record their own
+    pub fn load_texture(&mut self, name: &str, color_space: ColorSpace) -> Handle<Texture> {
+        self.load_texture_with_priority(name, color_space, LoadPriority::Normal)
+    }
+
+    pub fn load_texture_with_priority(
+        &mut self,
+        name: &str,
+        color_space: ColorSpace,
+        priority: LoadPriority,
+    ) -> Handle<Texture> {
+        self.load_with_settings(name, color_space, priority)
+    }
+
+    pub fn get_texture(&self, handle: Handle<Texture>) -> Option<Arc<Mutex<Texture>>> {
+        self.get(handle)
+    }
+
+    /// Remove the texture, invalidating its handle
+    /// The caller is responsible for freeing any GPU data the texture still holds
+    pub fn unload_texture(&mut self, handle: Handle<Texture>) -> Option<Arc<Mutex<Texture>>> {
+        self.unload(handle)
     }
 }
 
 impl Default for AssetManager {
     fn default() -> Self {
-        AssetManager::new()
+        AssetManager::new(&Config::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::test_support::temp_path;
+
+    fn wait_for_events<T: AssetLoader>(asset_manager: &mut AssetManager) -> Vec<AssetEvent<T>> {
+        let start = Instant::now();
+
+        loop {
+            let events = asset_manager.drain_events::<T>();
+
+            if !events.is_empty() || start.elapsed() > Duration::from_secs(5) {
+                return events;
+            }
+
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    // A custom asset: one item name per line, a line starting with `>` names another table
+    // and a line with only `!` makes the loader panic
+    struct ItemTable {
+        asset_info: AssetInfo,
+        items: Vec<String>,
+    }
+
+    impl AssetLoader for ItemTable {
+        type Settings = ();
+        type Data = String;
+
+        const EXTENSIONS: &'static [&'static str] = &["items"];
+
+        fn unloaded(asset_info: AssetInfo, _settings: ()) -> Self {
+            ItemTable {
+                asset_info,
+                items: vec![],
+            }
+        }
+
+        fn asset_info(&self) -> &AssetInfo {
+            &self.asset_info
+        }
+
+        fn asset_info_mut(&mut self) -> &mut AssetInfo {
+            &mut self.asset_info
+        }
+
+        fn settings(&self) {}
+
+        fn read(id: &str, _settings: &(), vfs: &Vfs) -> Result<String, String> {
+            let data = vfs.read_to_string(id)?;
+            assert!(!data.lines().any(|line| line == "!"), "broken table");
+
+            Ok(data)
+        }
+
+        fn store(&mut self, data: String) {
+            self.items = data
+                .lines()
+                .filter(|line| !line.starts_with('>'))
+                .map(|line| line.to_owned())
+                .collect();
+        }
+
+        fn dependencies(data: &String) -> Vec<Dependency> {
+            data.lines()
+                .filter_map(|line| line.strip_prefix('>'))
+                .map(Dependency::new::<ItemTable>)
+                .collect()
+        }
+    }
+
+    fn write_table(name: &str, contents: &str) -> String {
+        let path = temp_path(&format!("{}.items", name));
+        std::fs::write(&path, contents).unwrap();
+
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_missing_mesh_publishes_failed() {
+        let mut asset_manager = AssetManager::default();
+
+        let handle = asset_manager.load_mesh("does/not/exist.glb");
+
+        assert_eq!(
+            wait_for_events::<Mesh>(&mut asset_manager),
+            vec![AssetEvent::Failed(handle)]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_extension_fails() {
+        let mut asset_manager = AssetManager::default();
+
+        let handle = asset_manager.load::<Sound>("music.txt");
+
+        assert_eq!(
+            wait_for_events::<Sound>(&mut asset_manager),
+            vec![AssetEvent::Failed(handle)]
+        );
+        let sound = asset_manager.get_audio(handle).unwrap();
+        assert_eq!(
+            sound.lock().unwrap().asset_info.status,
+            AssetStatus::Invalid
+        );
+    }
+
+    #[test]
+    fn test_unload_publishes_unloaded() {
+        let mut asset_manager = AssetManager::default();
+
+        let handle = asset_manager.load_mesh("does/not/exist.glb");
+        wait_for_events::<Mesh>(&mut asset_manager);
+
+        asset_manager.unload_mesh(handle);
+
+        assert_eq!(
+            asset_manager.drain_mesh_events(),
+            vec![AssetEvent::Unloaded(handle)]
+        );
+        assert!(asset_manager.unload_mesh(handle).is_none());
+        assert!(asset_manager.drain_mesh_events().is_empty());
+    }
+
+    #[test]
+    fn test_load_custom_asset() {
+        let mut asset_manager = AssetManager::default();
+        let path = write_table("custom", "sword\nshield");
+
+        let handle = asset_manager.load::<ItemTable>(&path);
+
+        assert_eq!(
+            wait_for_events::<ItemTable>(&mut asset_manager),
+            vec![AssetEvent::Loaded(handle)]
+        );
+        assert_eq!(asset_manager.load::<ItemTable>(&path), handle);
+
+        let table = asset_manager.get(handle).unwrap();
+        assert_eq!(table.lock().unwrap().items, vec!["sword", "shield"]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_panicking_loader_fails_the_asset() {
+        let mut asset_manager = AssetManager::default();
+        let broken = write_table("broken", "sword\n!");
+        let working = write_table("working", "shield");
+
+        let handle = asset_manager.load::<ItemTable>(&broken);
+        assert_eq!(
+            wait_for_events::<ItemTable>(&mut asset_manager),
+            vec![AssetEvent::Failed(handle)]
+        );
+        let table = asset_manager.get(handle).unwrap();
+        assert_eq!(
+            table.lock().unwrap().asset_info.status,
+            AssetStatus::Invalid
+        );
+
+        // The loader threads are still there for the next load
+        let handle = asset_manager.load::<ItemTable>(&working);
+        assert_eq!(
+            wait_for_events::<ItemTable>(&mut asset_manager),
+            vec![AssetEvent::Loaded(handle)]
+        );
+
+        std::fs::remove_file(broken).unwrap();
+        std::fs::remove_file(working).unwrap();
+    }
+
+    #[test]
+    fn test_update_loads_dependencies() {
+        let mut asset_manager = AssetManager::default();
+        let potions = write_table("potions", "healing potion");
+        let shop = write_table("shop", &format!("sword\n>{}", potions));
+
+        let handle = asset_manager.load::<ItemTable>(&shop);
+        wait_for_events::<ItemTable>(&mut asset_manager);
+        assert_eq!(asset_manager.all::<ItemTable>().len(), 1);
+
+        asset_manager.update();
+
+        let dependency = asset_manager.load::<ItemTable>(&potions);
+        assert_ne!(dependency, handle);
+        assert_eq!(
+            wait_for_events::<ItemTable>(&mut asset_manager),
+            vec![AssetEvent::Loaded(dependency)]
+        );
+
+        std::fs::remove_file(shop).unwrap();
+        std::fs::remove_file(potions).unwrap();
     }
 }
@@ -0,0 +1,196 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{gltf_import::percent_decode, vfs::relative_id, ColorSpace};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlphaMode {
+    Opaque,
+    /// Fully opaque or fully transparent, depending on the alpha cutoff
+    Mask,
+    Blend,
+}
+
+/// A texture a material samples, the renderer loads it through the asset manager
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextureRef {
+    pub id: String,
+    pub color_space: ColorSpace,
+}
+
+/// A glTF metallic-roughness material, with the defaults the glTF spec gives missing values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PbrMaterial {
+    pub name: Option<String>,
+    pub base_color_factor: glm::Vec4,
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// Metallic in the blue channel, roughness in the green channel
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub normal_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    /// Occlusion in the red channel
+    pub occlusion_texture: Option<TextureRef>,
+    pub emissive_factor: glm::Vec3,
+    pub emissive_texture: Option<TextureRef>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+impl PbrMaterial {
+    /// Read a material from the glTF file at `path`, textures are referenced but not loaded
+    pub fn from_gltf(path: &str, material: &gltf::Material) -> PbrMaterial {
+        let pbr = material.pbr_metallic_roughness();
+
+        let texture = |texture: gltf::Texture, color_space| TextureRef {
+            id: PbrMaterial::gltf_image_id(path, &texture.source()),
+            color_space,
+        };
+
+        PbrMaterial {
+            name: material.name().map(|name| name.to_owned()),
+            base_color_factor: glm::Vec4::from(pbr.base_color_factor()),
+            base_color_texture: pbr
+                .base_color_texture()
+                .map(|info| texture(info.texture(), ColorSpace::Srgb)),
+            metallic_factor: pbr.metallic_factor(),
+            roughness_factor: pbr.roughness_factor(),
+            metallic_roughness_texture: pbr
+                .metallic_roughness_texture()
+                .map(|info| texture(info.texture(), ColorSpace::Linear)),
+            normal_scale: material
+                .normal_texture()
+                .map_or(1.0, |normal| normal.scale()),
+            normal_texture: material
+                .normal_texture()
+                .map(|normal| texture(normal.texture(), ColorSpace::Linear)),
+            occlusion_strength: material
+                .occlusion_texture()
+                .map_or(1.0, |occlusion| occlusion.strength()),
+            occlusion_texture: material
+                .occlusion_texture()
+                .map(|occlusion| texture(occlusion.texture(), ColorSpace::Linear)),
+            emissive_factor: glm::Vec3::from(material.emissive_factor()),
+            emissive_texture: material
+                .emissive_texture()
+                .map(|info| texture(info.texture(), ColorSpace::Srgb)),
+            alpha_mode: match material.alpha_mode() {
+                gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+                gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+            },
+            alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+            double_sided: material.double_sided(),
+        }
+    }
+
+    /// Every texture the material samples
+    pub fn textures(&self) -> impl Iterator<Item = &TextureRef> {
+        [
+            &self.base_color_texture,
+            &self.metallic_roughness_texture,
+            &self.normal_texture,
+            &self.occlusion_texture,
+            &self.emissive_texture,
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// The texture id of a glTF image, a file next to the glTF or an image embedded in it
+    pub fn gltf_image_id(path: &str, image: &gltf::Image) -> String {
+        match image.source() {
+            gltf::image::Source::Uri { uri, .. } if !uri.starts_with("data:") => {
+                relative_id(path, &percent_decode(uri))
+            }
+            _ => format!("{}#image{}", path, image.index()),
+        }
+    }
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        PbrMaterial {
+            name: None,
+            base_color_factor: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_scale: 1.0,
+            normal_texture: None,
+            occlusion_strength: 1.0,
+            occlusion_texture: None,
+            emissive_factor: glm::Vec3::zeros(),
+            emissive_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            double_sided: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MATERIALS: &str = r#"{
+        "asset": { "version": "2.0" },
+        "images": [
+            { "uri": "textures/color.png" },
+            { "uri": "data:image/png;base64,AAAA" }
+        ],
+        "textures": [{ "source": 0 }, { "source": 1 }],
+        "materials": [
+            {
+                "name": "bottle",
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [1.0, 0.5, 0.25, 1.0],
+                    "baseColorTexture": { "index": 0 },
+                    "metallicFactor": 0.0
+                },
+                "normalTexture": { "index": 1, "scale": 0.5 },
+                "alphaMode": "MASK",
+                "doubleSided": true
+            },
+            {}
+        ]
+    }"#;
+
+    #[test]
+    fn test_from_gltf() {
+        let gltf = gltf::Gltf::from_slice(MATERIALS.as_bytes()).unwrap();
+        let materials: Vec<PbrMaterial> = gltf
+            .materials()
+            .map(|material| PbrMaterial::from_gltf("models/bottle.gltf", &material))
+            .collect();
+
+        let bottle = &materials[0];
+        assert_eq!(bottle.name.as_deref(), Some("bottle"));
+        assert_eq!(bottle.base_color_factor, glm::vec4(1.0, 0.5, 0.25, 1.0));
+        assert_eq!(
+            bottle.base_color_texture,
+            Some(TextureRef {
+                id: "models/textures/color.png".to_owned(),
+                color_space: ColorSpace::Srgb,
+            })
+        );
+        assert_eq!(bottle.metallic_factor, 0.0);
+        assert_eq!(bottle.roughness_factor, 1.0);
+        assert_eq!(bottle.normal_scale, 0.5);
+        assert_eq!(
+            bottle.normal_texture,
+            Some(TextureRef {
+                id: "models/bottle.gltf#image1".to_owned(),
+                color_space: ColorSpace::Linear,
+            })
+        );
+        assert_eq!(bottle.alpha_mode, AlphaMode::Mask);
+        assert_eq!(bottle.alpha_cutoff, 0.5);
+        assert!(bottle.double_sided);
+
+        assert_eq!(materials[1], PbrMaterial::default());
+    }
+}
@@ -0,0 +1,170 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// An axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Aabb {
+    pub min: glm::Vec3,
+    pub max: glm::Vec3,
+}
+
+impl Aabb {
+    /// A box around nothing, growing it by a point gives a box around just that point
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: glm::Vec3::repeat(f32::INFINITY),
+            max: glm::Vec3::repeat(f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn grow(&mut self, point: &glm::Vec3) {
+        self.min = glm::min2(&self.min, point);
+        self.max = glm::max2(&self.max, point);
+    }
+
+    /// The smallest box around both boxes
+    pub fn merge(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: glm::min2(&self.min, &other.min),
+            max: glm::max2(&self.max, &other.max),
+        }
+    }
+
+    pub fn center(&self) -> glm::Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Half the size of the box along each axis
+    pub fn half_extents(&self) -> glm::Vec3 {
+        (self.max - self.min) / 2.0
+    }
+
+    pub fn contains(&self, point: &glm::Vec3) -> bool {
+        (0..3).all(|axis| self.min[axis] <= point[axis] && point[axis] <= self.max[axis])
+    }
+
+    /// The axis aligned box around this box after transforming it
+    /// Rotations make it larger than a box around the transformed contents would be
+    pub fn transformed(&self, matrix: &glm::Mat4) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+
+        let center = (matrix * self.center().push(1.0)).xyz();
+        // Each axis of the result reaches as far as the transformed half extents do along it
+        let rotation_scale = matrix.fixed_view::<3, 3>(0, 0).abs();
+        let half_extents = rotation_scale * self.half_extents();
+
+        Aabb {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb::empty()
+    }
+}
+
+/// A sphere around something, cheaper than a box to test against a view frustum
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingSphere {
+    pub center: glm::Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// A sphere around the points, centered on their bounding box
+    /// Not the smallest sphere, but close to it for most meshes and quick to find
+    pub fn from_points(points: &[glm::Vec3]) -> BoundingSphere {
+        let mut aabb = Aabb::empty();
+        for point in points {
+            aabb.grow(point);
+        }
+
+        if aabb.is_empty() {
+            return BoundingSphere::default();
+        }
+
+        let center = aabb.center();
+        let radius = points
+            .iter()
+            .map(|point| glm::distance(&center, point))
+            .fold(0.0, f32::max);
+
+        BoundingSphere { center, radius }
+    }
+
+    /// The sphere around this sphere after transforming it, scaled by the largest axis scale
+    pub fn transformed(&self, matrix: &glm::Mat4) -> BoundingSphere {
+        let scale = (0..3)
+            .map(|axis| matrix.fixed_view::<3, 1>(0, axis).norm())
+            .fold(0.0, f32::max);
+
+        BoundingSphere {
+            center: (matrix * self.center.push(1.0)).xyz(),
+            radius: self.radius * scale,
+        }
+    }
+
+    pub fn contains(&self, point: &glm::Vec3) -> bool {
+        glm::distance(&self.center, point) <= self.radius
+    }
+}
+
+impl Default for BoundingSphere {
+    fn default() -> Self {
+        BoundingSphere {
+            center: glm::Vec3::zeros(),
+            radius: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aabb_transformed() {
+        let aabb = Aabb {
+            min: glm::vec3(-1.0, -2.0, -3.0),
+            max: glm::vec3(1.0, 2.0, 3.0),
+        };
+
+        let moved = aabb.transformed(&glm::translation(&glm::vec3(10.0, 0.0, 0.0)));
+        assert_eq!(moved.min, glm::vec3(9.0, -2.0, -3.0));
+        assert_eq!(moved.max, glm::vec3(11.0, 2.0, 3.0));
+
+        // A quarter turn around z swaps the x and y extents
+        let rotated = aabb.transformed(&glm::rotation(
+            std::f32::consts::FRAC_PI_2,
+            &glm::vec3(0.0, 0.0, 1.0),
+        ));
+        assert!((rotated.max - glm::vec3(2.0, 1.0, 3.0)).norm() < 1e-5);
+
+        assert!(Aabb::empty().transformed(&glm::Mat4::identity()).is_empty());
+    }
+
+    #[test]
+    fn test_bounding_sphere() {
+        let points = [
+            glm::vec3(-1.0, 0.0, 0.0),
+            glm::vec3(1.0, 0.0, 0.0),
+            glm::vec3(0.0, 0.5, 0.0),
+        ];
+
+        let sphere = BoundingSphere::from_points(&points);
+        assert_eq!(sphere.center, glm::vec3(0.0, 0.25, 0.0));
+        assert!(points.iter().all(|point| sphere.contains(point)));
+
+        let scaled = sphere.transformed(&glm::scaling(&glm::vec3(1.0, 3.0, 1.0)));
+        assert_eq!(scaled.center, glm::vec3(0.0, 0.75, 0.0));
+        assert_eq!(scaled.radius, sphere.radius * 3.0);
+    }
+}
@@ -0,0 +1,325 @@
+use std::mem::size_of;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{geometry::Geometry, Aabb, Indices, SubMesh, Vertex};
+use crate::PbrMaterial;
+
+const MAGIC: &[u8; 4] = b"RDMS";
+/// Bumped whenever the layout or the meaning of the data changes, older files are then recooked
+pub const FORMAT_VERSION: u32 = 4;
+const HEADER_SIZE: usize = 72;
+const SECTION_ALIGNMENT: usize = 16;
+
+#[derive(Serialize, Deserialize)]
+struct Metadata {
+    bounds: Aabb,
+    sub_meshes: Vec<SubMesh>,
+    materials: Vec<PbrMaterial>,
+}
+
+/// A mesh preprocessed into a binary file, so loading it only has to copy bytes around
+///
+/// The header is followed by the bincode encoded bounds, sub-meshes and materials,
+/// then the vertex data exactly as `Vertex` is laid out in memory, then the 16 or 32 bit
+/// index data. Both are aligned to 16 bytes from the start of the file, so a memory mapped
+/// file can be copied into a GPU buffer as is. Everything is little endian, vertices are
+/// copied from memory as they are, so cooking assumes a little endian machine.
+///
+/// | Offset | Size | Value |
+/// |---|---|---|
+/// | 0 | 4 | `RDMS` |
+/// | 4 | 4 | format version |
+/// | 8 | 8 | source hash |
+/// | 16 | 4 | vertex size |
+/// | 20 | 4 | index size, 2 or 4 |
+/// | 24 | 16 | metadata offset and length |
+/// | 40 | 16 | vertex data offset and count |
+/// | 56 | 16 | index data offset and count |
+pub struct CookedMesh<'a> {
+    bytes: &'a [u8],
+    source_hash: u64,
+    index_size: u32,
+    metadata: (usize, usize),
+    vertices: (usize, usize),
+    indices: (usize, usize),
+}
+
+impl<'a> CookedMesh<'a> {
+    /// Check the header and that every section is inside the data, without reading them
+    pub fn parse(bytes: &'a [u8]) -> Result<CookedMesh<'a>, String> {
+        if bytes.len() < HEADER_SIZE || &bytes[0..4] != MAGIC {
+            return Err("not a cooked mesh".to_owned());
+        }
+
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+        let version = u32_at(4);
+        if version != FORMAT_VERSION {
+            return Err(format!(
+                "cooked with format version {}, expected {}",
+                version, FORMAT_VERSION
+            ));
+        }
+
+        let vertex_size = u32_at(16);
+        if vertex_size as usize != size_of::<Vertex>() {
+            return Err(format!("cooked with {} byte vertices", vertex_size));
+        }
+
+        let index_size = u32_at(20);
+        if index_size != 2 && index_size != 4 {
+            return Err(format!("invalid index size {}", index_size));
+        }
+
+        // Sections as byte ranges, checked against the size of the data
+        let section = |offset: usize, element_size: usize| {
+            let start = u64_at(offset) as usize;
+            let length = (u64_at(offset + 8) as usize).checked_mul(element_size)?;
+            let end = start.checked_add(length)?;
+
+            match end <= bytes.len() {
+                true => Some((start, length)),
+                false => None,
+            }
+        };
+
+        match (
+            section(24, 1),
+            section(40, vertex_size as usize),
+            section(56, index_size as usize),
+        ) {
+            (Some(metadata), Some(vertices), Some(indices)) => Ok(CookedMesh {
+                bytes,
+                source_hash: u64_at(8),
+                index_size,
+                metadata,
+                vertices,
+                indices,
+            }),
+            _ => Err("truncated cooked mesh".to_owned()),
+        }
+    }
+
+    /// The hash of the files the mesh was cooked from, see `hash_source`
+    pub fn source_hash(&self) -> u64 {
+        self.source_hash
+    }
+
+    /// The vertex data, laid out like `Vertex`
+    pub fn vertex_bytes(&self) -> &'a [u8] {
+        &self.bytes[self.vertices.0..self.vertices.0 + self.vertices.1]
+    }
+
+    pub fn index_bytes(&self) -> &'a [u8] {
+        &self.bytes[self.indices.0..self.indices.0 + self.indices.1]
+    }
+
+    /// 2 for 16 bit indices, 4 for 32 bit indices
+    pub fn index_size(&self) -> u32 {
+        self.index_size
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.1 / size_of::<Vertex>()
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.indices.1 / self.index_size as usize
+    }
+
+    pub(crate) fn to_geometry(&self) -> Result<Geometry, String> {
+        let metadata_bytes = &self.bytes[self.metadata.0..self.metadata.0 + self.metadata.1];
+
+        let metadata: Metadata = match bincode::deserialize(metadata_bytes) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                return Err("Failed to read cooked mesh metadata: ".to_owned() + &e.to_string())
+            }
+        };
+
+        let mut vertices = vec![Vertex::default(); self.vertex_count()];
+        let vertex_bytes = self.vertex_bytes();
+
+        // Vertex is repr(C) and made of f32s only, so any bytes of the right length are valid
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                vertex_bytes.as_ptr(),
+                vertices.as_mut_ptr() as *mut u8,
+                vertex_bytes.len(),
+            );
+        }
+
+        let indices = match self.index_size {
+            2 => self
+                .index_bytes()
+                .chunks_exact(2)
+                .map(|index| u16::from_le_bytes([index[0], index[1]]) as u32)
+                .collect(),
+            _ => self
+                .index_bytes()
+                .chunks_exact(4)
+                .map(|index| u32::from_le_bytes(index.try_into().unwrap()))
+                .collect(),
+        };
+
+        Ok(Geometry {
+            vertices,
+            indices,
+            sub_meshes: metadata.sub_meshes,
+            materials: metadata.materials,
+            bounds: metadata.bounds,
+            stats: None,
+            lods: vec![],
+        })
+    }
+}
+
+/// Encode the geometry in the cooked format, see `CookedMesh`
+pub fn cook(geometry: &Geometry, source_hash: u64) -> Vec<u8> {
+    let metadata = Metadata {
+        bounds: geometry.bounds,
+        sub_meshes: geometry.sub_meshes.clone(),
+        materials: geometry.materials.clone(),
+    };
+    let metadata_bytes = bincode::serialize(&metadata).unwrap();
+
+    // Vertex is repr(C) and made of f32s only, so it has no padding bytes
+    let vertex_bytes = unsafe {
+        std::slice::from_raw_parts(
+            geometry.vertices.as_ptr() as *const u8,
+            std::mem::size_of_val(geometry.vertices.as_slice()),
+        )
+    };
+
+    let (index_size, index_bytes): (u32, Vec<u8>) = match Indices::new(
+        geometry.indices.clone(),
+        geometry.max_sub_mesh_vertex_count(),
+    ) {
+        Indices::U16(indices) => (
+            2,
+            indices
+                .iter()
+                .flat_map(|index| index.to_le_bytes())
+                .collect(),
+        ),
+        Indices::U32(indices) => (
+            4,
+            indices
+                .iter()
+                .flat_map(|index| index.to_le_bytes())
+                .collect(),
+        ),
+    };
+
+    let metadata_offset = HEADER_SIZE;
+    let vertex_offset = align(metadata_offset + metadata_bytes.len());
+    let index_offset = align(vertex_offset + vertex_bytes.len());
+
+    let mut bytes = Vec::with_capacity(index_offset + index_bytes.len());
+    bytes.extend(MAGIC);
+    bytes.extend(FORMAT_VERSION.to_le_bytes());
+    bytes.extend(source_hash.to_le_bytes());
+    bytes.extend((size_of::<Vertex>() as u32).to_le_bytes());
+    bytes.extend(index_size.to_le_bytes());
+
+    for (offset, count) in [
+        (metadata_offset, metadata_bytes.len()),
+        (vertex_offset, geometry.vertices.len()),
+        (index_offset, geometry.indices.len()),
+    ] {
+        bytes.extend((offset as u64).to_le_bytes());
+        bytes.extend((count as u64).to_le_bytes());
+    }
+
+    bytes.extend(&metadata_bytes);
+    bytes.resize(vertex_offset, 0);
+    bytes.extend(vertex_bytes);
+    bytes.resize(index_offset, 0);
+    bytes.extend(&index_bytes);
+
+    bytes
+}
+
+fn align(offset: usize) -> usize {
+    offset.div_ceil(SECTION_ALIGNMENT) * SECTION_ALIGNMENT
+}
+
+/// A 64 bit FNV-1a hash of the source file, which stays the same across platforms and builds
+pub fn hash_source(bytes: &[u8]) -> u64 {
+    hash_reference(0xcbf29ce484222325, bytes)
+}
+
+/// Continue the hash over a file the source references, like a glTF buffer
+pub fn hash_reference(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Where the cooked version of a mesh is kept, one file per id
+pub fn cache_id(id: &str) -> String {
+    format!("cache://meshes/{:016x}.rdmesh", hash_source(id.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> Geometry {
+        let mut geometry = Geometry::default();
+        let vertices = (0..3)
+            .map(|i| Vertex {
+                position: glm::vec3(i as f32, 1.0, -2.0),
+                uv0: glm::vec2(0.5, i as f32),
+                ..Default::default()
+            })
+            .collect();
+
+        geometry.push(vertices, vec![0, 1, 2], Some(0), glm::Mat4::identity());
+        geometry.materials.push(PbrMaterial::default());
+
+        geometry
+    }
+
+    #[test]
+    fn test_cook_round_trip() {
+        let geometry = triangle();
+
+        let bytes = cook(&geometry, 42);
+        let cooked = CookedMesh::parse(&bytes).unwrap();
+
+        assert_eq!(cooked.source_hash(), 42);
+        assert_eq!(cooked.vertex_count(), 3);
+        assert_eq!(cooked.index_size(), 2);
+        assert_eq!(cooked.index_bytes(), [0, 0, 1, 0, 2, 0]);
+        assert_eq!(cooked.vertices.0 % SECTION_ALIGNMENT, 0);
+        assert_eq!(cooked.indices.0 % SECTION_ALIGNMENT, 0);
+
+        let read = cooked.to_geometry().unwrap();
+        assert_eq!(read.vertices, geometry.vertices);
+        assert_eq!(read.indices, geometry.indices);
+        assert_eq!(read.bounds, geometry.bounds);
+        assert_eq!(read.materials, geometry.materials);
+        assert_eq!(read.sub_meshes[0].material, Some(0));
+    }
+
+    #[test]
+    fn test_parse_rejects_other_versions() {
+        let mut bytes = cook(&triangle(), 0);
+        bytes[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+
+        assert!(CookedMesh::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_truncated() {
+        let bytes = cook(&triangle(), 0);
+
+        assert!(CookedMesh::parse(&bytes[..bytes.len() - 1]).is_err());
+        assert!(CookedMesh::parse(&bytes[..HEADER_SIZE - 1]).is_err());
+    }
+}
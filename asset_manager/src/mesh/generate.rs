@@ -0,0 +1,128 @@
+use log::warn;
+
+use super::{deduplicate_vertices, Vertex};
+
+/// Give every triangle the normal of its face, for meshes that come without normals
+/// Corners are split per triangle first, then merged again where they end up identical
+pub fn generate_flat_normals(vertices: &[Vertex], indices: &[u32]) -> (Vec<Vertex>, Vec<u32>) {
+    let mut corners = unweld(vertices, indices);
+
+    for triangle in corners.chunks_exact_mut(3) {
+        let edge1 = triangle[1].position - triangle[0].position;
+        let edge2 = triangle[2].position - triangle[0].position;
+
+        // Degenerate triangles get a zero normal rather than NaNs
+        let normal = edge1
+            .cross(&edge2)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(glm::Vec3::zeros);
+
+        for corner in triangle {
+            corner.normal = normal;
+        }
+    }
+
+    deduplicate_vertices(&corners)
+}
+
+/// Generate MikkTSpace tangents from the normals and first texture coordinates
+/// Like `generate_flat_normals`, corners are split and merged again since tangents may differ
+/// between triangles sharing a vertex
+pub fn generate_tangents(vertices: &[Vertex], indices: &[u32]) -> (Vec<Vertex>, Vec<u32>) {
+    let mut corners = unweld(vertices, indices);
+
+    if !mikktspace::generate_tangents(&mut Triangles(&mut corners)) {
+        warn!("Failed to generate tangents, keeping the defaults");
+    }
+
+    deduplicate_vertices(&corners)
+}
+
+// Every corner of every triangle as its own vertex
+fn unweld(vertices: &[Vertex], indices: &[u32]) -> Vec<Vertex> {
+    let triangle_corners = indices.len() - indices.len() % 3;
+
+    indices[..triangle_corners]
+        .iter()
+        .map(|&index| vertices[index as usize])
+        .collect()
+}
+
+// An unwelded triangle list, as MikkTSpace reads and writes it
+struct Triangles<'a>(&'a mut [Vertex]);
+
+impl mikktspace::Geometry for Triangles<'_> {
+    fn num_faces(&self) -> usize {
+        self.0.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.0[face * 3 + vert].position.into()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.0[face * 3 + vert].normal.into()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.0[face * 3 + vert].uv0.into()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.0[face * 3 + vert].tangent = tangent.into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A unit quad facing +Z, with texture coordinates following the positions
+    fn quad() -> (Vec<Vertex>, Vec<u32>) {
+        let vertices = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+            .iter()
+            .map(|&(x, y)| Vertex {
+                position: glm::vec3(x, y, 0.0),
+                normal: glm::vec3(0.0, 0.0, 1.0),
+                uv0: glm::vec2(x, y),
+                ..Default::default()
+            })
+            .collect();
+
+        (vertices, vec![0, 1, 2, 2, 3, 0])
+    }
+
+    #[test]
+    fn test_generate_flat_normals() {
+        let (mut vertices, indices) = quad();
+        for vertex in &mut vertices {
+            vertex.normal = glm::Vec3::zeros();
+        }
+
+        let (vertices, indices) = generate_flat_normals(&vertices, &indices);
+
+        // Both triangles face the same way, so the shared corners merge again
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(indices.len(), 6);
+        for vertex in &vertices {
+            assert_eq!(vertex.normal, glm::vec3(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn test_generate_tangents() {
+        let (vertices, indices) = quad();
+
+        let (vertices, indices) = generate_tangents(&vertices, &indices);
+
+        assert_eq!(indices.len(), 6);
+        for vertex in &vertices {
+            assert!((vertex.tangent.xyz() - glm::vec3(1.0, 0.0, 0.0)).norm() < 1e-5);
+            assert_eq!(vertex.tangent.w.abs(), 1.0);
+        }
+    }
+}
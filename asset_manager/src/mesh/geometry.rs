@@ -0,0 +1,178 @@
+use std::ops::Range;
+
+use super::{Aabb, BoundingSphere, MeshLod, MeshStats, SubMesh, Vertex};
+use crate::PbrMaterial;
+
+/// Vertex and index data being collected for a mesh, one sub-mesh at a time
+#[derive(Default)]
+pub struct Geometry {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub sub_meshes: Vec<SubMesh>,
+    /// The materials the sub-meshes' material slots index into
+    pub materials: Vec<PbrMaterial>,
+    /// Around every sub-mesh, with their transforms applied
+    pub bounds: Aabb,
+    /// What optimizing changed, `None` until the geometry is optimized
+    pub stats: Option<MeshStats>,
+    /// Simplified versions of the sub-meshes, their indices follow the full detail ones
+    pub lods: Vec<MeshLod>,
+}
+
+impl Geometry {
+    /// Append a sub-mesh, its indices stay relative to its own vertices
+    pub fn push(
+        &mut self,
+        vertices: Vec<Vertex>,
+        indices: Vec<u32>,
+        material: Option<usize>,
+        transform: glm::Mat4,
+    ) {
+        self.push_part(vertices, indices, material, transform, 0);
+    }
+
+    /// Append a sub-mesh of one of the file's meshes, see `SubMesh::part`
+    pub fn push_part(
+        &mut self,
+        vertices: Vec<Vertex>,
+        indices: Vec<u32>,
+        material: Option<usize>,
+        transform: glm::Mat4,
+        part: usize,
+    ) {
+        let points: Vec<glm::Vec3> = vertices
+            .iter()
+            .map(|vertex| (transform * vertex.position.push(1.0)).xyz())
+            .collect();
+
+        let mut bounds = Aabb::empty();
+        for point in &points {
+            bounds.grow(point);
+        }
+        self.bounds = self.bounds.merge(&bounds);
+
+        self.sub_meshes.push(SubMesh {
+            first_index: self.indices.len() as u32,
+            index_count: indices.len() as u32,
+            vertex_offset: self.vertices.len() as i32,
+            material,
+            transform,
+            bounds,
+            bounding_sphere: BoundingSphere::from_points(&points),
+            part,
+        });
+
+        self.vertices.extend(vertices);
+        self.indices.extend(indices);
+    }
+
+    /// A sphere around every sub-mesh, with their transforms applied
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        let points: Vec<glm::Vec3> = self
+            .sub_meshes
+            .iter()
+            .zip(self.sub_mesh_vertex_ranges())
+            .flat_map(|(sub_mesh, range)| {
+                self.vertices[range]
+                    .iter()
+                    .map(|vertex| (sub_mesh.transform * vertex.position.push(1.0)).xyz())
+            })
+            .collect();
+
+        BoundingSphere::from_points(&points)
+    }
+
+    /// The part of `vertices` each sub-mesh's indices address, in sub-mesh order
+    pub fn sub_mesh_vertex_ranges(&self) -> Vec<Range<usize>> {
+        let mut vertex_offsets: Vec<usize> = self
+            .sub_meshes
+            .iter()
+            .map(|sub_mesh| sub_mesh.vertex_offset as usize)
+            .collect();
+        vertex_offsets.push(self.vertices.len());
+
+        vertex_offsets
+            .windows(2)
+            .map(|offsets| offsets[0]..offsets[1])
+            .collect()
+    }
+
+    /// The most vertices any one sub-mesh addresses, which decides the index size
+    pub fn max_sub_mesh_vertex_count(&self) -> usize {
+        self.sub_mesh_vertex_ranges()
+            .iter()
+            .map(|range| range.len())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertices(count: usize) -> Vec<Vertex> {
+        vec![Vertex::default(); count]
+    }
+
+    #[test]
+    fn test_push_offsets_sub_meshes() {
+        let mut geometry = Geometry::default();
+
+        geometry.push(vertices(3), vec![0, 1, 2], Some(0), glm::Mat4::identity());
+        geometry.push(
+            vertices(4),
+            vec![0, 1, 2, 2, 3, 0],
+            None,
+            glm::Mat4::identity(),
+        );
+
+        assert_eq!(geometry.vertices.len(), 7);
+        assert_eq!(geometry.indices.len(), 9);
+
+        let second = geometry.sub_meshes[1];
+        assert_eq!(second.first_index, 3);
+        assert_eq!(second.index_count, 6);
+        assert_eq!(second.vertex_offset, 3);
+        assert_eq!(second.material, None);
+    }
+
+    #[test]
+    fn test_push_grows_bounds() {
+        let mut geometry = Geometry::default();
+        assert!(geometry.bounds.is_empty());
+
+        let mut corners = vertices(2);
+        corners[1].position = glm::vec3(1.0, 2.0, 3.0);
+
+        geometry.push(corners.clone(), vec![], None, glm::Mat4::identity());
+        geometry.push(
+            corners,
+            vec![],
+            None,
+            glm::translation(&glm::vec3(-1.0, 0.0, 0.0)),
+        );
+
+        assert_eq!(geometry.bounds.min, glm::vec3(-1.0, 0.0, 0.0));
+        assert_eq!(geometry.bounds.max, glm::vec3(1.0, 2.0, 3.0));
+        assert_eq!(geometry.sub_meshes[1].bounds.min, glm::vec3(-1.0, 0.0, 0.0));
+        assert_eq!(geometry.sub_meshes[1].bounds.max, glm::vec3(0.0, 2.0, 3.0));
+
+        let sphere = geometry.bounding_sphere();
+        assert_eq!(sphere.center, glm::vec3(0.0, 1.0, 1.5));
+        assert!(sphere.contains(&glm::vec3(-1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_max_sub_mesh_vertex_count() {
+        let mut geometry = Geometry::default();
+
+        assert_eq!(geometry.max_sub_mesh_vertex_count(), 0);
+
+        geometry.push(vertices(3), vec![0, 1, 2], None, glm::Mat4::identity());
+        geometry.push(vertices(5), vec![0, 1, 2], None, glm::Mat4::identity());
+        geometry.push(vertices(4), vec![0, 1, 2], None, glm::Mat4::identity());
+
+        assert_eq!(geometry.max_sub_mesh_vertex_count(), 5);
+    }
+}
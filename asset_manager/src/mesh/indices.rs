@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use super::Vertex;
+
+/// Index data, kept as 16 bit whenever every vertex can be addressed with it
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    pub fn new(indices: Vec<u32>, vertex_count: usize) -> Indices {
+        if vertex_count <= u16::MAX as usize + 1 {
+            Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = u32> + '_> {
+        match self {
+            Indices::U16(indices) => Box::new(indices.iter().map(|&index| index as u32)),
+            Indices::U32(indices) => Box::new(indices.iter().copied()),
+        }
+    }
+}
+
+impl Default for Indices {
+    fn default() -> Self {
+        Indices::U16(vec![])
+    }
+}
+
+/// Merge bitwise identical vertices
+/// Returns the unique vertices and the indices that rebuild the original list from them
+pub fn deduplicate_vertices(vertices: &[Vertex]) -> (Vec<Vertex>, Vec<u32>) {
+    let mut unique_vertices = vec![];
+    let mut indices = Vec::with_capacity(vertices.len());
+    let mut seen = HashMap::new();
+
+    for vertex in vertices {
+        let index = *seen.entry(vertex.bits()).or_insert_with(|| {
+            unique_vertices.push(*vertex);
+            unique_vertices.len() as u32 - 1
+        });
+
+        indices.push(index);
+    }
+
+    (unique_vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(x: f32) -> Vertex {
+        Vertex {
+            position: glm::vec3(x, 0.0, 0.0),
+            normal: glm::vec3(0.0, 1.0, 0.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_small_meshes_use_16_bit_indices() {
+        assert!(matches!(Indices::new(vec![0, 1, 2], 3), Indices::U16(_)));
+        assert!(matches!(Indices::new(vec![0], 65536), Indices::U16(_)));
+        assert!(matches!(Indices::new(vec![0], 65537), Indices::U32(_)));
+    }
+
+    #[test]
+    fn test_deduplicate_vertices() {
+        let vertices = [
+            vertex(0.0),
+            vertex(1.0),
+            vertex(0.0),
+            vertex(2.0),
+            vertex(1.0),
+        ];
+
+        let (unique_vertices, indices) = deduplicate_vertices(&vertices);
+
+        assert_eq!(unique_vertices.len(), 3);
+        assert_eq!(indices, vec![0, 1, 0, 2, 1]);
+    }
+}
@@ -0,0 +1,163 @@
+use log::warn;
+
+use super::{geometry::Geometry, optimize_vertex_cache, simplify::simplify_mesh, SubMesh};
+
+// Levels that keep nearly every triangle of the one before aren't worth drawing
+const MIN_REDUCTION: f32 = 0.95;
+
+/// A simplified version of a mesh, drawn from the same vertices with its own indices
+#[derive(Debug, Clone)]
+pub struct MeshLod {
+    /// The mesh's sub-meshes, with the index ranges of this level
+    pub sub_meshes: Vec<SubMesh>,
+    /// How far the surface moved from the full detail mesh, relative to the size of the mesh
+    pub error: f32,
+}
+
+/// Append up to `levels` simplified versions of every sub-mesh, each keeping about
+/// `reduction` of the triangles of the one before
+/// Stops early once simplifying doesn't remove enough triangles anymore
+pub(crate) fn generate_lods(geometry: &mut Geometry, levels: usize, reduction: f32) {
+    let ranges = geometry.sub_mesh_vertex_ranges();
+    let size = (geometry.bounds.max - geometry.bounds.min).norm();
+    let mut error: f32 = 0.0;
+
+    for _ in 0..levels {
+        let previous = match geometry.lods.last() {
+            Some(lod) => &lod.sub_meshes,
+            None => &geometry.sub_meshes,
+        };
+
+        let mut sub_meshes = vec![];
+        let mut indices = vec![];
+
+        // Each level simplifies the one before, which is faster than starting over each time
+        for (sub_mesh, range) in previous.iter().zip(&ranges) {
+            let first_index = sub_mesh.first_index as usize;
+            let previous_indices = geometry
+                .indices
+                .get(first_index..first_index + sub_mesh.index_count as usize);
+            let vertices = geometry.vertices.get(range.clone());
+
+            // Indices past the sub-mesh's vertices can't be simplified, it keeps the range of
+            // the level before
+            let (previous_indices, vertices) = match (previous_indices, vertices) {
+                (Some(previous_indices), Some(vertices))
+                    if previous_indices
+                        .iter()
+                        .all(|&index| (index as usize) < vertices.len()) =>
+                {
+                    (previous_indices, vertices)
+                }
+                _ => {
+                    warn!("Not simplifying a sub-mesh with indices outside of its vertices");
+                    sub_meshes.push(*sub_mesh);
+                    continue;
+                }
+            };
+
+            let target_index_count = (previous_indices.len() as f32 * reduction) as usize;
+            let (simplified, sub_mesh_error) =
+                simplify_mesh(vertices, previous_indices, target_index_count);
+
+            sub_meshes.push(SubMesh {
+                first_index: (geometry.indices.len() + indices.len()) as u32,
+                index_count: simplified.len() as u32,
+                ..*sub_mesh
+            });
+            indices.extend(optimize_vertex_cache(&simplified, vertices.len()));
+            error = error.max(sub_mesh_error);
+        }
+
+        let previous_index_count: u32 = previous.iter().map(|sub_mesh| sub_mesh.index_count).sum();
+        let index_count: u32 = sub_meshes.iter().map(|sub_mesh| sub_mesh.index_count).sum();
+
+        if index_count as f32 > previous_index_count as f32 * MIN_REDUCTION {
+            break;
+        }
+
+        geometry.indices.extend(indices);
+        geometry.lods.push(MeshLod {
+            sub_meshes,
+            error: match size > 0.0 {
+                true => error / size,
+                false => 0.0,
+            },
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vertex;
+
+    #[test]
+    fn test_generate_lods_appends_indices() {
+        // A bumpy grid, so every level has some error
+        let size = 16;
+        let vertices = (0..=size)
+            .flat_map(|y| (0..=size).map(move |x| (x, y)))
+            .map(|(x, y)| Vertex {
+                position: glm::vec3(x as f32, y as f32, ((x * y) % 3) as f32 * 0.1),
+                ..Default::default()
+            })
+            .collect();
+
+        let mut indices = vec![];
+        for y in 0..size {
+            for x in 0..size {
+                let corner = y * (size + 1) + x;
+                let above = corner + size + 1;
+                indices.extend([corner, corner + 1, above + 1, above + 1, above, corner]);
+            }
+        }
+
+        let mut geometry = Geometry::default();
+        geometry.push(vertices, indices, None, glm::Mat4::identity());
+        let full_index_count = geometry.indices.len();
+
+        generate_lods(&mut geometry, 3, 0.5);
+
+        assert_eq!(geometry.lods.len(), 3);
+
+        let mut first_index = full_index_count as u32;
+        let mut index_count = full_index_count as u32;
+        for lod in &geometry.lods {
+            let sub_mesh = lod.sub_meshes[0];
+
+            assert_eq!(sub_mesh.first_index, first_index);
+            assert!(sub_mesh.index_count <= index_count / 2 + 3);
+            assert_eq!(sub_mesh.vertex_offset, 0);
+
+            first_index += sub_mesh.index_count;
+            index_count = sub_mesh.index_count;
+        }
+        assert_eq!(geometry.indices.len(), first_index as usize);
+
+        let errors: Vec<f32> = geometry.lods.iter().map(|lod| lod.error).collect();
+        assert!(errors.windows(2).all(|errors| errors[0] <= errors[1]));
+    }
+
+    #[test]
+    fn test_generate_lods_keeps_sub_meshes_with_invalid_indices() {
+        let vertices = (0..4)
+            .map(|i| Vertex {
+                position: glm::vec3((i % 2) as f32, (i / 2) as f32, 0.0),
+                ..Default::default()
+            })
+            .collect();
+
+        let mut geometry = Geometry::default();
+        geometry.push(
+            vertices,
+            vec![0, 1, 3, 3, 2, 7],
+            None,
+            glm::Mat4::identity(),
+        );
+
+        generate_lods(&mut geometry, 2, 0.5);
+
+        assert!(geometry.lods.is_empty());
+    }
+}
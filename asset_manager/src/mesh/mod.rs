@@ -1,57 +1,476 @@
+mod bounds;
+mod cooked;
+mod generate;
+mod geometry;
+mod indices;
+mod lod;
+mod obj;
+mod optimize;
+mod primitives;
+mod simplify;
+mod stl;
+mod sub_mesh;
 mod vertex;
 
-use rand::prelude::*;
+use std::path::Path;
 
-pub use vertex::Vertex;
+pub use bounds::{Aabb, BoundingSphere};
+pub use cooked::CookedMesh;
+pub use generate::{generate_flat_normals, generate_tangents};
+pub use indices::{deduplicate_vertices, Indices};
+pub use lod::MeshLod;
+pub use optimize::{
+    average_cache_miss_ratio, optimize_mesh, optimize_overdraw, optimize_vertex_cache,
+    optimize_vertex_fetch, quantize_vertices, MeshStats,
+};
+pub use primitives::{builtin_mesh, BUILTIN_SCHEME};
+pub use simplify::simplify_mesh;
+pub use sub_mesh::SubMesh;
+pub use vertex::{Vertex, VertexAttribute, VertexFormat};
 
-use gpu_info::Buffer;
+use gpu_info::MeshBuffers;
+use log::{trace, warn};
 
-use crate::asset_info::{AssetInfo, AssetStatus};
+use crate::asset_info::{source_path, AssetInfo, AssetStatus};
+use crate::asset_loader::has_extension;
+use crate::gltf_import::{import_gltf_slice, is_external};
+use crate::{AssetLoader, Dependency, PbrMaterial, Texture, Vfs};
+
+use geometry::Geometry;
 
 pub struct Mesh {
     pub asset_info: AssetInfo,
-    pub gpu_info: Option<Buffer>,
+    pub gpu_info: Option<MeshBuffers>,
     pub vertices: Vec<Vertex>,
-    pub vertex_count: u32,
+    pub indices: Indices,
+    /// Every primitive of the file, kept after upload since drawing needs them
+    pub sub_meshes: Vec<SubMesh>,
+    /// Every material of the file, indexed by the sub-meshes' material slots
+    pub materials: Vec<PbrMaterial>,
+    /// Around every sub-mesh, with their transforms applied
+    pub bounds: Aabb,
+    pub bounding_sphere: BoundingSphere,
+    pub settings: MeshSettings,
+    /// What optimizing the mesh changed, `None` when it wasn't optimized
+    pub stats: Option<MeshStats>,
+    /// Simplified versions of the sub-meshes, each simpler than the one before
+    pub lods: Vec<MeshLod>,
+}
+
+/// How a mesh is prepared after it is read
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshSettings {
+    /// Merge identical vertices and reorder the mesh for the GPU's caches
+    pub optimize: bool,
+    /// Round the vertex attributes to the precision of a packed format before optimizing,
+    /// which merges more vertices but loses some detail, see `quantize_vertices`
+    pub quantize: bool,
+    /// How many simplified levels of detail to generate, none by default
+    pub lod_levels: usize,
+    /// The share of triangles each level keeps of the one before
+    pub lod_reduction: f32,
+}
+
+impl Default for MeshSettings {
+    fn default() -> Self {
+        MeshSettings {
+            optimize: true,
+            quantize: false,
+            lod_levels: 0,
+            lod_reduction: 0.5,
+        }
+    }
+}
+
+impl AssetLoader for Mesh {
+    type Settings = MeshSettings;
+    type Data = Geometry;
+
+    const EXTENSIONS: &'static [&'static str] = &["gltf", "glb", "obj", "stl"];
+
+    fn unloaded(asset_info: AssetInfo, settings: MeshSettings) -> Mesh {
+        Mesh {
+            asset_info,
+            gpu_info: None,
+            vertices: vec![],
+            indices: Indices::default(),
+            sub_meshes: vec![],
+            materials: vec![],
+            bounds: Aabb::empty(),
+            bounding_sphere: BoundingSphere::default(),
+            settings,
+            stats: None,
+            lods: vec![],
+        }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+
+    fn asset_info_mut(&mut self) -> &mut AssetInfo {
+        &mut self.asset_info
+    }
+
+    fn settings(&self) -> MeshSettings {
+        self.settings
+    }
+
+    /// Read the cooked version of the mesh if it is up to date, otherwise cook it
+    /// Builtin meshes are generated instead, they have no file to read or cook
+    fn read(id: &str, _settings: &MeshSettings, vfs: &Vfs) -> Result<Geometry, String> {
+        if primitives::is_builtin(id) {
+            let (vertices, indices) = builtin_mesh(id)?;
+
+            let mut geometry = Geometry::default();
+            geometry.push(vertices, indices, None, glm::Mat4::identity());
+
+            return Ok(geometry);
+        }
+
+        let (source, source_hash) = Mesh::read_source(id, vfs)?;
+
+        if let Some(geometry) = Mesh::read_cooked(id, source_hash, vfs) {
+            return Ok(geometry);
+        }
+
+        let geometry = Mesh::read_geometry(id, source, vfs)?;
+
+        if vfs.is_mounted("cache") {
+            let bytes = cooked::cook(&geometry, source_hash);
+
+            if let Err(e) = vfs.write(&cooked::cache_id(id), &bytes) {
+                warn!("Failed to cook mesh: {}", e);
+            }
+        }
+
+        Ok(geometry)
+    }
+
+    /// Builtin ids like `builtin://cube?size=2` have no extension
+    fn supports(id: &str) -> bool {
+        primitives::is_builtin(id) || has_extension(id, Mesh::EXTENSIONS)
+    }
+
+    fn store(&mut self, geometry: Geometry) {
+        let max_vertex_count = geometry.max_sub_mesh_vertex_count();
+        self.bounding_sphere = geometry.bounding_sphere();
+
+        self.indices = Indices::new(geometry.indices, max_vertex_count);
+        self.vertices = geometry.vertices;
+        self.sub_meshes = geometry.sub_meshes;
+        self.materials = geometry.materials;
+        self.bounds = geometry.bounds;
+        self.stats = geometry.stats;
+        self.lods = geometry.lods;
+    }
+
+    /// The cooked file keeps the mesh as read, so changing the settings doesn't need a recook
+    /// Levels of detail are simplified from the optimized mesh and share its vertices
+    fn optimize(geometry: &mut Geometry, settings: &MeshSettings) -> bool {
+        if settings.optimize {
+            let (optimized, stats) =
+                optimize::optimize_geometry(std::mem::take(geometry), settings.quantize);
+
+            trace!(
+                "Optimized mesh: {} triangles, {} vertices down to {}, ACMR {:.2} down to {:.2}",
+                stats.triangle_count,
+                stats.vertex_count_before,
+                stats.vertex_count_after,
+                stats.acmr_before,
+                stats.acmr_after
+            );
+
+            *geometry = Geometry {
+                stats: Some(stats),
+                ..optimized
+            };
+        }
+
+        if settings.lod_levels > 0 {
+            lod::generate_lods(geometry, settings.lod_levels, settings.lod_reduction);
+
+            trace!("Generated {} levels of detail", geometry.lods.len());
+        }
+
+        settings.optimize
+    }
+
+    /// The textures of every material, so they load alongside the mesh
+    fn dependencies(geometry: &Geometry) -> Vec<Dependency> {
+        let mut dependencies: Vec<Dependency> = vec![];
+
+        for texture in geometry.materials.iter().flat_map(PbrMaterial::textures) {
+            if !dependencies
+                .iter()
+                .any(|dependency| dependency.id() == texture.id)
+            {
+                dependencies.push(Dependency::with_settings::<Texture>(
+                    &texture.id,
+                    texture.color_space,
+                ));
+            }
+        }
+
+        dependencies
+    }
+}
+
+// A mesh's source file as read by `Mesh::read_source`, glTF files along with their buffers
+enum Source {
+    Obj(String),
+    Stl(Vec<u8>),
+    Gltf(Box<gltf::Document>, Vec<gltf::buffer::Data>),
 }
 
 impl Mesh {
-    pub fn load(&mut self) {
-        self.asset_info.status = crate::asset_info::AssetStatus::Loaded;
+    /// Cook the mesh ahead of time, so the first load doesn't have to
+    /// The cooked file is written to `cache://`, and only used while the source is unchanged
+    pub fn cook(id: &str, vfs: &Vfs) -> Result<(), String> {
+        let (source, source_hash) = Mesh::read_source(id, vfs)?;
+        let geometry = Mesh::read_geometry(id, source, vfs)?;
+
+        vfs.write(&cooked::cache_id(id), &cooked::cook(&geometry, source_hash))
+    }
 
-        let import = gltf::import(&self.asset_info.id);
+    fn read_cooked(id: &str, source_hash: u64, vfs: &Vfs) -> Option<Geometry> {
+        let bytes = vfs.read(&cooked::cache_id(id)).ok()?;
+
+        let cooked = match CookedMesh::parse(&bytes) {
+            Ok(cooked) => cooked,
+            Err(e) => {
+                trace!("Recooking mesh {}: {}", id, e);
+                return None;
+            }
+        };
 
-        if import.is_err() {
-            self.asset_info.status = crate::asset_info::AssetStatus::Invalid;
-            return;
+        if cooked.source_hash() != source_hash {
+            trace!("Recooking changed mesh: {}", id);
+            return None;
         }
 
-        let (gltf, buffers, _) = import.unwrap();
+        match cooked.to_geometry() {
+            Ok(geometry) => Some(geometry),
+            Err(e) => {
+                warn!("Recooking mesh {}: {}", id, e);
+                None
+            }
+        }
+    }
 
-        for scene in gltf.scenes() {
-            for node in scene.nodes() {
-                let mesh = node.mesh().unwrap();
+    /// The id of a single mesh inside a glTF file, loaded without any node transform
+    pub fn gltf_mesh_id(path: &str, mesh_index: usize) -> String {
+        format!("{}#mesh{}", path, mesh_index)
+    }
+
+    /// The id of every mesh inside a glTF file, each once without any node transform and
+    /// with its glTF mesh index as the part of its sub-meshes
+    /// Reads the file once for a whole scene, where every node draws its own part
+    pub fn gltf_meshes_id(path: &str) -> String {
+        format!("{}#meshes", path)
+    }
+
+    /// Around the sub-meshes of one part, see `SubMesh::part`, `None` when it has none
+    pub fn part_bounds(&self, part: usize) -> Option<(Aabb, BoundingSphere)> {
+        let sub_meshes: Vec<&SubMesh> = self
+            .sub_meshes
+            .iter()
+            .filter(|sub_mesh| sub_mesh.part == part)
+            .collect();
+
+        let bounds = sub_meshes.iter().fold(Aabb::empty(), |bounds, sub_mesh| {
+            bounds.merge(&sub_mesh.bounds)
+        });
+
+        if bounds.is_empty() {
+            return None;
+        }
+
+        // The sub-meshes' spheres are inside a sphere around their centers and radii
+        let center = bounds.center();
+        let radius = sub_meshes
+            .iter()
+            .map(|sub_mesh| {
+                let sphere = &sub_mesh.bounding_sphere;
+                glm::distance(&center, &sphere.center) + sphere.radius
+            })
+            .fold(0.0, f32::max);
+
+        Some((bounds, BoundingSphere { center, radius }))
+    }
+
+    // Read the source file once, for hashing and then reading the geometry
+    // The files it references are hashed with it, so editing a glTF buffer or an MTL library
+    // is noticed like editing the source itself
+    // The loader is chosen by extension, anything that isn't OBJ or STL is read as glTF
+    fn read_source(id: &str, vfs: &Vfs) -> Result<(Source, u64), String> {
+        let path = source_path(id);
+        let bytes = vfs.read(path)?;
+        let mut source_hash = cooked::hash_source(&bytes);
 
-                for primitive in mesh.primitives() {
-                    if primitive.mode() != gltf::mesh::Mode::Triangles {
-                        continue;
+        let extension = Path::new(path)
+            .extension()
+            .map(|extension| extension.to_ascii_lowercase());
+
+        let source = match extension.as_ref().and_then(|extension| extension.to_str()) {
+            Some("obj") => {
+                let source = match String::from_utf8(bytes) {
+                    Ok(source) => source,
+                    Err(e) => return Err(format!("{}: {}", path, e)),
+                };
+
+                // Missing libraries are skipped when reading too, the mesh just has no materials
+                for library in obj::libraries(&source, path) {
+                    if let Ok(bytes) = vfs.read(&library) {
+                        source_hash = cooked::hash_reference(source_hash, &bytes);
                     }
+                }
+
+                Source::Obj(source)
+            }
+            Some("stl") => Source::Stl(bytes),
+            _ => {
+                let (gltf, buffers) = import_gltf_slice(path, &bytes, vfs)?;
 
-                    self.vertices = Mesh::get_triangular_primitive_vertices(&primitive, &buffers);
-                    self.vertex_count = self.vertices.len() as u32;
-                    self.asset_info.status = AssetStatus::Loaded;
+                for buffer in gltf.buffers().filter(is_external) {
+                    source_hash = cooked::hash_reference(source_hash, &buffers[buffer.index()]);
                 }
+
+                Source::Gltf(Box::new(gltf), buffers)
+            }
+        };
+
+        Ok((source, source_hash))
+    }
+
+    fn read_geometry(id: &str, source: Source, vfs: &Vfs) -> Result<Geometry, String> {
+        let path = source_path(id);
+
+        match source {
+            Source::Obj(source) => obj::read(&source, path, vfs),
+            Source::Stl(bytes) => stl::read(&bytes, path),
+            Source::Gltf(gltf, buffers) => Mesh::read_gltf_geometry(id, &gltf, &buffers),
+        }
+    }
+
+    fn read_gltf_geometry(
+        id: &str,
+        gltf: &gltf::Document,
+        buffers: &[gltf::buffer::Data],
+    ) -> Result<Geometry, String> {
+        let path = source_path(id);
+
+        let mut geometry = Geometry {
+            materials: gltf
+                .materials()
+                .map(|material| PbrMaterial::from_gltf(path, &material))
+                .collect(),
+            ..Default::default()
+        };
+
+        if id.ends_with("#meshes") {
+            for mesh in gltf.meshes() {
+                Mesh::add_mesh_geometry(&mut geometry, &mesh, buffers, glm::Mat4::identity());
+            }
+
+            return Ok(geometry);
+        }
+
+        if let Some(mesh_index) = id.split_once("#mesh").map(|(_, index)| index) {
+            let mesh = match mesh_index
+                .parse()
+                .ok()
+                .and_then(|index| gltf.meshes().nth(index))
+            {
+                Some(mesh) => mesh,
+                None => return Err(format!("Invalid glTF mesh id: {}", id)),
+            };
+
+            Mesh::add_mesh_geometry(&mut geometry, &mesh, buffers, glm::Mat4::identity());
+
+            return Ok(geometry);
+        }
+
+        if gltf.scenes().len() == 0 {
+            // Files without scenes still carry their meshes, place them all at the origin
+            for mesh in gltf.meshes() {
+                Mesh::add_mesh_geometry(&mut geometry, &mesh, buffers, glm::Mat4::identity());
             }
         }
+
+        for scene in gltf.scenes() {
+            for node in scene.nodes() {
+                Mesh::add_node_geometry(&mut geometry, &node, buffers, glm::Mat4::identity());
+            }
+        }
+
+        Ok(geometry)
+    }
+
+    // Walk the node and its children, collecting the primitives of every node that has a mesh
+    fn add_node_geometry(
+        geometry: &mut Geometry,
+        node: &gltf::Node,
+        buffers: &[gltf::buffer::Data],
+        parent_transform: glm::Mat4,
+    ) {
+        let transform = parent_transform * glm::Mat4::from(node.transform().matrix());
+
+        // Cameras, lights and empty grouping nodes have no mesh but may have children that do
+        if let Some(mesh) = node.mesh() {
+            Mesh::add_mesh_geometry(geometry, &mesh, buffers, transform);
+        }
+
+        for child in node.children() {
+            Mesh::add_node_geometry(geometry, &child, buffers, transform);
+        }
+    }
+
+    fn add_mesh_geometry(
+        geometry: &mut Geometry,
+        mesh: &gltf::Mesh,
+        buffers: &[gltf::buffer::Data],
+        transform: glm::Mat4,
+    ) {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+
+            let (vertices, indices) = Mesh::get_triangular_primitive_geometry(&primitive, buffers);
+
+            geometry.push_part(
+                vertices,
+                indices,
+                primitive.material().index(),
+                transform,
+                mesh.index(),
+            );
+        }
     }
 
     // The mesh has been uploaded to the GPU and we are storing the GPU info for later reference
-    pub fn add_gpu_info(&mut self, gpu_info: Buffer) {
-        self.gpu_info = Some(gpu_info);
+    // Returns the buffers this replaced when the mesh was reloaded, the caller must free them
+    pub fn add_gpu_info(&mut self, gpu_info: MeshBuffers) -> Option<MeshBuffers> {
+        let previous = self.gpu_info.replace(gpu_info);
         self.asset_info.status = AssetStatus::Uploaded;
 
         // Free the cpu side data since we no longer need it
         self.vertices = vec![];
+        self.indices = Indices::default();
+
+        previous
+    }
+
+    /// The mesh couldn't be uploaded, like one without any triangles, don't try again until it
+    /// is reloaded
+    pub fn upload_failed(&mut self) {
+        self.vertices = vec![];
+        self.indices = Indices::default();
+        self.asset_info.status = match self.gpu_info {
+            Some(_) => AssetStatus::Uploaded,
+            None => AssetStatus::Invalid,
+        };
     }
 
     pub fn remove_gpu_info(&mut self) {
@@ -59,43 +478,287 @@ impl Mesh {
         self.asset_info.status = AssetStatus::Unloaded;
     }
 
+    /// The sub-meshes to draw at a level of detail, 0 being the full detail mesh
+    /// Levels past the simplest one the mesh has draw the simplest one
+    pub fn lod(&self, level: usize) -> &[SubMesh] {
+        match level.checked_sub(1) {
+            Some(index) => match self.lods.get(index).or(self.lods.last()) {
+                Some(lod) => &lod.sub_meshes,
+                None => &self.sub_meshes,
+            },
+            None => &self.sub_meshes,
+        }
+    }
+
     pub fn needs_uploaded(&self) -> bool {
-        self.asset_info.status == AssetStatus::Loaded
+        matches!(
+            self.asset_info.status,
+            AssetStatus::Loaded | AssetStatus::Optimized
+        )
     }
 
-    fn get_triangular_primitive_vertices(
+    fn get_triangular_primitive_geometry(
         primitive: &gltf::Primitive,
         buffers: &[gltf::buffer::Data],
-    ) -> Vec<Vertex> {
-        let mut positions: Vec<glm::Vec3> = vec![];
-        let mut normals: Vec<glm::Vec3> = vec![];
-
+    ) -> (Vec<Vertex>, Vec<u32>) {
         let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
 
-        if let Some(iter) = reader.read_positions() {
-            for position in iter {
-                positions.push(glm::vec3(position[0], position[1], position[2]));
-            }
+        let positions: Vec<[f32; 3]> = reader
+            .read_positions()
+            .map_or_else(Vec::new, |iter| iter.collect());
+        let normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(|iter| iter.collect());
+        let tangents: Option<Vec<[f32; 4]>> = reader.read_tangents().map(|iter| iter.collect());
+        let uv0: Option<Vec<[f32; 2]>> = reader
+            .read_tex_coords(0)
+            .map(|iter| iter.into_f32().collect());
+        let uv1: Option<Vec<[f32; 2]>> = reader
+            .read_tex_coords(1)
+            .map(|iter| iter.into_f32().collect());
+        let colors: Option<Vec<[f32; 4]>> = reader
+            .read_colors(0)
+            .map(|iter| iter.into_rgba_f32().collect());
+        let joints: Option<Vec<[f32; 4]>> = reader.read_joints(0).map(|iter| {
+            iter.into_u16()
+                .map(|joints| joints.map(|joint| joint as f32))
+                .collect()
+        });
+        let weights: Option<Vec<[f32; 4]>> =
+            reader.read_weights(0).map(|iter| iter.into_f32().collect());
+
+        let default = Vertex::default();
+
+        let vertices: Vec<Vertex> = positions
+            .iter()
+            .enumerate()
+            .map(|(index, position)| Vertex {
+                position: glm::Vec3::from(*position),
+                normal: Mesh::attribute(&normals, index, default.normal),
+                tangent: Mesh::attribute(&tangents, index, default.tangent),
+                uv0: Mesh::attribute(&uv0, index, default.uv0),
+                uv1: Mesh::attribute(&uv1, index, default.uv1),
+                color: Mesh::attribute(&colors, index, default.color),
+                joints: Mesh::attribute(&joints, index, default.joints),
+                weights: Mesh::attribute(&weights, index, default.weights),
+            })
+            .collect();
+
+        let (mut vertices, mut indices) = match reader.read_indices() {
+            Some(indices) => (vertices, indices.into_u32().collect()),
+            // Unindexed primitives list every triangle corner, so merge the shared ones
+            None => deduplicate_vertices(&vertices),
+        };
+
+        if normals.is_none() {
+            (vertices, indices) = generate_flat_normals(&vertices, &indices);
         }
-        if let Some(iter) = reader.read_normals() {
-            for normal in iter {
-                normals.push(glm::vec3(normal[0], normal[1], normal[2]));
-            }
+
+        // Tangents follow the texture coordinates, without them the default is as good as any
+        if tangents.is_none() && uv0.is_some() {
+            (vertices, indices) = generate_tangents(&vertices, &indices);
         }
 
-        let mut rng = rand::thread_rng();
+        (vertices, indices)
+    }
 
-        let mut vertices: Vec<Vertex> = vec![];
-        if let Some(indices) = reader.read_indices() {
-            for index in indices.into_u32() {
-                vertices.push(Vertex {
-                    position: positions[index as usize],
-                    normal: normals[index as usize],
-                    color: glm::vec3(rng.gen(), rng.gen(), rng.gen()),
-                })
-            }
+    // The attribute of one vertex, or the default when the primitive doesn't have it
+    fn attribute<T: Copy, U: From<T>>(values: &Option<Vec<T>>, index: usize, default: U) -> U {
+        match values.as_ref().and_then(|values| values.get(index)) {
+            Some(value) => U::from(*value),
+            None => default,
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+
+    // Two meshes of one triangle each, their positions in `meshes.bin`
+    const TWO_MESHES: &str = r#"{
+        "asset": { "version": "2.0" },
+        "buffers": [{ "uri": "meshes.bin", "byteLength": 72 }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 36 }
+        ],
+        "accessors": [
+            {
+                "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                "min": [0, 0, 0], "max": [1, 1, 0]
+            },
+            {
+                "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3",
+                "min": [10, 0, 0], "max": [11, 1, 0]
+            }
+        ],
+        "meshes": [
+            { "primitives": [{ "attributes": { "POSITION": 0 } }] },
+            { "primitives": [{ "attributes": { "POSITION": 1 } }] }
+        ]
+    }"#;
+
+    // The buffer of `TWO_MESHES`, the second triangle moved along x
+    fn two_meshes_buffer(x: f32) -> Vec<u8> {
+        [0.0, x]
+            .iter()
+            .flat_map(|&x| [x, 0.0, 0.0, x + 1.0, 0.0, 0.0, x, 1.0, 0.0])
+            .flat_map(f32::to_le_bytes)
+            .collect()
+    }
+
+    #[test]
+    fn test_read_uses_cooked_mesh_until_source_changes() {
+        let directory = temp_dir("cooked-mesh");
+        std::fs::create_dir_all(directory.join("cache")).unwrap();
+        std::fs::write(directory.join("triangle.obj"), TRIANGLE).unwrap();
+
+        let vfs = Vfs::new();
+        vfs.mount("assets", &directory).unwrap();
+        vfs.mount("cache", &directory.join("cache")).unwrap();
+
+        let id = "assets://triangle.obj";
+        let geometry = Mesh::read(id, &MeshSettings::default(), &vfs).unwrap();
+        assert_eq!(geometry.vertices.len(), 3);
+        assert!(vfs.exists(&cooked::cache_id(id)));
+
+        // A cooked file for the same source is trusted, even when it differs from the source
+        let source_hash = cooked::hash_source(TRIANGLE.as_bytes());
+        let mut cooked_geometry = Geometry::default();
+        cooked_geometry.push(vec![Vertex::default()], vec![], None, glm::Mat4::identity());
+        vfs.write(
+            &cooked::cache_id(id),
+            &cooked::cook(&cooked_geometry, source_hash),
+        )
+        .unwrap();
+        assert_eq!(
+            Mesh::read(id, &MeshSettings::default(), &vfs)
+                .unwrap()
+                .vertices
+                .len(),
+            1
+        );
+
+        std::fs::write(
+            directory.join("triangle.obj"),
+            TRIANGLE.to_owned() + "f 3 2 1\n",
+        )
+        .unwrap();
+        let geometry = Mesh::read(id, &MeshSettings::default(), &vfs).unwrap();
+        assert_eq!(geometry.indices.len(), 6);
+        assert_eq!(geometry.bounds.max, glm::vec3(1.0, 1.0, 0.0));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_read_recooks_when_a_referenced_file_changes() {
+        let directory = temp_dir("cooked-refs");
+        std::fs::create_dir_all(directory.join("cache")).unwrap();
+        std::fs::write(directory.join("meshes.gltf"), TWO_MESHES).unwrap();
+        std::fs::write(directory.join("meshes.bin"), two_meshes_buffer(10.0)).unwrap();
+        std::fs::write(
+            directory.join("triangle.obj"),
+            "mtllib triangle.mtl\nusemtl red\n".to_owned() + TRIANGLE,
+        )
+        .unwrap();
+        std::fs::write(directory.join("triangle.mtl"), "newmtl red\nKd 1 0 0\n").unwrap();
+
+        let vfs = Vfs::new();
+        vfs.mount("assets", &directory).unwrap();
+        vfs.mount("cache", &directory.join("cache")).unwrap();
+
+        let gltf_id = Mesh::gltf_meshes_id("assets://meshes.gltf");
+        let geometry = Mesh::read(&gltf_id, &MeshSettings::default(), &vfs).unwrap();
+        assert_eq!(geometry.bounds.max.x, 11.0);
+
+        std::fs::write(directory.join("meshes.bin"), two_meshes_buffer(20.0)).unwrap();
+        let geometry = Mesh::read(&gltf_id, &MeshSettings::default(), &vfs).unwrap();
+        assert_eq!(geometry.bounds.max.x, 21.0);
+
+        let obj_id = "assets://triangle.obj";
+        let geometry = Mesh::read(obj_id, &MeshSettings::default(), &vfs).unwrap();
+        assert_eq!(geometry.materials[0].base_color_factor.y, 0.0);
+
+        std::fs::write(directory.join("triangle.mtl"), "newmtl red\nKd 1 1 0\n").unwrap();
+        let geometry = Mesh::read(obj_id, &MeshSettings::default(), &vfs).unwrap();
+        assert_eq!(geometry.materials[0].base_color_factor.y, 1.0);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_read_every_gltf_mesh_as_parts() {
+        let directory = temp_dir("gltf-meshes");
+        std::fs::write(directory.join("meshes.gltf"), TWO_MESHES).unwrap();
+        std::fs::write(directory.join("meshes.bin"), two_meshes_buffer(10.0)).unwrap();
+
+        let vfs = Vfs::new();
+        vfs.mount("assets", &directory).unwrap();
+
+        let id = Mesh::gltf_meshes_id("assets://meshes.gltf");
+        let geometry = Mesh::read(&id, &MeshSettings::default(), &vfs).unwrap();
+
+        let parts: Vec<usize> = geometry
+            .sub_meshes
+            .iter()
+            .map(|sub_mesh| sub_mesh.part)
+            .collect();
+        assert_eq!(parts, [0, 1]);
+
+        let asset_info = AssetInfo {
+            id,
+            status: AssetStatus::Unloaded,
+        };
+        let mut mesh = Mesh::unloaded(asset_info, MeshSettings::default());
+        mesh.store(geometry);
+
+        let (bounds, sphere) = mesh.part_bounds(1).unwrap();
+        assert_eq!(bounds.min, glm::vec3(10.0, 0.0, 0.0));
+        assert_eq!(bounds.max, glm::vec3(11.0, 1.0, 0.0));
+        assert!(sphere.contains(&glm::vec3(11.0, 0.0, 0.0)));
+        assert!(!sphere.contains(&glm::vec3(1.0, 0.0, 0.0)));
+        assert!(mesh.part_bounds(2).is_none());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_optimize_records_stats() {
+        let mut geometry = Geometry::default();
+        // A quad with its shared corners duplicated
+        let corners = [
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (1.0, 1.0),
+            (1.0, 1.0),
+            (0.0, 1.0),
+            (0.0, 0.0),
+        ];
+        let vertices = corners
+            .iter()
+            .map(|&(x, y)| Vertex {
+                position: glm::vec3(x, y, 0.0),
+                ..Default::default()
+            })
+            .collect();
+        geometry.push(vertices, (0..6).collect(), None, glm::Mat4::identity());
+
+        let settings = MeshSettings {
+            optimize: false,
+            ..Default::default()
+        };
+        assert!(!Mesh::optimize(&mut geometry, &settings));
+        assert_eq!(geometry.stats, None);
 
-        vertices
+        assert!(Mesh::optimize(&mut geometry, &MeshSettings::default()));
+        let stats = geometry.stats.unwrap();
+        assert_eq!(stats.triangle_count, 2);
+        assert_eq!(stats.vertex_count_before, 6);
+        assert_eq!(stats.vertex_count_after, 4);
+        assert_eq!(geometry.vertices.len(), 4);
+        assert_eq!(geometry.sub_meshes[0].index_count, 6);
     }
 }
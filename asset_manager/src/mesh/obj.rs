@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+
+use log::warn;
+
+use super::{deduplicate_vertices, generate_tangents, geometry::Geometry, Vertex};
+use crate::{vfs::relative_id, AlphaMode, ColorSpace, PbrMaterial, TextureRef, Vfs};
+
+// One corner of a face, 0 based indices into the positions, texture coordinates and normals
+#[derive(Clone, Copy)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+struct Face {
+    corners: Vec<Corner>,
+    material: Option<usize>,
+    /// 0 when smoothing is off
+    smoothing_group: u32,
+}
+
+/// Read the source of a Wavefront OBJ file and the MTL libraries it references
+pub fn read(source: &str, path: &str, vfs: &Vfs) -> Result<Geometry, String> {
+    match parse(source, path, vfs) {
+        Ok(geometry) => Ok(geometry),
+        Err(e) => Err(format!("{}: {}", path, e)),
+    }
+}
+
+/// The ids of the MTL libraries the OBJ source references
+pub fn libraries(source: &str, id: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("mtllib"))
+        .filter(|libraries| libraries.starts_with(char::is_whitespace))
+        .flat_map(str::split_whitespace)
+        .map(|library| relative_id(id, library))
+        .collect()
+}
+
+/// Parse OBJ source, MTL libraries and textures are looked up relative to the OBJ file's id
+pub fn parse(source: &str, id: &str, vfs: &Vfs) -> Result<Geometry, String> {
+    let mut positions: Vec<glm::Vec3> = vec![];
+    let mut uvs: Vec<glm::Vec2> = vec![];
+    let mut normals: Vec<glm::Vec3> = vec![];
+    let mut faces: Vec<Face> = vec![];
+
+    let mut materials: Vec<PbrMaterial> = vec![];
+    let mut material = None;
+    let mut smoothing_group = 0;
+
+    for (line_number, line) in source.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+
+        let keyword = match tokens.next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+
+        let error = |e: String| format!("line {}: {}", line_number + 1, e);
+
+        match keyword {
+            "v" => positions.push(parse_vec3(&mut tokens).map_err(error)?),
+            // OBJ texture coordinates start at the bottom of the image
+            "vt" => {
+                let u = parse_float(tokens.next()).map_err(error)?;
+                let v = tokens.next().map_or(Ok(0.0), |v| parse_float(Some(v)));
+                uvs.push(glm::vec2(u, 1.0 - v.map_err(error)?));
+            }
+            "vn" => normals.push(parse_vec3(&mut tokens).map_err(error)?),
+            "f" => {
+                let corners = tokens
+                    .map(|corner| parse_corner(corner, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<Corner>, String>>()
+                    .map_err(error)?;
+
+                if corners.len() < 3 {
+                    return Err(error("face with fewer than 3 corners".to_owned()));
+                }
+
+                faces.push(Face {
+                    corners,
+                    material,
+                    smoothing_group,
+                });
+            }
+            "s" => {
+                smoothing_group = match tokens.next() {
+                    Some("off") | None => 0,
+                    Some(group) => group.parse().unwrap_or(0),
+                }
+            }
+            "mtllib" => {
+                for library in tokens {
+                    match read_mtl(&relative_id(id, library), vfs) {
+                        Ok(library_materials) => materials.extend(library_materials),
+                        // The geometry is still usable without its materials
+                        Err(e) => warn!("Failed to read MTL library: {}", e),
+                    }
+                }
+            }
+            "usemtl" => {
+                let name = tokens.next();
+                material = materials
+                    .iter()
+                    .position(|material| material.name.as_deref() == name);
+            }
+            // Objects, groups, lines and curves don't change how faces are drawn
+            _ => {}
+        }
+    }
+
+    let mut geometry = Geometry {
+        materials,
+        ..Default::default()
+    };
+
+    let smooth_normals = smooth_normals(&faces, &positions);
+
+    // One sub-mesh per material, in the order the materials are first used
+    let mut material_slots: Vec<Option<usize>> = vec![];
+    for face in &faces {
+        if !material_slots.contains(&face.material) {
+            material_slots.push(face.material);
+        }
+    }
+
+    for material_slot in material_slots {
+        let mut corners = vec![];
+
+        for face in faces.iter().filter(|face| face.material == material_slot) {
+            let face_normal = face_normal(face, &positions)
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(glm::Vec3::zeros);
+
+            // Polygons are split into a fan of triangles around their first corner
+            for index in 1..face.corners.len() - 1 {
+                for corner in [
+                    face.corners[0],
+                    face.corners[index],
+                    face.corners[index + 1],
+                ] {
+                    let normal = match corner.normal {
+                        Some(normal) => normals[normal],
+                        None if face.smoothing_group == 0 => face_normal,
+                        None => smooth_normals[&(corner.position, face.smoothing_group)],
+                    };
+
+                    corners.push(Vertex {
+                        position: positions[corner.position],
+                        normal,
+                        uv0: corner.uv.map_or_else(glm::Vec2::zeros, |uv| uvs[uv]),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        let (mut vertices, mut indices) = deduplicate_vertices(&corners);
+
+        if !uvs.is_empty() {
+            (vertices, indices) = generate_tangents(&vertices, &indices);
+        }
+
+        geometry.push(vertices, indices, material_slot, glm::Mat4::identity());
+    }
+
+    Ok(geometry)
+}
+
+// The normal of every position in every smoothing group, averaged over the faces using it
+fn smooth_normals(faces: &[Face], positions: &[glm::Vec3]) -> HashMap<(usize, u32), glm::Vec3> {
+    let mut smooth_normals: HashMap<(usize, u32), glm::Vec3> = HashMap::new();
+
+    for face in faces.iter().filter(|face| face.smoothing_group != 0) {
+        // Unnormalized, so larger faces weigh more
+        let normal = face_normal(face, positions);
+
+        for corner in &face.corners {
+            *smooth_normals
+                .entry((corner.position, face.smoothing_group))
+                .or_insert_with(glm::Vec3::zeros) += normal;
+        }
+    }
+
+    for normal in smooth_normals.values_mut() {
+        *normal = normal
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(glm::Vec3::zeros);
+    }
+
+    smooth_normals
+}
+
+// Newell's method, which also works for polygons that aren't quite planar
+fn face_normal(face: &Face, positions: &[glm::Vec3]) -> glm::Vec3 {
+    let mut normal = glm::Vec3::zeros();
+
+    for (index, corner) in face.corners.iter().enumerate() {
+        let current = positions[corner.position];
+        let next = positions[face.corners[(index + 1) % face.corners.len()].position];
+
+        normal += glm::vec3(
+            (current.y - next.y) * (current.z + next.z),
+            (current.z - next.z) * (current.x + next.x),
+            (current.x - next.x) * (current.y + next.y),
+        );
+    }
+
+    normal / 2.0
+}
+
+// `v`, `v/vt`, `v//vn` or `v/vt/vn`, with negative indices counting back from the latest
+fn parse_corner(
+    corner: &str,
+    position_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+) -> Result<Corner, String> {
+    let mut indices = corner.split('/');
+
+    let position = parse_index(indices.next(), position_count)?;
+    let uv = match indices.next() {
+        Some("") | None => None,
+        index => Some(parse_index(index, uv_count)?),
+    };
+    let normal = match indices.next() {
+        Some("") | None => None,
+        index => Some(parse_index(index, normal_count)?),
+    };
+
+    Ok(Corner {
+        position,
+        uv,
+        normal,
+    })
+}
+
+fn parse_index(index: Option<&str>, count: usize) -> Result<usize, String> {
+    let index: i64 = match index.map(str::parse) {
+        Some(Ok(index)) => index,
+        _ => return Err(format!("invalid index {:?}", index)),
+    };
+
+    let resolved = match index {
+        index if index > 0 => index - 1,
+        index if index < 0 => count as i64 + index,
+        _ => -1,
+    };
+
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {} out of range", index));
+    }
+
+    Ok(resolved as usize)
+}
+
+fn parse_float(token: Option<&str>) -> Result<f32, String> {
+    match token.map(str::parse) {
+        Some(Ok(value)) => Ok(value),
+        _ => Err(format!("invalid number {:?}", token)),
+    }
+}
+
+fn parse_vec3<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<glm::Vec3, String> {
+    Ok(glm::vec3(
+        parse_float(tokens.next())?,
+        parse_float(tokens.next())?,
+        parse_float(tokens.next())?,
+    ))
+}
+
+fn read_mtl(id: &str, vfs: &Vfs) -> Result<Vec<PbrMaterial>, String> {
+    Ok(parse_mtl(&vfs.read_to_string(id)?, id))
+}
+
+/// Parse an MTL library into PBR materials
+/// Specular exponents become roughness, and the PBR extension's `Pr` and `Pm` are used as is
+/// Textures are looked up relative to the library's id
+pub fn parse_mtl(source: &str, id: &str) -> Vec<PbrMaterial> {
+    let mut materials: Vec<PbrMaterial> = vec![];
+
+    for line in source.lines() {
+        let mut tokens = line.split_whitespace();
+
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            materials.push(PbrMaterial {
+                name: tokens.next().map(|name| name.to_owned()),
+                // Most OBJ materials are plastics, not the metals glTF defaults to
+                metallic_factor: 0.0,
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => continue,
+        };
+
+        let values: Vec<f32> = tokens
+            .clone()
+            .filter_map(|token| token.parse().ok())
+            .collect();
+
+        // Texture statements end with the file name, after any options
+        let texture = |color_space| {
+            tokens.clone().last().map(|file| TextureRef {
+                id: relative_id(id, file),
+                color_space,
+            })
+        };
+
+        match (keyword, values.as_slice()) {
+            ("Kd", [r, g, b, ..]) => {
+                material.base_color_factor = glm::vec4(*r, *g, *b, material.base_color_factor.w)
+            }
+            ("d", [dissolve, ..]) => material.base_color_factor.w = *dissolve,
+            ("Tr", [transparency, ..]) => material.base_color_factor.w = 1.0 - transparency,
+            ("Ke", [r, g, b, ..]) => material.emissive_factor = glm::vec3(*r, *g, *b),
+            ("Ns", [exponent, ..]) => material.roughness_factor = (2.0 / (exponent + 2.0)).sqrt(),
+            ("Pr", [roughness, ..]) => material.roughness_factor = *roughness,
+            ("Pm", [metallic, ..]) => material.metallic_factor = *metallic,
+            ("map_Kd", _) => material.base_color_texture = texture(ColorSpace::Srgb),
+            ("map_Ke", _) => {
+                material.emissive_texture = texture(ColorSpace::Srgb);
+
+                if material.emissive_factor == glm::Vec3::zeros() {
+                    material.emissive_factor = glm::vec3(1.0, 1.0, 1.0);
+                }
+            }
+            ("norm" | "map_Bump" | "map_bump" | "bump", _) => {
+                material.normal_texture = texture(ColorSpace::Linear)
+            }
+            _ => {}
+        }
+    }
+
+    for material in &mut materials {
+        if material.base_color_factor.w < 1.0 {
+            material.alpha_mode = AlphaMode::Blend;
+        }
+    }
+
+    materials
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quad() {
+        let source = "
+            # A quad with texture coordinates, split into two triangles
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vt 0 0
+            vt 1 0
+            vt 1 1
+            vt 0 1
+            f 1/1 2/2 3/3 4/4
+        ";
+
+        let geometry = parse(source, "cube.obj", &Vfs::new()).unwrap();
+
+        assert_eq!(geometry.sub_meshes.len(), 1);
+        assert_eq!(geometry.vertices.len(), 4);
+        assert_eq!(geometry.indices.len(), 6);
+        for vertex in &geometry.vertices {
+            assert_eq!(vertex.normal, glm::vec3(0.0, 0.0, 1.0));
+        }
+        // Flipped to start at the top
+        assert_eq!(geometry.vertices[0].uv0, glm::vec2(0.0, 1.0));
+    }
+
+    #[test]
+    fn test_smoothing_groups() {
+        // Two faces folded along the y axis, sharing the edge at x = 0
+        let source = "
+            v 0 0 0
+            v 0 1 0
+            v 1 0 1
+            v -1 0 1
+            s 1
+            f 1 3 2
+            f 1 2 4
+        ";
+
+        let smooth = parse(source, "cube.obj", &Vfs::new()).unwrap();
+        let flat = parse(&source.replace("s 1", "s off"), "cube.obj", &Vfs::new()).unwrap();
+
+        // The shared edge is one pair of vertices when smoothed and split when flat
+        assert_eq!(smooth.vertices.len(), 4);
+        assert_eq!(flat.vertices.len(), 6);
+
+        let shared = smooth
+            .vertices
+            .iter()
+            .find(|vertex| vertex.position == glm::vec3(0.0, 0.0, 0.0))
+            .unwrap();
+        assert!((shared.normal - glm::vec3(0.0, 0.0, 1.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn test_negative_indices_and_normals() {
+        let source = "
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            vn 0 0 -1
+            f -3//-1 -2//-1 -1//-1
+        ";
+
+        let geometry = parse(source, "cube.obj", &Vfs::new()).unwrap();
+
+        assert_eq!(geometry.vertices.len(), 3);
+        assert_eq!(geometry.vertices[0].normal, glm::vec3(0.0, 0.0, -1.0));
+        assert!(parse("v 0 0 0\nf 1 2 3", "cube.obj", &Vfs::new()).is_err());
+    }
+
+    #[test]
+    fn test_parse_mtl() {
+        let source = "
+            newmtl glass
+            Kd 0.5 0.5 1.0
+            d 0.25
+            Ns 0
+            map_Kd -s 1 1 1 glass.png
+
+            newmtl lamp
+            map_Ke glow.png
+            Pm 1
+        ";
+
+        let materials = parse_mtl(source, "assets://textures/glass.mtl");
+
+        assert_eq!(materials.len(), 2);
+
+        let glass = &materials[0];
+        assert_eq!(glass.name.as_deref(), Some("glass"));
+        assert_eq!(glass.base_color_factor, glm::vec4(0.5, 0.5, 1.0, 0.25));
+        assert_eq!(glass.alpha_mode, AlphaMode::Blend);
+        assert_eq!(glass.roughness_factor, 1.0);
+        assert_eq!(glass.metallic_factor, 0.0);
+        assert_eq!(
+            glass.base_color_texture.as_ref().map(|texture| &texture.id),
+            Some(&"assets://textures/glass.png".to_owned())
+        );
+
+        let lamp = &materials[1];
+        assert_eq!(lamp.emissive_factor, glm::vec3(1.0, 1.0, 1.0));
+        assert_eq!(lamp.metallic_factor, 1.0);
+        assert_eq!(lamp.alpha_mode, AlphaMode::Opaque);
+    }
+}
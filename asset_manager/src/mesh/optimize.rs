@@ -0,0 +1,488 @@
+use super::{deduplicate_vertices, geometry::Geometry, Aabb, Vertex};
+
+/// The size of the post-transform vertex cache the triangle order is optimized for
+const CACHE_SIZE: usize = 32;
+/// Smaller than `CACHE_SIZE`, so clusters only start where the order really jumps
+const CLUSTER_CACHE_SIZE: usize = 16;
+
+/// What optimizing a mesh changed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshStats {
+    pub triangle_count: usize,
+    pub vertex_count_before: usize,
+    pub vertex_count_after: usize,
+    /// Average vertex cache misses per triangle, between 0.5 at best and 3 at worst
+    pub acmr_before: f32,
+    pub acmr_after: f32,
+}
+
+/// Optimize every sub-mesh on its own, since each is drawn from its own vertex offset
+/// Sub-meshes with indices past their vertices are kept as they are
+pub(crate) fn optimize_geometry(geometry: Geometry, quantize: bool) -> (Geometry, MeshStats) {
+    let mut optimized = Geometry::default();
+    let mut misses_before = 0;
+    let mut misses_after = 0;
+
+    for (sub_mesh, range) in geometry
+        .sub_meshes
+        .iter()
+        .zip(geometry.sub_mesh_vertex_ranges())
+    {
+        let vertices = &geometry.vertices[range];
+        let first_index = sub_mesh.first_index as usize;
+        let indices = &geometry.indices[first_index..first_index + sub_mesh.index_count as usize];
+
+        let (vertices, indices) = match indices
+            .iter()
+            .all(|&index| (index as usize) < vertices.len())
+        {
+            true => {
+                misses_before += cache_misses(indices, vertices.len());
+                let (vertices, indices) = optimize_mesh(vertices, indices, quantize);
+                misses_after += cache_misses(&indices, vertices.len());
+
+                (vertices, indices)
+            }
+            false => (vertices.to_vec(), indices.to_vec()),
+        };
+
+        optimized.push_part(
+            vertices,
+            indices,
+            sub_mesh.material,
+            sub_mesh.transform,
+            sub_mesh.part,
+        );
+    }
+
+    optimized.materials = geometry.materials;
+
+    let triangle_count = optimized.indices.len() / 3;
+    let ratio = |misses: usize| match triangle_count {
+        0 => 0.0,
+        _ => misses as f32 / triangle_count as f32,
+    };
+
+    let stats = MeshStats {
+        triangle_count,
+        vertex_count_before: geometry.vertices.len(),
+        vertex_count_after: optimized.vertices.len(),
+        acmr_before: ratio(misses_before),
+        acmr_after: ratio(misses_after),
+    };
+
+    (optimized, stats)
+}
+
+/// Optimize one sub-mesh for the GPU, returning its new vertices and indices
+///
+/// Identical vertices are merged, triangles are reordered for the vertex cache and then
+/// in clusters so outward facing ones are drawn first, and vertices are sorted by first use.
+/// Quantizing first lets vertices that differ only by float noise merge too.
+pub fn optimize_mesh(
+    vertices: &[Vertex],
+    indices: &[u32],
+    quantize: bool,
+) -> (Vec<Vertex>, Vec<u32>) {
+    let mut vertices = vertices.to_vec();
+    if quantize {
+        quantize_vertices(&mut vertices);
+    }
+
+    let (unique_vertices, remap) = deduplicate_vertices(&vertices);
+
+    let triangle_corners = indices.len() - indices.len() % 3;
+    let indices: Vec<u32> = indices[..triangle_corners]
+        .iter()
+        .map(|&index| remap[index as usize])
+        .collect();
+
+    let indices = optimize_vertex_cache(&indices, unique_vertices.len());
+    let indices = optimize_overdraw(&indices, &unique_vertices);
+
+    optimize_vertex_fetch(&unique_vertices, &indices)
+}
+
+/// Average cache misses per triangle, simulating a FIFO cache of `CACHE_SIZE` vertices
+pub fn average_cache_miss_ratio(indices: &[u32], vertex_count: usize) -> f32 {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return 0.0;
+    }
+
+    cache_misses(indices, vertex_count) as f32 / triangle_count as f32
+}
+
+fn cache_misses(indices: &[u32], vertex_count: usize) -> usize {
+    FifoCache::new(vertex_count, CACHE_SIZE)
+        .misses_per_triangle(indices)
+        .sum()
+}
+
+// Tom Forsyth's linear-speed vertex cache optimization
+fn vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+
+    // The last triangle's vertices are scored the same, so their order doesn't matter
+    let cache_score = match cache_position {
+        None => 0.0,
+        Some(position) if position < 3 => 0.75,
+        Some(position) => (1.0 - (position - 3) as f32 / (CACHE_SIZE - 3) as f32).powf(1.5),
+    };
+
+    // Vertices with few triangles left are finished first, so they can leave the cache
+    cache_score + 2.0 / (remaining_triangles as f32).sqrt()
+}
+
+/// Reorder the triangles so vertices are reused while they are still in the vertex cache
+pub fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangles: Vec<&[u32]> = indices.chunks_exact(3).collect();
+
+    let mut vertex_triangles: Vec<Vec<usize>> = vec![vec![]; vertex_count];
+    for (triangle, corners) in triangles.iter().enumerate() {
+        for &vertex in *corners {
+            vertex_triangles[vertex as usize].push(triangle);
+        }
+    }
+
+    let mut cache_positions: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut vertex_scores: Vec<f32> = vertex_triangles
+        .iter()
+        .map(|triangles| vertex_score(None, triangles.len()))
+        .collect();
+
+    let triangle_score = |corners: &[u32], vertex_scores: &[f32]| -> f32 {
+        corners
+            .iter()
+            .map(|&vertex| vertex_scores[vertex as usize])
+            .sum()
+    };
+
+    let mut triangle_scores: Vec<f32> = triangles
+        .iter()
+        .map(|corners| triangle_score(corners, &vertex_scores))
+        .collect();
+    let mut emitted = vec![false; triangles.len()];
+
+    let mut cache: Vec<u32> = vec![];
+    let mut result = Vec::with_capacity(indices.len());
+    let mut next_triangle = None;
+
+    for _ in 0..triangles.len() {
+        // Nothing in the cache has triangles left, so start over from the best anywhere
+        let triangle = next_triangle.unwrap_or_else(|| {
+            (0..triangles.len())
+                .filter(|&triangle| !emitted[triangle])
+                .max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]))
+                .unwrap()
+        });
+
+        let corners = triangles[triangle];
+        emitted[triangle] = true;
+        result.extend_from_slice(corners);
+
+        for &vertex in corners {
+            vertex_triangles[vertex as usize].retain(|&other| other != triangle);
+        }
+
+        let mut new_cache = corners.to_vec();
+        new_cache.extend(cache.iter().filter(|vertex| !corners.contains(vertex)));
+        let evicted = new_cache.split_off(new_cache.len().min(CACHE_SIZE));
+        cache = new_cache;
+
+        for &vertex in &evicted {
+            cache_positions[vertex as usize] = None;
+        }
+        for (position, &vertex) in cache.iter().enumerate() {
+            cache_positions[vertex as usize] = Some(position);
+        }
+
+        for &vertex in cache.iter().chain(&evicted) {
+            let vertex = vertex as usize;
+            vertex_scores[vertex] =
+                vertex_score(cache_positions[vertex], vertex_triangles[vertex].len());
+        }
+
+        // Only triangles of vertices whose score changed can have become the best
+        next_triangle = None;
+        let mut best_score = f32::NEG_INFINITY;
+
+        for &vertex in cache.iter().chain(&evicted) {
+            for &other in &vertex_triangles[vertex as usize] {
+                triangle_scores[other] = triangle_score(triangles[other], &vertex_scores);
+
+                if cache_positions[vertex as usize].is_some() && triangle_scores[other] > best_score
+                {
+                    best_score = triangle_scores[other];
+                    next_triangle = Some(other);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// Reorder clusters of triangles so the ones facing away from the mesh center are drawn
+/// first, which hides more of the rest behind them
+/// Clusters start where the vertex cache order jumps, so the cache efficiency stays
+pub fn optimize_overdraw(indices: &[u32], vertices: &[Vertex]) -> Vec<u32> {
+    let triangles: Vec<&[u32]> = indices.chunks_exact(3).collect();
+    if triangles.is_empty() {
+        return vec![];
+    }
+
+    let mut cluster_starts: Vec<usize> = FifoCache::new(vertices.len(), CLUSTER_CACHE_SIZE)
+        .misses_per_triangle(indices)
+        .enumerate()
+        .filter(|&(triangle, misses)| triangle == 0 || misses == 3)
+        .map(|(triangle, _)| triangle)
+        .collect();
+    cluster_starts.push(triangles.len());
+
+    let position = |vertex: u32| vertices[vertex as usize].position;
+
+    let mesh_center = indices
+        .iter()
+        .fold(glm::Vec3::zeros(), |sum, &vertex| sum + position(vertex))
+        / indices.len() as f32;
+
+    let mut clusters: Vec<(f32, &[&[u32]])> = cluster_starts
+        .windows(2)
+        .map(|range| {
+            let cluster = &triangles[range[0]..range[1]];
+
+            // Summing the unnormalized face normals weights them by area
+            let mut center = glm::Vec3::zeros();
+            let mut normal = glm::Vec3::zeros();
+            for corners in cluster {
+                let (a, b, c) = (
+                    position(corners[0]),
+                    position(corners[1]),
+                    position(corners[2]),
+                );
+                center += (a + b + c) / 3.0;
+                normal += (b - a).cross(&(c - a));
+            }
+            center /= cluster.len() as f32;
+
+            let facing = (center - mesh_center).dot(
+                &normal
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(glm::Vec3::zeros),
+            );
+
+            (facing, cluster)
+        })
+        .collect();
+
+    clusters.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    clusters
+        .iter()
+        .flat_map(|(_, cluster)| cluster.iter().flat_map(|corners| corners.iter().copied()))
+        .collect()
+}
+
+/// Order the vertices by first use, so the GPU reads the vertex data front to back
+/// Vertices no triangle uses are dropped
+pub fn optimize_vertex_fetch(vertices: &[Vertex], indices: &[u32]) -> (Vec<Vertex>, Vec<u32>) {
+    let mut remap: Vec<Option<u32>> = vec![None; vertices.len()];
+    let mut new_vertices = vec![];
+
+    let new_indices = indices
+        .iter()
+        .map(|&index| {
+            *remap[index as usize].get_or_insert_with(|| {
+                new_vertices.push(vertices[index as usize]);
+                new_vertices.len() as u32 - 1
+            })
+        })
+        .collect();
+
+    (new_vertices, new_indices)
+}
+
+/// Round every attribute to the precision a packed vertex format would keep: 16 bits per
+/// position axis across the bounds, 10 bit normals and tangents, uvs in 4096ths and 8 bit
+/// colors and joint weights
+/// The vertices stay 32 bit floats, but nearly identical ones become identical and merge
+pub fn quantize_vertices(vertices: &mut [Vertex]) {
+    let mut bounds = Aabb::empty();
+    for vertex in vertices.iter() {
+        bounds.grow(&vertex.position);
+    }
+
+    let extent = bounds.max - bounds.min;
+
+    let round = |value: f32, steps: f32| (value * steps).round() / steps;
+
+    for vertex in vertices.iter_mut() {
+        for axis in 0..3 {
+            if extent[axis] > 0.0 {
+                let offset = vertex.position[axis] - bounds.min[axis];
+                vertex.position[axis] =
+                    bounds.min[axis] + round(offset / extent[axis], 65535.0) * extent[axis];
+            }
+        }
+
+        vertex.normal = vertex.normal.map(|value| round(value, 511.0));
+        for axis in 0..3 {
+            vertex.tangent[axis] = round(vertex.tangent[axis], 511.0);
+        }
+        vertex.uv0 = vertex.uv0.map(|value| round(value, 4096.0));
+        vertex.uv1 = vertex.uv1.map(|value| round(value, 4096.0));
+        vertex.color = vertex.color.map(|value| round(value, 255.0));
+        vertex.weights = vertex.weights.map(|value| round(value, 255.0));
+    }
+}
+
+// Tracks which vertices a FIFO cache would hold, by when each was last added
+struct FifoCache {
+    added: Vec<usize>,
+    time: usize,
+    size: usize,
+}
+
+impl FifoCache {
+    fn new(vertex_count: usize, size: usize) -> FifoCache {
+        FifoCache {
+            added: vec![0; vertex_count],
+            time: size + 1,
+            size,
+        }
+    }
+
+    fn misses_per_triangle<'a>(mut self, indices: &'a [u32]) -> impl Iterator<Item = usize> + 'a {
+        indices.chunks_exact(3).map(move |corners| {
+            let mut misses = 0;
+
+            for &vertex in corners {
+                if self.time - self.added[vertex as usize] > self.size {
+                    self.added[vertex as usize] = self.time;
+                    self.time += 1;
+                    misses += 1;
+                }
+            }
+
+            misses
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A grid of quads, with the triangles in column order so the cache can't keep up
+    fn grid(size: u32) -> (Vec<Vertex>, Vec<u32>) {
+        let vertices = (0..=size)
+            .flat_map(|y| (0..=size).map(move |x| (x, y)))
+            .map(|(x, y)| Vertex {
+                position: glm::vec3(x as f32, y as f32, 0.0),
+                normal: glm::vec3(0.0, 0.0, 1.0),
+                ..Default::default()
+            })
+            .collect();
+
+        let mut indices = vec![];
+        for x in 0..size {
+            for y in 0..size {
+                let corner = y * (size + 1) + x;
+                let above = corner + size + 1;
+                indices.extend([corner, corner + 1, above + 1, above + 1, above, corner]);
+            }
+        }
+
+        (vertices, indices)
+    }
+
+    fn sorted_triangles(indices: &[u32], vertices: &[Vertex]) -> Vec<[u32; 9]> {
+        let mut triangles: Vec<[u32; 9]> = indices
+            .chunks_exact(3)
+            .map(|corners| {
+                let mut bits = [0; 9];
+                for (corner, &vertex) in corners.iter().enumerate() {
+                    let position = vertices[vertex as usize].position;
+                    for axis in 0..3 {
+                        bits[corner * 3 + axis] = position[axis].to_bits();
+                    }
+                }
+                bits
+            })
+            .collect();
+        triangles.sort();
+        triangles
+    }
+
+    #[test]
+    fn test_optimize_vertex_cache_reduces_misses() {
+        let (vertices, indices) = grid(64);
+
+        let optimized = optimize_vertex_cache(&indices, vertices.len());
+
+        assert_eq!(
+            sorted_triangles(&optimized, &vertices),
+            sorted_triangles(&indices, &vertices)
+        );
+        assert!(
+            average_cache_miss_ratio(&optimized, vertices.len())
+                < average_cache_miss_ratio(&indices, vertices.len()) * 0.75
+        );
+    }
+
+    #[test]
+    fn test_optimize_mesh_keeps_triangles() {
+        let (vertices, indices) = grid(8);
+        // Every vertex twice, with nothing using the second copies
+        let mut doubled = vertices.clone();
+        doubled.extend(vertices.iter().copied());
+
+        let (optimized_vertices, optimized_indices) = optimize_mesh(&doubled, &indices, false);
+
+        assert_eq!(optimized_vertices.len(), vertices.len());
+        assert_eq!(
+            sorted_triangles(&optimized_indices, &optimized_vertices),
+            sorted_triangles(&indices, &vertices)
+        );
+    }
+
+    #[test]
+    fn test_optimize_vertex_fetch_orders_by_first_use() {
+        let vertices: Vec<Vertex> = (0..4)
+            .map(|x| Vertex {
+                position: glm::vec3(x as f32, 0.0, 0.0),
+                ..Default::default()
+            })
+            .collect();
+
+        let (vertices, indices) = optimize_vertex_fetch(&vertices, &[3, 1, 3]);
+
+        assert_eq!(indices, vec![0, 1, 0]);
+        assert_eq!(vertices.len(), 2);
+        assert_eq!(vertices[0].position.x, 3.0);
+    }
+
+    #[test]
+    fn test_quantize_merges_nearly_identical_vertices() {
+        let mut vertices = vec![
+            Vertex::default(),
+            Vertex {
+                position: glm::vec3(1e-7, 0.0, 0.0),
+                normal: glm::vec3(1e-5, 0.0, 0.0),
+                ..Default::default()
+            },
+            Vertex {
+                position: glm::vec3(1.0, 0.0, 0.0),
+                ..Default::default()
+            },
+        ];
+
+        quantize_vertices(&mut vertices);
+
+        assert_eq!(vertices[0], vertices[1]);
+        assert_eq!(vertices[2].position.x, 1.0);
+    }
+}
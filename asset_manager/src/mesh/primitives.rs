@@ -0,0 +1,586 @@
+use std::{collections::HashMap, f32::consts::PI, ops::RangeInclusive};
+
+use super::{generate_tangents, Vertex};
+
+/// The scheme of generated meshes, like `builtin://cube?size=2`
+pub const BUILTIN_SCHEME: &str = "builtin://";
+
+// Subdividing further makes meshes of millions of triangles
+const MAX_SUBDIVISIONS: u32 = 8;
+const MAX_SEGMENTS: u32 = 1024;
+
+pub(crate) fn is_builtin(id: &str) -> bool {
+    id.starts_with(BUILTIN_SCHEME)
+}
+
+/// Generate the mesh a builtin id names, with the parameters of its query
+///
+/// Meshes are centred on the origin with +Y up, lengths default to a unit sized mesh:
+/// - `cube?size=1`
+/// - `sphere?radius=0.5&segments=32&rings=16`
+/// - `icosphere?radius=0.5&subdivisions=2`
+/// - `plane?size=1&subdivisions=1`, facing +Y
+/// - `cylinder?radius=0.5&height=1&segments=32`
+/// - `capsule?radius=0.5&height=2&segments=32&rings=8`, the height includes the caps
+/// - `cone?radius=0.5&height=1&segments=32`, pointing up
+/// - `torus?radius=0.5&tube_radius=0.25&segments=32&sides=16`, lying flat
+pub fn builtin_mesh(id: &str) -> Result<(Vec<Vertex>, Vec<u32>), String> {
+    let (name, query) = match id.strip_prefix(BUILTIN_SCHEME) {
+        Some(rest) => rest.split_once('?').unwrap_or((rest, "")),
+        None => return Err(format!("{}: not a builtin mesh", id)),
+    };
+
+    let mut parameters = Parameters::parse(id, query)?;
+
+    let mesh = match name {
+        "cube" => cube(parameters.length("size", 1.0)?),
+        "sphere" => uv_sphere(
+            parameters.length("radius", 0.5)?,
+            parameters.count("segments", 32, 3..=MAX_SEGMENTS)?,
+            parameters.count("rings", 16, 2..=MAX_SEGMENTS)?,
+        ),
+        "icosphere" => icosphere(
+            parameters.length("radius", 0.5)?,
+            parameters.count("subdivisions", 2, 0..=MAX_SUBDIVISIONS)?,
+        ),
+        "plane" => plane(
+            parameters.length("size", 1.0)?,
+            parameters.count("subdivisions", 1, 1..=MAX_SEGMENTS)?,
+        ),
+        "cylinder" => cylinder(
+            parameters.length("radius", 0.5)?,
+            parameters.length("height", 1.0)?,
+            parameters.count("segments", 32, 3..=MAX_SEGMENTS)?,
+        ),
+        "capsule" => capsule(
+            parameters.length("radius", 0.5)?,
+            parameters.length("height", 2.0)?,
+            parameters.count("segments", 32, 3..=MAX_SEGMENTS)?,
+            parameters.count("rings", 8, 1..=MAX_SEGMENTS)?,
+        ),
+        "cone" => cone(
+            parameters.length("radius", 0.5)?,
+            parameters.length("height", 1.0)?,
+            parameters.count("segments", 32, 3..=MAX_SEGMENTS)?,
+        ),
+        "torus" => torus(
+            parameters.length("radius", 0.5)?,
+            parameters.length("tube_radius", 0.25)?,
+            parameters.count("segments", 32, 3..=MAX_SEGMENTS)?,
+            parameters.count("sides", 16, 3..=MAX_SEGMENTS)?,
+        ),
+        _ => return Err(format!("{}: unknown builtin mesh {}", id, name)),
+    };
+
+    parameters.finish()?;
+
+    Ok(mesh)
+}
+
+// The key value pairs of a query, each taken once by the mesh it describes
+struct Parameters<'a> {
+    id: &'a str,
+    values: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Parameters<'a> {
+    fn parse(id: &'a str, query: &'a str) -> Result<Parameters<'a>, String> {
+        let mut values = vec![];
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            match pair.split_once('=') {
+                Some(value) => values.push(value),
+                None => return Err(format!("{}: {} has no value", id, pair)),
+            }
+        }
+
+        Ok(Parameters { id, values })
+    }
+
+    fn take(&mut self, name: &str) -> Option<&'a str> {
+        let index = self.values.iter().position(|(key, _)| *key == name)?;
+
+        Some(self.values.remove(index).1)
+    }
+
+    fn length(&mut self, name: &str, default: f32) -> Result<f32, String> {
+        match self.take(name).map(str::parse::<f32>) {
+            None => Ok(default),
+            Some(Ok(value)) if value.is_finite() && value > 0.0 => Ok(value),
+            Some(_) => Err(format!("{}: {} must be a positive number", self.id, name)),
+        }
+    }
+
+    fn count(
+        &mut self,
+        name: &str,
+        default: u32,
+        range: RangeInclusive<u32>,
+    ) -> Result<u32, String> {
+        match self.take(name).map(str::parse::<u32>) {
+            None => Ok(default),
+            Some(Ok(value)) if range.contains(&value) => Ok(value),
+            Some(_) => Err(format!(
+                "{}: {} must be a whole number from {} to {}",
+                self.id,
+                name,
+                range.start(),
+                range.end()
+            )),
+        }
+    }
+
+    // Misspelled parameters would otherwise be silently ignored
+    fn finish(self) -> Result<(), String> {
+        match self.values.first() {
+            Some((name, _)) => Err(format!("{}: unknown parameter {}", self.id, name)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A cube with its own vertices per face, so the edges stay sharp
+fn cube(size: f32) -> (Vec<Vertex>, Vec<u32>) {
+    let half = size / 2.0;
+    let x = glm::vec3(1.0, 0.0, 0.0);
+    let y = glm::vec3(0.0, 1.0, 0.0);
+    let z = glm::vec3(0.0, 0.0, 1.0);
+
+    // The normal, then the directions the texture's u and v follow seen from outside
+    let faces = [
+        (z, x, -y),
+        (-z, -x, -y),
+        (x, -z, -y),
+        (-x, z, -y),
+        (y, x, z),
+        (-y, x, -z),
+    ];
+
+    let mut vertices = vec![];
+    let mut indices = vec![];
+
+    for (normal, u_axis, v_axis) in faces {
+        let (face_vertices, face_indices) = grid(1, 1, |u, v| {
+            let position = (normal + u_axis * (2.0 * u - 1.0) + v_axis * (2.0 * v - 1.0)) * half;
+
+            (position, normal)
+        });
+
+        let offset = vertices.len() as u32;
+        vertices.extend(face_vertices);
+        indices.extend(face_indices.iter().map(|index| index + offset));
+    }
+
+    generate_tangents(&vertices, &indices)
+}
+
+/// A sphere of rings of latitude, with poles at the top and bottom
+fn uv_sphere(radius: f32, segments: u32, rings: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let profile = (0..=rings)
+        .map(|ring| {
+            let v = ring as f32 / rings as f32;
+            let (sin, cos) = (v * PI).sin_cos();
+
+            ProfilePoint {
+                radius: radius * sin,
+                height: radius * cos,
+                normal: glm::vec2(sin, cos),
+                v,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let (vertices, indices) = revolve(&profile, segments);
+
+    generate_tangents(&vertices, &indices)
+}
+
+/// A subdivided icosahedron, whose triangles are more even than a UV sphere's
+fn icosphere(radius: f32, subdivisions: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut points: Vec<glm::Vec3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .iter()
+    .map(|&(x, y, z)| glm::vec3(x, y, z).normalize())
+    .collect();
+
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    // Split every triangle in four, sharing the new points along each edge
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let point = (points[a as usize] + points[b as usize]).normalize();
+                points.push(point);
+                points.len() as u32 - 1
+            })
+        };
+
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut vertices: Vec<Vertex> = points
+        .iter()
+        .map(|point| Vertex {
+            position: point * radius,
+            normal: *point,
+            uv0: glm::vec2(
+                (point.x.atan2(point.z) / (2.0 * PI)).rem_euclid(1.0),
+                point.y.clamp(-1.0, 1.0).acos() / PI,
+            ),
+            ..Default::default()
+        })
+        .collect();
+
+    // Triangles across the seam where u wraps around get copies of their vertices past 1
+    let mut wrapped = HashMap::new();
+    let mut indices = vec![];
+
+    for triangle in &triangles {
+        let u = triangle.map(|index| vertices[index as usize].uv0.x);
+        let crosses_seam = u.iter().cloned().fold(f32::MIN, f32::max)
+            - u.iter().cloned().fold(f32::MAX, f32::min)
+            > 0.5;
+
+        for &index in triangle {
+            let index = match crosses_seam && vertices[index as usize].uv0.x < 0.5 {
+                true => *wrapped.entry(index).or_insert_with(|| {
+                    let mut vertex = vertices[index as usize];
+                    vertex.uv0.x += 1.0;
+                    vertices.push(vertex);
+                    vertices.len() as u32 - 1
+                }),
+                false => index,
+            };
+
+            indices.push(index);
+        }
+    }
+
+    generate_tangents(&vertices, &indices)
+}
+
+/// A square facing +Y, split into `subdivisions` squares along each side
+fn plane(size: f32, subdivisions: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let (vertices, indices) = grid(subdivisions, subdivisions, |u, v| {
+        (
+            glm::vec3((u - 0.5) * size, 0.0, (v - 0.5) * size),
+            glm::vec3(0.0, 1.0, 0.0),
+        )
+    });
+
+    generate_tangents(&vertices, &indices)
+}
+
+fn cylinder(radius: f32, height: f32, segments: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let half = height / 2.0;
+    let side = [(half, 0.0), (-half, 1.0)].map(|(height, v)| ProfilePoint {
+        radius,
+        height,
+        normal: glm::vec2(1.0, 0.0),
+        v,
+    });
+
+    let (mut vertices, mut indices) = revolve(&side, segments);
+    add_disc(&mut vertices, &mut indices, radius, half, segments);
+    add_disc(&mut vertices, &mut indices, radius, -half, segments);
+
+    generate_tangents(&vertices, &indices)
+}
+
+/// A cylinder with half spheres for ends, `height` from the top of one to the bottom of the other
+fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let half_cylinder = (height / 2.0 - radius).max(0.0);
+    let total_length = PI * radius + 2.0 * half_cylinder;
+
+    // Each half sphere goes a quarter turn from its pole to the cylinder
+    let profile = [(half_cylinder, 0.0), (-half_cylinder, PI / 2.0)]
+        .iter()
+        .flat_map(|&(center, start)| {
+            (0..=rings).map(move |ring| {
+                let angle = start + ring as f32 / rings as f32 * PI / 2.0;
+                let (sin, cos) = angle.sin_cos();
+
+                ProfilePoint {
+                    radius: radius * sin,
+                    height: center + radius * cos,
+                    normal: glm::vec2(sin, cos),
+                    v: (angle * radius + half_cylinder - center) / total_length,
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let (vertices, indices) = revolve(&profile, segments);
+
+    generate_tangents(&vertices, &indices)
+}
+
+fn cone(radius: f32, height: f32, segments: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let half = height / 2.0;
+    let normal = glm::vec2(height, radius).normalize();
+    let side = [(0.0, half), (radius, -half)].map(|(ring_radius, height)| ProfilePoint {
+        radius: ring_radius,
+        height,
+        normal,
+        v: ring_radius / radius,
+    });
+
+    let (mut vertices, mut indices) = revolve(&side, segments);
+    add_disc(&mut vertices, &mut indices, radius, -half, segments);
+
+    generate_tangents(&vertices, &indices)
+}
+
+/// A ring around the Y axis, `radius` to the middle of its tube
+fn torus(radius: f32, tube_radius: f32, segments: u32, sides: u32) -> (Vec<Vertex>, Vec<u32>) {
+    // Around the tube from its outside, going down first like the other profiles
+    let profile = (0..=sides)
+        .map(|side| {
+            let v = side as f32 / sides as f32;
+            let (sin, cos) = (-v * 2.0 * PI).sin_cos();
+
+            ProfilePoint {
+                radius: radius + tube_radius * cos,
+                height: tube_radius * sin,
+                normal: glm::vec2(cos, sin),
+                v,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let (vertices, indices) = revolve(&profile, segments);
+
+    generate_tangents(&vertices, &indices)
+}
+
+// A point of the outline a mesh is revolved from, the normal is outwards and up
+struct ProfilePoint {
+    radius: f32,
+    height: f32,
+    normal: glm::Vec2,
+    v: f32,
+}
+
+// Turn the profile, listed top to bottom, around the Y axis
+fn revolve(profile: &[ProfilePoint], segments: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let rows = profile.len() as u32 - 1;
+    let mut vertices = Vec::with_capacity(((segments + 1) * (rows + 1)) as usize);
+
+    for point in profile {
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let (sin, cos) = (u * 2.0 * PI).sin_cos();
+
+            vertices.push(Vertex {
+                position: glm::vec3(sin * point.radius, point.height, cos * point.radius),
+                normal: glm::vec3(sin * point.normal.x, point.normal.y, cos * point.normal.x),
+                uv0: glm::vec2(u, point.v),
+                ..Default::default()
+            });
+        }
+    }
+
+    let indices = grid_indices(&vertices, segments, rows);
+
+    (vertices, indices)
+}
+
+// A surface of `columns` by `rows` squares, from positions and normals along u and v
+// Seen from outside, u should go right and v down, like the texture
+fn grid(
+    columns: u32,
+    rows: u32,
+    point: impl Fn(f32, f32) -> (glm::Vec3, glm::Vec3),
+) -> (Vec<Vertex>, Vec<u32>) {
+    let mut vertices = Vec::with_capacity(((columns + 1) * (rows + 1)) as usize);
+
+    for row in 0..=rows {
+        for column in 0..=columns {
+            let (u, v) = (column as f32 / columns as f32, row as f32 / rows as f32);
+            let (position, normal) = point(u, v);
+
+            vertices.push(Vertex {
+                position,
+                normal,
+                uv0: glm::vec2(u, v),
+                ..Default::default()
+            });
+        }
+    }
+
+    let indices = grid_indices(&vertices, columns, rows);
+
+    (vertices, indices)
+}
+
+// Two triangles per square, leaving out those that collapse where rows meet at a point
+fn grid_indices(vertices: &[Vertex], columns: u32, rows: u32) -> Vec<u32> {
+    let mut indices = Vec::with_capacity((columns * rows * 6) as usize);
+
+    for row in 0..rows {
+        for column in 0..columns {
+            let corner = row * (columns + 1) + column;
+            let below = corner + columns + 1;
+
+            for triangle in [[corner, below + 1, corner + 1], [corner, below, below + 1]] {
+                let [a, b, c] = triangle.map(|index| vertices[index as usize].position);
+
+                if (b - a).cross(&(c - a)).norm() > f32::EPSILON {
+                    indices.extend(triangle);
+                }
+            }
+        }
+    }
+
+    indices
+}
+
+// A flat cap at the height, facing up above the origin and down below it
+fn add_disc(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    radius: f32,
+    height: f32,
+    segments: u32,
+) {
+    let up = height.signum();
+    let center = vertices.len() as u32;
+    let normal = glm::vec3(0.0, up, 0.0);
+
+    // Seen from outside, the texture's v goes towards +Z on the top and -Z on the bottom
+    for segment in 0..=segments {
+        let (sin, cos) = match segment {
+            0 => (0.0, 0.0),
+            _ => (segment as f32 / segments as f32 * 2.0 * PI).sin_cos(),
+        };
+
+        vertices.push(Vertex {
+            position: glm::vec3(sin * radius, height, cos * radius),
+            normal,
+            uv0: glm::vec2(0.5 + sin / 2.0, 0.5 + cos * up / 2.0),
+            ..Default::default()
+        });
+    }
+
+    for segment in 1..segments {
+        let (a, b) = (center + segment, center + segment + 1);
+
+        match up > 0.0 {
+            true => indices.extend([center, a, b]),
+            false => indices.extend([center, b, a]),
+        }
+    }
+    // Close the ring back to its first point
+    match up > 0.0 {
+        true => indices.extend([center, center + segments, center + 1]),
+        false => indices.extend([center, center + 1, center + segments]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssetLoader, Mesh};
+
+    // Every triangle of a convex mesh around the origin should face away from it
+    fn assert_closed_and_outward(id: &str) {
+        let (vertices, indices) = builtin_mesh(id).unwrap();
+
+        assert!(!indices.is_empty(), "{}", id);
+
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| vertices[triangle[corner] as usize]);
+            let face_normal = (b.position - a.position).cross(&(c.position - a.position));
+            let center = (a.position + b.position + c.position) / 3.0;
+
+            assert!(face_normal.dot(&center) > 0.0, "{} faces inwards", id);
+            assert!(face_normal.dot(&a.normal) > 0.0, "{} normals disagree", id);
+            assert!((a.normal.norm() - 1.0).abs() < 1e-4, "{}", id);
+            assert!(a.tangent.xyz().norm() > 0.9, "{} has no tangents", id);
+        }
+    }
+
+    #[test]
+    fn test_builtin_meshes_face_outwards() {
+        for id in [
+            "builtin://cube",
+            "builtin://sphere?segments=8&rings=4",
+            "builtin://icosphere?subdivisions=1",
+            "builtin://cylinder?height=3",
+            "builtin://capsule?radius=1&height=4&rings=3",
+            "builtin://capsule?radius=1&height=1",
+            "builtin://cone?segments=5",
+        ] {
+            assert_closed_and_outward(id);
+        }
+    }
+
+    #[test]
+    fn test_builtin_parameters() {
+        let (vertices, _) = builtin_mesh("builtin://cube?size=2").unwrap();
+        assert!(vertices
+            .iter()
+            .all(|vertex| vertex.position.abs().max() == 1.0));
+
+        let (vertices, indices) = builtin_mesh("builtin://plane?subdivisions=4").unwrap();
+        assert_eq!(vertices.len(), 25);
+        assert_eq!(indices.len(), 4 * 4 * 6);
+
+        let (vertices, _) = builtin_mesh("builtin://torus?radius=2&tube_radius=1").unwrap();
+        let widest = vertices
+            .iter()
+            .map(|vertex| vertex.position.xz().norm())
+            .fold(0.0, f32::max);
+        assert!((widest - 3.0).abs() < 1e-4);
+
+        // Sizes like 0.5 would otherwise read as an extension
+        assert!(Mesh::supports("builtin://cube?size=0.5"));
+
+        assert!(builtin_mesh("builtin://teapot").is_err());
+        assert!(builtin_mesh("builtin://cube?sise=2").is_err());
+        assert!(builtin_mesh("builtin://cube?size=-1").is_err());
+        assert!(builtin_mesh("builtin://sphere?segments=2").is_err());
+        assert!(builtin_mesh("builtin://icosphere?subdivisions=20").is_err());
+    }
+}
@@ -0,0 +1,446 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
+};
+
+use super::Vertex;
+
+// Planes through border edges keep open edges in place, weighted well above the surface's
+const BORDER_WEIGHT: f64 = 10.0;
+
+// Sums of squared distances to planes, as the upper half of a symmetric 4x4 matrix
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(normal: [f64; 3], point: [f64; 3], weight: f64) -> Quadric {
+        let [a, b, c] = normal;
+        let d = -(a * point[0] + b * point[1] + c * point[2]);
+
+        Quadric(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|value| value * weight),
+        )
+    }
+
+    fn add(&self, other: &Quadric) -> Quadric {
+        let mut sum = *self;
+        for (value, other) in sum.0.iter_mut().zip(other.0) {
+            *value += other;
+        }
+        sum
+    }
+
+    fn error(&self, [x, y, z]: [f64; 3]) -> f64 {
+        let q = &self.0;
+
+        let error = q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9];
+
+        // Rounding can take it slightly below zero
+        error.max(0.0)
+    }
+}
+
+// Moving the point `from` onto the point `to`, valid while neither has changed since
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    versions: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed, so the cheapest collapse is at the top of the heap
+// Ties are broken by the points, so the result doesn't depend on the order collapses are pushed
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then((other.from, other.to, other.versions).cmp(&(self.from, self.to, self.versions)))
+    }
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(a: [f64; 3]) -> Option<[f64; 3]> {
+    let length = dot(a, a).sqrt();
+
+    match length > 0.0 {
+        true => Some(a.map(|value| value / length)),
+        false => None,
+    }
+}
+
+struct Simplifier {
+    points: Vec<[f64; 3]>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    border: Vec<bool>,
+    collapsed: Vec<bool>,
+    /// Corners as points, changing as points collapse
+    triangles: Vec<[usize; 3]>,
+    alive: Vec<bool>,
+    point_triangles: Vec<Vec<usize>>,
+    heap: BinaryHeap<Collapse>,
+}
+
+impl Simplifier {
+    fn triangle_normal(&self, corners: [usize; 3]) -> [f64; 3] {
+        let [a, b, c] = corners.map(|point| self.points[point]);
+        cross(sub(b, a), sub(c, a))
+    }
+
+    fn push_collapse(&mut self, a: usize, b: usize) {
+        let quadric = self.quadrics[a].add(&self.quadrics[b]);
+
+        // Border points only move along the border, anything else could tear the edge open
+        let candidates = [(a, b), (b, a)]
+            .into_iter()
+            .filter(|&(from, to)| !self.border[from] || self.border[to]);
+
+        let best = candidates
+            .map(|(from, to)| (quadric.error(self.points[to]), from, to))
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        if let Some((cost, from, to)) = best {
+            self.heap.push(Collapse {
+                cost,
+                from,
+                to,
+                versions: (self.versions[from], self.versions[to]),
+            });
+        }
+    }
+
+    // Collapsing must not flip or flatten any triangle that stays
+    fn can_collapse(&self, from: usize, to: usize) -> bool {
+        self.point_triangles[from]
+            .iter()
+            .filter(|&&triangle| self.alive[triangle])
+            .map(|&triangle| self.triangles[triangle])
+            .filter(|corners| !corners.contains(&to))
+            .all(|corners| {
+                let before = self.triangle_normal(corners);
+                let after = self.triangle_normal(corners.map(|point| match point == from {
+                    true => to,
+                    false => point,
+                }));
+
+                dot(before, after) > 0.0
+            })
+    }
+
+    // Returns the number of triangles removed
+    fn collapse(&mut self, from: usize, to: usize) -> usize {
+        let mut removed = 0;
+
+        self.collapsed[from] = true;
+        self.quadrics[to] = self.quadrics[to].add(&self.quadrics[from]);
+        self.versions[from] += 1;
+        self.versions[to] += 1;
+
+        for triangle in std::mem::take(&mut self.point_triangles[from]) {
+            if !self.alive[triangle] {
+                continue;
+            }
+
+            if self.triangles[triangle].contains(&to) {
+                self.alive[triangle] = false;
+                removed += 1;
+            } else {
+                for corner in &mut self.triangles[triangle] {
+                    if *corner == from {
+                        *corner = to;
+                    }
+                }
+                self.point_triangles[to].push(triangle);
+            }
+        }
+
+        let alive = &self.alive;
+        self.point_triangles[to].retain(|&triangle| alive[triangle]);
+
+        let neighbours: HashSet<usize> = self.point_triangles[to]
+            .iter()
+            .flat_map(|&triangle| self.triangles[triangle])
+            .filter(|&point| point != to)
+            .collect();
+
+        for neighbour in neighbours {
+            self.push_collapse(to, neighbour);
+        }
+
+        removed
+    }
+}
+
+/// Simplify the triangles down to about `target_index_count` indices by collapsing edges,
+/// cheapest first by their quadric error. No new vertices are made, so the result can be
+/// drawn from the same vertex buffer as the original.
+///
+/// Vertices at the same position collapse together, so seams between attributes don't crack.
+/// Returns the indices and the error, roughly the distance the surface moved at most.
+pub fn simplify_mesh(
+    vertices: &[Vertex],
+    indices: &[u32],
+    target_index_count: usize,
+) -> (Vec<u32>, f32) {
+    // Vertices split for different normals or uvs share a point
+    let mut point_ids: HashMap<[u32; 3], usize> = HashMap::new();
+    let mut points = vec![];
+    let mut point_vertices: Vec<Vec<u32>> = vec![];
+
+    let vertex_points: Vec<usize> = vertices
+        .iter()
+        .enumerate()
+        .map(|(index, vertex)| {
+            let key = [0, 1, 2].map(|axis| vertex.position[axis].to_bits());
+
+            let point = *point_ids.entry(key).or_insert_with(|| {
+                points.push([0, 1, 2].map(|axis| vertex.position[axis] as f64));
+                point_vertices.push(vec![]);
+                points.len() - 1
+            });
+            point_vertices[point].push(index as u32);
+
+            point
+        })
+        .collect();
+
+    let corners: Vec<[u32; 3]> = indices
+        .chunks_exact(3)
+        .map(|corners| [corners[0], corners[1], corners[2]])
+        .filter(|corners| {
+            let [a, b, c] = corners.map(|vertex| vertex_points[vertex as usize]);
+            a != b && b != c && c != a
+        })
+        .collect();
+
+    let mut simplifier = Simplifier {
+        quadrics: vec![Quadric::default(); points.len()],
+        versions: vec![0; points.len()],
+        border: vec![false; points.len()],
+        collapsed: vec![false; points.len()],
+        triangles: corners
+            .iter()
+            .map(|corners| corners.map(|vertex| vertex_points[vertex as usize]))
+            .collect(),
+        alive: vec![true; corners.len()],
+        point_triangles: vec![vec![]; points.len()],
+        heap: BinaryHeap::new(),
+        points,
+    };
+
+    let mut edge_triangles: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+
+    for (triangle, &corners) in simplifier.triangles.iter().enumerate() {
+        // Weighted by area, so small triangles don't pull the surface around
+        let normal = simplifier.triangle_normal(corners);
+        let area = dot(normal, normal).sqrt() / 2.0;
+
+        if let Some(normal) = normalize(normal) {
+            let quadric = Quadric::plane(normal, simplifier.points[corners[0]], area);
+            for point in corners {
+                simplifier.quadrics[point] = simplifier.quadrics[point].add(&quadric);
+            }
+        }
+
+        for (corner, &point) in corners.iter().enumerate() {
+            simplifier.point_triangles[point].push(triangle);
+
+            let next = corners[(corner + 1) % 3];
+            edge_triangles
+                .entry((point.min(next), point.max(next)))
+                .or_default()
+                .push(triangle);
+        }
+    }
+
+    for (&(a, b), triangles) in &edge_triangles {
+        if triangles.len() != 1 {
+            continue;
+        }
+
+        // A plane through the border edge, standing upright on its triangle
+        let edge = sub(simplifier.points[b], simplifier.points[a]);
+        let face_normal = simplifier.triangle_normal(simplifier.triangles[triangles[0]]);
+
+        if let Some(normal) = normalize(cross(edge, face_normal)) {
+            let quadric = Quadric::plane(
+                normal,
+                simplifier.points[a],
+                dot(edge, edge) * BORDER_WEIGHT,
+            );
+
+            for point in [a, b] {
+                simplifier.border[point] = true;
+                simplifier.quadrics[point] = simplifier.quadrics[point].add(&quadric);
+            }
+        }
+    }
+
+    for &(a, b) in edge_triangles.keys() {
+        simplifier.push_collapse(a, b);
+    }
+
+    let target_triangle_count = target_index_count / 3;
+    let mut triangle_count = corners.len();
+    let mut max_cost: f64 = 0.0;
+
+    while triangle_count > target_triangle_count {
+        let Some(collapse) = simplifier.heap.pop() else {
+            break;
+        };
+
+        let (from, to) = (collapse.from, collapse.to);
+
+        if simplifier.collapsed[from]
+            || simplifier.collapsed[to]
+            || collapse.versions != (simplifier.versions[from], simplifier.versions[to])
+            || !simplifier.can_collapse(from, to)
+        {
+            continue;
+        }
+
+        triangle_count -= simplifier.collapse(from, to);
+        max_cost = max_cost.max(collapse.cost);
+    }
+
+    // A vertex whose point moved is replaced by the vertex at the new point most like it
+    let closest_vertex = |vertex: u32, point: usize| -> u32 {
+        let original = &vertices[vertex as usize];
+
+        let difference = |candidate: &u32| {
+            let candidate = &vertices[*candidate as usize];
+            (candidate.uv0 - original.uv0).norm_squared()
+                + (1.0 - candidate.normal.dot(&original.normal))
+        };
+
+        *point_vertices[point]
+            .iter()
+            .min_by(|a, b| difference(a).total_cmp(&difference(b)))
+            .unwrap()
+    };
+
+    let mut result = Vec::with_capacity(triangle_count * 3);
+
+    for (triangle, corners) in corners.iter().enumerate() {
+        if !simplifier.alive[triangle] {
+            continue;
+        }
+
+        for (&vertex, &point) in corners.iter().zip(&simplifier.triangles[triangle]) {
+            result.push(match vertex_points[vertex as usize] == point {
+                true => vertex,
+                false => closest_vertex(vertex, point),
+            });
+        }
+    }
+
+    (result, max_cost.sqrt() as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A flat grid, with `size` quads along each side
+    fn grid(size: u32) -> (Vec<Vertex>, Vec<u32>) {
+        let vertices = (0..=size)
+            .flat_map(|y| (0..=size).map(move |x| (x, y)))
+            .map(|(x, y)| Vertex {
+                position: glm::vec3(x as f32, y as f32, 0.0),
+                normal: glm::vec3(0.0, 0.0, 1.0),
+                ..Default::default()
+            })
+            .collect();
+
+        let mut indices = vec![];
+        for y in 0..size {
+            for x in 0..size {
+                let corner = y * (size + 1) + x;
+                let above = corner + size + 1;
+                indices.extend([corner, corner + 1, above + 1, above + 1, above, corner]);
+            }
+        }
+
+        (vertices, indices)
+    }
+
+    #[test]
+    fn test_simplify_flat_grid_without_error() {
+        let (vertices, indices) = grid(8);
+
+        let (simplified, error) = simplify_mesh(&vertices, &indices, 6);
+
+        assert!(simplified.len() < indices.len() / 4);
+        assert!(error < 1e-3);
+
+        // The outline stays where it was, so the grid still covers the same area
+        let area: f32 = simplified
+            .chunks_exact(3)
+            .map(|corners| {
+                let [a, b, c] = [0, 1, 2].map(|corner| vertices[corners[corner] as usize].position);
+                (b - a).cross(&(c - a)).z / 2.0
+            })
+            .sum();
+        assert!((area - 64.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_simplify_keeps_target_when_already_below() {
+        let (vertices, indices) = grid(2);
+
+        let (simplified, _) = simplify_mesh(&vertices, &indices, indices.len());
+
+        assert_eq!(simplified, indices);
+    }
+}
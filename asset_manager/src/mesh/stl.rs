@@ -0,0 +1,186 @@
+use super::{deduplicate_vertices, geometry::Geometry, Vertex};
+
+const HEADER_SIZE: usize = 80;
+const TRIANGLE_SIZE: usize = 50;
+
+/// Read the bytes of an ASCII or binary STL file
+pub fn read(bytes: &[u8], path: &str) -> Result<Geometry, String> {
+    match parse(bytes) {
+        Ok(geometry) => Ok(geometry),
+        Err(e) => Err(format!("{}: {}", path, e)),
+    }
+}
+
+/// Parse STL data, every facet keeps its own flat normal
+pub fn parse(bytes: &[u8]) -> Result<Geometry, String> {
+    // Binary files may also start with "solid", so their size is the better tell
+    let triangles = if binary_size(bytes) == Some(bytes.len()) {
+        parse_binary(bytes)
+    } else if bytes.trim_ascii_start().starts_with(b"solid") {
+        parse_ascii(bytes)?
+    } else {
+        return Err("not an ASCII or binary STL file".to_owned());
+    };
+
+    let mut corners = Vec::with_capacity(triangles.len() * 3);
+
+    for (normal, positions) in triangles {
+        // Many exporters leave the facet normal zeroed, so fall back to the winding
+        let normal = match normal.try_normalize(f32::EPSILON) {
+            Some(normal) => normal,
+            None => (positions[1] - positions[0])
+                .cross(&(positions[2] - positions[0]))
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(glm::Vec3::zeros),
+        };
+
+        for position in positions {
+            corners.push(Vertex {
+                position,
+                normal,
+                ..Default::default()
+            });
+        }
+    }
+
+    let (vertices, indices) = deduplicate_vertices(&corners);
+
+    let mut geometry = Geometry::default();
+    geometry.push(vertices, indices, None, glm::Mat4::identity());
+
+    Ok(geometry)
+}
+
+// The size a binary STL with the triangle count in its header would be
+fn binary_size(bytes: &[u8]) -> Option<usize> {
+    let count = bytes.get(HEADER_SIZE..HEADER_SIZE + 4)?;
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+
+    Some(HEADER_SIZE + 4 + count * TRIANGLE_SIZE)
+}
+
+fn parse_binary(bytes: &[u8]) -> Vec<(glm::Vec3, [glm::Vec3; 3])> {
+    bytes[HEADER_SIZE + 4..]
+        .chunks_exact(TRIANGLE_SIZE)
+        .map(|triangle| {
+            let vec3 = |offset: usize| {
+                let float = |index: usize| {
+                    let start = offset + index * 4;
+                    f32::from_le_bytes(triangle[start..start + 4].try_into().unwrap())
+                };
+
+                glm::vec3(float(0), float(1), float(2))
+            };
+
+            // The last 2 bytes are an attribute count nobody agrees on the meaning of
+            (vec3(0), [vec3(12), vec3(24), vec3(36)])
+        })
+        .collect()
+}
+
+fn parse_ascii(bytes: &[u8]) -> Result<Vec<(glm::Vec3, [glm::Vec3; 3])>, String> {
+    let source = match std::str::from_utf8(bytes) {
+        Ok(source) => source,
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let mut triangles = vec![];
+    let mut normal = glm::Vec3::zeros();
+    let mut positions = vec![];
+
+    for (line_number, line) in source.lines().enumerate() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        let vec3 = |values: &[&str]| -> Result<glm::Vec3, String> {
+            let values: Vec<f32> = values
+                .iter()
+                .filter_map(|value| value.parse().ok())
+                .collect();
+
+            match values.as_slice() {
+                [x, y, z] => Ok(glm::vec3(*x, *y, *z)),
+                _ => Err(format!("line {}: invalid vector", line_number + 1)),
+            }
+        };
+
+        match tokens.as_slice() {
+            ["facet", "normal", values @ ..] => {
+                normal = vec3(values)?;
+                positions.clear();
+            }
+            ["vertex", values @ ..] => positions.push(vec3(values)?),
+            ["endfacet"] => {
+                if positions.len() != 3 {
+                    return Err(format!(
+                        "line {}: facet with {} vertices",
+                        line_number + 1,
+                        positions.len()
+                    ));
+                }
+
+                triangles.push((normal, [positions[0], positions[1], positions[2]]));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ascii() {
+        let source = "solid square
+            facet normal 0 0 1
+                outer loop
+                    vertex 0 0 0
+                    vertex 1 0 0
+                    vertex 1 1 0
+                endloop
+            endfacet
+            facet normal 0 0 0
+                outer loop
+                    vertex 1 1 0
+                    vertex 0 1 0
+                    vertex 0 0 0
+                endloop
+            endfacet
+        endsolid square";
+
+        let geometry = parse(source.as_bytes()).unwrap();
+
+        assert_eq!(geometry.vertices.len(), 4);
+        assert_eq!(geometry.indices, vec![0, 1, 2, 2, 3, 0]);
+        for vertex in &geometry.vertices {
+            assert_eq!(vertex.normal, glm::vec3(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn test_parse_binary() {
+        // The header starts like an ASCII file, which must not confuse the parser
+        let mut bytes = b"solid but actually binary".to_vec();
+        bytes.resize(HEADER_SIZE, 0);
+        bytes.extend(1u32.to_le_bytes());
+        for value in [
+            0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+        ] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend([0, 0]);
+
+        let geometry = parse(&bytes).unwrap();
+
+        assert_eq!(geometry.vertices.len(), 3);
+        assert_eq!(geometry.vertices[1].position, glm::vec3(1.0, 0.0, 0.0));
+        assert_eq!(geometry.vertices[1].normal, glm::vec3(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse(b"not a mesh").is_err());
+    }
+}
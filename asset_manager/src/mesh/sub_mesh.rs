@@ -0,0 +1,22 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::{Aabb, BoundingSphere};
+
+/// One glTF primitive inside the vertex and index data shared by the whole mesh
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SubMesh {
+    /// Position of the first index of this sub-mesh in the mesh index data
+    pub first_index: u32,
+    pub index_count: u32,
+    /// Added to every index of this sub-mesh to find its vertex in the mesh vertex data
+    pub vertex_offset: i32,
+    /// The material slot of the primitive, the glTF material index if it has one
+    pub material: Option<usize>,
+    /// Transform of the node the primitive belongs to, relative to the mesh origin
+    pub transform: glm::Mat4,
+    /// Around the sub-mesh's vertices in mesh space, with its transform applied
+    pub bounds: Aabb,
+    pub bounding_sphere: BoundingSphere,
+    /// The glTF mesh of the file the primitive belongs to, 0 for files without meshes
+    pub part: usize,
+}
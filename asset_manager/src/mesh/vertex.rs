@@ -1,10 +1,117 @@
-// TODO: remove the alignment help and when doing upload,
-//       have the gpu build the format that it is looking for,
-//       less knowing about other places' stuff
-#[repr(C, align(16))]
-#[derive(Clone, Copy)]
+use std::mem::offset_of;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VertexFormat {
+    Float32x2,
+    Float32x3,
+    Float32x4,
+}
+
+/// Where a vertex attribute is found in `Vertex` and the shader location it is read at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub format: VertexFormat,
+    pub offset: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Vertex {
     pub position: glm::Vec3,
     pub normal: glm::Vec3,
-    pub color: glm::Vec3,
+    /// The w component is the handedness of the bitangent, as in glTF
+    pub tangent: glm::Vec4,
+    pub uv0: glm::Vec2,
+    pub uv1: glm::Vec2,
+    /// Linear RGBA
+    pub color: glm::Vec4,
+    /// Up to four joints of the mesh's skin that move the vertex, as indices into its joints
+    pub joints: glm::Vec4,
+    /// How much each of the joints moves the vertex, all zero for meshes without a skin
+    pub weights: glm::Vec4,
+}
+
+impl Vertex {
+    /// The vertex layout, the renderer's vertex input description is built from this
+    pub const ATTRIBUTES: [VertexAttribute; 8] = [
+        VertexAttribute {
+            location: 0,
+            format: VertexFormat::Float32x3,
+            offset: offset_of!(Vertex, position) as u32,
+        },
+        VertexAttribute {
+            location: 1,
+            format: VertexFormat::Float32x3,
+            offset: offset_of!(Vertex, normal) as u32,
+        },
+        VertexAttribute {
+            location: 2,
+            format: VertexFormat::Float32x4,
+            offset: offset_of!(Vertex, tangent) as u32,
+        },
+        VertexAttribute {
+            location: 3,
+            format: VertexFormat::Float32x2,
+            offset: offset_of!(Vertex, uv0) as u32,
+        },
+        VertexAttribute {
+            location: 4,
+            format: VertexFormat::Float32x2,
+            offset: offset_of!(Vertex, uv1) as u32,
+        },
+        VertexAttribute {
+            location: 5,
+            format: VertexFormat::Float32x4,
+            offset: offset_of!(Vertex, color) as u32,
+        },
+        VertexAttribute {
+            location: 6,
+            format: VertexFormat::Float32x4,
+            offset: offset_of!(Vertex, joints) as u32,
+        },
+        VertexAttribute {
+            location: 7,
+            format: VertexFormat::Float32x4,
+            offset: offset_of!(Vertex, weights) as u32,
+        },
+    ];
+
+    /// The bits of every attribute, for finding identical vertices
+    pub(crate) fn bits(&self) -> [u32; 26] {
+        let mut bits = [0; 26];
+
+        let values = self
+            .position
+            .iter()
+            .chain(&self.normal)
+            .chain(&self.tangent)
+            .chain(&self.uv0)
+            .chain(&self.uv1)
+            .chain(&self.color)
+            .chain(&self.joints)
+            .chain(&self.weights);
+
+        // Adding zero turns -0.0 into 0.0, which would otherwise keep equal vertices apart
+        for (bit, value) in bits.iter_mut().zip(values) {
+            *bit = (value + 0.0).to_bits();
+        }
+
+        bits
+    }
+}
+
+impl Default for Vertex {
+    fn default() -> Self {
+        Vertex {
+            position: glm::Vec3::zeros(),
+            normal: glm::Vec3::zeros(),
+            tangent: glm::vec4(1.0, 0.0, 0.0, 1.0),
+            uv0: glm::Vec2::zeros(),
+            uv1: glm::Vec2::zeros(),
+            color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            joints: glm::Vec4::zeros(),
+            weights: glm::Vec4::zeros(),
+        }
+    }
 }
@@ -0,0 +1,100 @@
+use std::{sync::Arc, time::Duration};
+
+use rodio::{source::SeekError, Source};
+
+/// Decoded samples, interleaved by channel
+/// Cloning shares the samples, so any number of voices can play the same buffer at once
+#[derive(Clone, Debug)]
+pub struct SoundBuffer {
+    pub samples: Arc<[i16]>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl SoundBuffer {
+    /// How many samples each channel has
+    pub fn frame_count(&self) -> usize {
+        match self.channels {
+            0 => 0,
+            channels => self.samples.len() / channels as usize,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self.sample_rate {
+            0 => Duration::ZERO,
+            sample_rate => Duration::from_secs_f64(self.frame_count() as f64 / sample_rate as f64),
+        }
+    }
+
+    /// A source playing the buffer from the start
+    pub fn source(&self) -> SoundSource {
+        SoundSource {
+            buffer: self.clone(),
+            position: 0,
+        }
+    }
+}
+
+/// Plays a `SoundBuffer`, each voice has its own source and position
+#[derive(Clone)]
+pub struct SoundSource {
+    buffer: SoundBuffer,
+    position: usize,
+}
+
+impl SoundSource {
+    /// How far the source has played
+    pub fn position(&self) -> Duration {
+        let frame = self.position / self.buffer.channels.max(1) as usize;
+
+        match self.buffer.sample_rate {
+            0 => Duration::ZERO,
+            sample_rate => Duration::from_secs_f64(frame as f64 / sample_rate as f64),
+        }
+    }
+}
+
+impl Iterator for SoundSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = *self.buffer.samples.get(self.position)?;
+        self.position += 1;
+
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.buffer.samples.len() - self.position.min(self.buffer.samples.len());
+
+        (remaining, Some(remaining))
+    }
+}
+
+impl Source for SoundSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.size_hint().0)
+    }
+
+    fn channels(&self) -> u16 {
+        self.buffer.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.buffer.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(self.buffer.duration())
+    }
+
+    /// Seeking past the end stops the source
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        let frame = (position.as_secs_f64() * self.buffer.sample_rate as f64) as usize;
+
+        self.position = (frame * self.buffer.channels as usize).min(self.buffer.samples.len());
+
+        Ok(())
+    }
+}
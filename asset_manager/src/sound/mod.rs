@@ -1,40 +1,134 @@
-use std::{fs::File, io::BufReader};
+mod buffer;
+mod music;
+mod stream;
 
-use log::warn;
-use rodio::Decoder;
+use std::{io::Cursor, time::Duration};
 
-use crate::asset_info::{AssetInfo, AssetStatus};
+use rodio::{source::UniformSourceIterator, Decoder, Source};
+
+use crate::asset_info::AssetInfo;
+use crate::{AssetLoader, Vfs};
+
+pub use buffer::{SoundBuffer, SoundSource};
+pub use music::{Music, MusicSettings};
+pub use stream::{MusicControl, MusicStream, MusicTrack};
 
 pub struct Sound {
     pub asset_info: AssetInfo,
-    pub source: Option<Decoder<BufReader<File>>>,
+    /// The decoded samples, `None` until the sound has loaded
+    pub buffer: Option<SoundBuffer>,
 }
 
-impl Sound {
-    pub fn load(&mut self) {
-        let file = File::open(&self.asset_info.id);
+impl AssetLoader for Sound {
+    type Settings = ();
+    type Data = SoundBuffer;
 
-        if file.is_err() {
-            self.asset_info.status = AssetStatus::Invalid;
+    const EXTENSIONS: &'static [&'static str] = &["wav", "ogg", "mp3", "flac"];
 
-            warn!("Failed to load sound file: {}", self.asset_info.id);
-            return;
+    fn unloaded(asset_info: AssetInfo, _settings: ()) -> Sound {
+        Sound {
+            asset_info,
+            buffer: None,
         }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+
+    fn asset_info_mut(&mut self) -> &mut AssetInfo {
+        &mut self.asset_info
+    }
 
-        let buffer = BufReader::new(file.unwrap());
+    fn settings(&self) {}
 
-        let source = Decoder::new(buffer);
+    /// Read the sound file and decode all of it
+    fn read(path: &str, _settings: &(), vfs: &Vfs) -> Result<SoundBuffer, String> {
+        let buffer = Cursor::new(vfs.read(path)?);
 
-        if source.is_err() {
-            self.asset_info.status = AssetStatus::Invalid;
+        let decoder = match Decoder::new(buffer) {
+            Ok(decoder) => decoder,
+            Err(e) => return Err(format!("Failed to decode sound file {}: {}", path, e)),
+        };
 
-            warn!("Failed to decode sound file: {}", self.asset_info.id);
-            return;
+        let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+
+        if channels == 0 || sample_rate == 0 {
+            return Err(format!("Failed to decode sound file {}: no samples", path));
         }
 
-        self.source = Some(source.unwrap());
-        self.asset_info.status = AssetStatus::Loaded;
+        // Formats like MP3 may change between frames, so everything is converted to the first
+        Ok(SoundBuffer {
+            samples: UniformSourceIterator::new(decoder, channels, sample_rate).collect(),
+            sample_rate,
+            channels,
+        })
+    }
+
+    fn store(&mut self, buffer: SoundBuffer) {
+        self.buffer = Some(buffer);
+    }
+}
+
+impl Sound {
+    /// `None` until the sound has loaded
+    pub fn duration(&self) -> Option<Duration> {
+        self.buffer.as_ref().map(SoundBuffer::duration)
+    }
+
+    pub fn channels(&self) -> Option<u16> {
+        self.buffer.as_ref().map(|buffer| buffer.channels)
+    }
+
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.buffer.as_ref().map(|buffer| buffer.sample_rate)
+    }
+
+    /// A new voice of the sound, `None` until the sound has loaded
+    pub fn source(&self) -> Option<SoundSource> {
+        self.buffer.as_ref().map(SoundBuffer::source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_path, wav};
+
+    #[test]
+    fn test_decoded_buffer_plays_many_times() {
+        let path = temp_path("sound.wav");
+        let samples: Vec<i16> = (0..8000).map(|i| (i % 100) as i16).collect();
+        std::fs::write(&path, wav(2, 8000, &samples)).unwrap();
+        let path = path.to_str().unwrap();
+
+        let mut sound = Sound::unloaded(
+            AssetInfo {
+                id: path.to_owned(),
+                status: crate::AssetStatus::Unloaded,
+            },
+            (),
+        );
+        assert!(sound.source().is_none());
+
+        sound.store(Sound::read(path, &(), &Vfs::new()).unwrap());
+
+        assert_eq!(sound.channels(), Some(2));
+        assert_eq!(sound.sample_rate(), Some(8000));
+        assert_eq!(sound.duration(), Some(Duration::from_millis(500)));
+        assert_eq!(sound.buffer.as_ref().unwrap().frame_count(), 4000);
+
+        // Voices share the samples but not their position
+        let mut first = sound.source().unwrap();
+        let second = sound.source().unwrap();
+        first.nth(99);
+
+        assert_eq!(first.position(), Duration::from_secs_f64(50.0 / 8000.0));
+        assert_eq!(second.collect::<Vec<i16>>(), samples);
+
+        first.try_seek(Duration::from_millis(250)).unwrap();
+        assert_eq!(first.count(), 4000);
 
-        warn!("Loaded sound file: {}", self.asset_info.id);
+        std::fs::remove_file(path).unwrap();
     }
 }
@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use rodio::Source;
+
+use crate::asset_info::AssetInfo;
+use crate::{AssetLoader, Vfs};
+
+use super::stream::{MusicStream, MusicTrack, TrackFile};
+
+/// Where looping playback of a music track jumps back to
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MusicSettings {
+    /// Where looping playback starts over, the start of the track by default
+    pub loop_start: Duration,
+    /// Where looping playback jumps back to `loop_start`, the end of the track when `None`
+    pub loop_end: Option<Duration>,
+}
+
+/// A long sound like a soundtrack, which is streamed from its file instead of decoded
+/// all at once, see `Sound` for short sounds
+pub struct Music {
+    pub asset_info: AssetInfo,
+    pub settings: MusicSettings,
+    /// `None` until the music has loaded
+    pub track: Option<MusicTrack>,
+}
+
+impl AssetLoader for Music {
+    type Settings = MusicSettings;
+    type Data = MusicTrack;
+
+    const EXTENSIONS: &'static [&'static str] = &["wav", "ogg", "mp3", "flac"];
+
+    fn unloaded(asset_info: AssetInfo, settings: MusicSettings) -> Music {
+        Music {
+            asset_info,
+            settings,
+            track: None,
+        }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+
+    fn asset_info_mut(&mut self) -> &mut AssetInfo {
+        &mut self.asset_info
+    }
+
+    fn settings(&self) -> MusicSettings {
+        self.settings
+    }
+
+    /// Only read the format of the file, it is decoded while it plays
+    fn read(path: &str, settings: &MusicSettings, vfs: &Vfs) -> Result<MusicTrack, String> {
+        let file = match vfs.real_path(path) {
+            Some(real_path) => TrackFile::Path(real_path),
+            None => TrackFile::Memory(vfs.read(path)?.into()),
+        };
+
+        let decoder = match MusicTrack::decoder(&file) {
+            Ok(decoder) => decoder,
+            Err(e) => return Err(format!("Failed to decode music file {}: {}", path, e)),
+        };
+
+        let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+
+        if channels == 0 || sample_rate == 0 {
+            return Err(format!("Failed to decode music file {}: no samples", path));
+        }
+
+        let duration = decoder.total_duration();
+
+        if let (Some(loop_end), Some(duration)) = (settings.loop_end, duration) {
+            if loop_end > duration {
+                return Err(format!("Loop end of music {} is past its end", path));
+            }
+        }
+        if settings.loop_start >= settings.loop_end.or(duration).unwrap_or(Duration::MAX) {
+            return Err(format!("Loop start of music {} is past its loop end", path));
+        }
+
+        Ok(MusicTrack {
+            file,
+            channels,
+            sample_rate,
+            duration,
+            loop_start: settings.loop_start,
+            loop_end: settings.loop_end,
+        })
+    }
+
+    fn store(&mut self, track: MusicTrack) {
+        self.track = Some(track);
+    }
+}
+
+impl Music {
+    /// `None` until the music has loaded, or when the format doesn't tell
+    pub fn duration(&self) -> Option<Duration> {
+        self.track.as_ref()?.duration
+    }
+
+    /// Start streaming the music, `None` until it has loaded
+    pub fn stream(&self, looping: bool) -> Option<MusicStream> {
+        Some(MusicStream::new(self.track.clone()?, looping))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_path, wav};
+
+    fn write_music(name: &str, samples: &[i16]) -> String {
+        let path = temp_path(&format!("{}.wav", name));
+        std::fs::write(&path, wav(1, 8000, samples)).unwrap();
+
+        path.to_str().unwrap().to_owned()
+    }
+
+    fn read_music(path: &str, settings: MusicSettings) -> Music {
+        let mut music = Music::unloaded(
+            AssetInfo {
+                id: path.to_owned(),
+                status: crate::AssetStatus::Unloaded,
+            },
+            settings,
+        );
+        music.store(Music::read(path, &settings, &Vfs::new()).unwrap());
+
+        music
+    }
+
+    #[test]
+    fn test_stream_seeks_and_loops() {
+        let samples: Vec<i16> = (0..8000).collect();
+        let path = write_music("music-loop", &samples);
+
+        let settings = MusicSettings {
+            loop_start: Duration::from_millis(250),
+            loop_end: Some(Duration::from_millis(500)),
+        };
+        let music = read_music(&path, settings);
+        assert_eq!(music.duration(), Some(Duration::from_secs(1)));
+
+        // Plays up to the loop end, then the loop over and over
+        let mut stream = music.stream(true).unwrap();
+        let played: Vec<i16> = stream.by_ref().take(8000).collect();
+        let expected: Vec<i16> = (0..4000).chain(2000..4000).chain(2000..4000).collect();
+        assert_eq!(played, expected);
+
+        // Seeking past the loop end goes back to the loop start
+        let control = stream.control();
+        control.seek(Duration::from_millis(750));
+        assert_eq!(stream.next(), Some(2000));
+        assert_eq!(control.position(), Duration::from_millis(250));
+
+        // Stops at the end of the track once it no longer loops
+        control.set_looping(false);
+        control.seek(Duration::from_millis(750));
+        assert_eq!(stream.next(), Some(6000));
+        assert_eq!(control.position(), Duration::from_millis(750));
+        assert_eq!(stream.count(), 1999);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_queued_track_follows_without_gap() {
+        let first_samples: Vec<i16> = (0..40000).map(|i| (i % 20000) as i16).collect();
+        let second_samples: Vec<i16> = (0..3000).map(|i| -i).collect();
+        let first_path = write_music("music-first", &first_samples);
+        let second_path = write_music("music-second", &second_samples);
+
+        let first = read_music(&first_path, MusicSettings::default());
+        let second = read_music(&second_path, MusicSettings::default());
+
+        // Queueing stops the first track from looping
+        let stream = first.stream(true).unwrap();
+        stream.control().queue(second.track.clone().unwrap());
+
+        let played: Vec<i16> = stream.collect();
+        assert_eq!(played, [first_samples, second_samples].concat());
+
+        std::fs::remove_file(first_path).unwrap();
+        std::fs::remove_file(second_path).unwrap();
+    }
+
+    #[test]
+    fn test_track_queued_after_decoding_ended_still_plays() {
+        let first_samples: Vec<i16> = (0..800).collect();
+        let second_samples: Vec<i16> = (0..800).map(|i| -i).collect();
+        let first_path = write_music("music-short-first", &first_samples);
+        let second_path = write_music("music-short-second", &second_samples);
+
+        let first = read_music(&first_path, MusicSettings::default());
+        let second = read_music(&second_path, MusicSettings::default());
+
+        // The whole track fits in one chunk, so decoding has reached its end once the
+        // first sample plays
+        let mut stream = first.stream(false).unwrap();
+        let mut played: Vec<i16> = stream.by_ref().take(799).collect();
+        stream.control().queue(second.track.clone().unwrap());
+        played.extend(stream);
+
+        assert_eq!(played, [first_samples, second_samples].concat());
+
+        std::fs::remove_file(first_path).unwrap();
+        std::fs::remove_file(second_path).unwrap();
+    }
+
+    #[test]
+    fn test_loop_points_past_the_end_fail() {
+        let path = write_music("music-invalid", &[0; 800]);
+
+        let settings = MusicSettings {
+            loop_start: Duration::ZERO,
+            loop_end: Some(Duration::from_secs(1)),
+        };
+        assert!(Music::read(&path, &settings, &Vfs::new()).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,476 @@
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read, Seek, SeekFrom},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{sync_channel, Receiver, SyncSender, TryRecvError},
+        Arc, Condvar, Mutex,
+    },
+    task::Poll,
+    thread,
+    time::Duration,
+};
+
+use log::warn;
+use rodio::{source::UniformSourceIterator, Decoder, Source};
+
+// Frames decoded at a time, and chunks decoded ahead of playback
+const CHUNK_FRAMES: usize = 4096;
+const CHUNKS_AHEAD: usize = 4;
+
+/// Where a track is read from while it streams
+#[derive(Clone, Debug)]
+pub(crate) enum TrackFile {
+    Path(PathBuf),
+    /// Files in pak archives can't be opened on their own, so they are kept compressed
+    Memory(Arc<[u8]>),
+}
+
+/// A long sound that is decoded a little at a time while it plays, see `Music`
+#[derive(Clone, Debug)]
+pub struct MusicTrack {
+    pub(crate) file: TrackFile,
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// `None` when the format doesn't tell without decoding everything
+    pub duration: Option<Duration>,
+    /// Where looping playback starts over
+    pub loop_start: Duration,
+    /// Where looping playback jumps back to `loop_start`, the end of the track when `None`
+    pub loop_end: Option<Duration>,
+}
+
+pub(crate) enum TrackReader {
+    File(BufReader<File>),
+    Memory(Cursor<Arc<[u8]>>),
+}
+
+impl Read for TrackReader {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            TrackReader::File(file) => file.read(buffer),
+            TrackReader::Memory(memory) => memory.read(buffer),
+        }
+    }
+}
+
+impl Seek for TrackReader {
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            TrackReader::File(file) => file.seek(position),
+            TrackReader::Memory(memory) => memory.seek(position),
+        }
+    }
+}
+
+impl MusicTrack {
+    pub(crate) fn decoder(file: &TrackFile) -> Result<Decoder<TrackReader>, String> {
+        let reader = match file {
+            TrackFile::Path(path) => match File::open(path) {
+                Ok(file) => TrackReader::File(BufReader::new(file)),
+                Err(e) => return Err(format!("{}: {}", path.display(), e)),
+            },
+            TrackFile::Memory(bytes) => TrackReader::Memory(Cursor::new(bytes.clone())),
+        };
+
+        match Decoder::new(reader) {
+            Ok(decoder) => Ok(decoder),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+// The decoder of the track playing, converted to the stream's format
+struct TrackDecoder {
+    track: MusicTrack,
+    source: Box<dyn Source<Item = i16> + Send>,
+    /// Frames into the track, at the stream's sample rate
+    frame: u64,
+}
+
+impl TrackDecoder {
+    fn open(track: MusicTrack, channels: u16, sample_rate: u32) -> Result<TrackDecoder, String> {
+        let decoder = MusicTrack::decoder(&track.file)?;
+
+        let source: Box<dyn Source<Item = i16> + Send> =
+            match decoder.channels() == channels && decoder.sample_rate() == sample_rate {
+                true => Box::new(decoder),
+                false => Box::new(UniformSourceIterator::new(decoder, channels, sample_rate)),
+            };
+
+        Ok(TrackDecoder {
+            track,
+            source,
+            frame: 0,
+        })
+    }
+
+    // Formats that can't seek are opened again and decoded up to the position
+    fn seek(&mut self, position: Duration, channels: u16, sample_rate: u32) -> Result<(), String> {
+        let frame = (position.as_secs_f64() * sample_rate as f64) as u64;
+
+        if self.source.try_seek(position).is_err() {
+            *self = TrackDecoder::open(self.track.clone(), channels, sample_rate)?;
+
+            let samples = frame as usize * channels as usize;
+            self.source.by_ref().take(samples).for_each(drop);
+        }
+
+        self.frame = frame;
+
+        Ok(())
+    }
+}
+
+struct Chunk {
+    generation: u64,
+    samples: Vec<i16>,
+    /// Frames into its track the chunk starts at
+    start_frame: u64,
+    /// Sent without samples once the last track was decoded, the stream ends there unless
+    /// something was requested after this `StreamShared::version`
+    end: Option<u64>,
+}
+
+// What the game asks of a stream, picked up by its decoding thread between chunks
+struct Requests {
+    seek: Option<Duration>,
+    next: Option<MusicTrack>,
+    looping: bool,
+    /// The stream was dropped, nothing is left to decode for
+    closed: bool,
+}
+
+impl Requests {
+    // Whether there is anything to decode after the last track has ended
+    fn has_work(&self) -> bool {
+        self.closed || self.seek.is_some() || self.next.is_some() || self.looping
+    }
+
+    // Only called with the requests locked, so the decoding thread sees the version with them
+    fn changed(&mut self, shared: &StreamShared) {
+        shared.version.fetch_add(1, Ordering::Relaxed);
+        shared.changed.notify_one();
+    }
+}
+
+struct StreamShared {
+    requests: Mutex<Requests>,
+    /// Wakes the decoding thread waiting at the end of the last track when a request comes in
+    changed: Condvar,
+    /// Counts seeks, so chunks decoded before the latest one are skipped
+    /// Only changed with `requests` locked, so the decoding thread sees it with the seek
+    generation: AtomicU64,
+    /// Counts requests that give a stream at its end more to decode
+    /// Playback reads it without locking `requests`, so the audio thread never waits on it
+    version: AtomicU64,
+    /// Frames into the playing track, as heard
+    position: AtomicU64,
+}
+
+/// Controls a `MusicStream` after it was handed to the audio output, cheap to clone
+#[derive(Clone)]
+pub struct MusicControl {
+    shared: Arc<StreamShared>,
+    sample_rate: u32,
+}
+
+impl MusicControl {
+    /// Jump to the position in the playing track
+    pub fn seek(&self, position: Duration) {
+        let mut requests = self.shared.requests.lock().unwrap();
+
+        self.shared.generation.fetch_add(1, Ordering::Relaxed);
+        requests.seek = Some(position);
+        requests.changed(&self.shared);
+    }
+
+    /// Play the track right after the playing one ends, without a gap
+    /// Replaces the track queued before, and stops the playing track from looping
+    pub fn queue(&self, track: MusicTrack) {
+        let mut requests = self.shared.requests.lock().unwrap();
+
+        requests.next = Some(track);
+        requests.looping = false;
+        requests.changed(&self.shared);
+    }
+
+    pub fn set_looping(&self, looping: bool) {
+        let mut requests = self.shared.requests.lock().unwrap();
+
+        requests.looping = looping;
+        if looping {
+            requests.changed(&self.shared);
+        }
+    }
+
+    /// How far into the playing track playback is
+    pub fn position(&self) -> Duration {
+        let frame = self.shared.position.load(Ordering::Relaxed);
+
+        Duration::from_secs_f64(frame as f64 / self.sample_rate as f64)
+    }
+}
+
+/// Plays music tracks one after the other, decoding them on a thread of their own
+/// The stream ends after the last track unless it loops
+pub struct MusicStream {
+    chunks: Receiver<Chunk>,
+    chunk: Chunk,
+    index: usize,
+    channels: u16,
+    sample_rate: u32,
+    shared: Arc<StreamShared>,
+}
+
+impl MusicStream {
+    /// Start decoding the track, the file is opened on the decoding thread
+    pub fn new(track: MusicTrack, looping: bool) -> MusicStream {
+        let (channels, sample_rate) = (track.channels, track.sample_rate);
+        let (sender, chunks) = sync_channel(CHUNKS_AHEAD);
+
+        let shared = Arc::new(StreamShared {
+            requests: Mutex::new(Requests {
+                seek: None,
+                next: None,
+                looping,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+            generation: AtomicU64::new(0),
+            version: AtomicU64::new(0),
+            position: AtomicU64::new(0),
+        });
+
+        let decoding_shared = shared.clone();
+        let spawned = thread::Builder::new()
+            .name("music-stream".to_owned())
+            .spawn(move || {
+                match TrackDecoder::open(track, channels, sample_rate) {
+                    Ok(decoder) => decode(decoder, channels, sample_rate, &decoding_shared, sender),
+                    Err(e) => warn!("Failed to stream music: {}", e),
+                };
+            });
+
+        if let Err(e) = spawned {
+            warn!("Failed to start music stream: {}", e);
+        }
+
+        MusicStream {
+            chunks,
+            chunk: Chunk {
+                generation: 0,
+                samples: vec![],
+                start_frame: 0,
+                end: None,
+            },
+            index: 0,
+            channels,
+            sample_rate,
+            shared,
+        }
+    }
+
+    pub fn control(&self) -> MusicControl {
+        MusicControl {
+            shared: self.shared.clone(),
+            sample_rate: self.sample_rate,
+        }
+    }
+}
+
+// Decode chunks until the last track ends or the stream is dropped
+fn decode(
+    mut decoder: TrackDecoder,
+    channels: u16,
+    sample_rate: u32,
+    shared: &StreamShared,
+    sender: SyncSender<Chunk>,
+) {
+    let to_frame = |duration: Duration| (duration.as_secs_f64() * sample_rate as f64) as u64;
+
+    loop {
+        let (generation, seek, looping) = {
+            let mut requests = shared.requests.lock().unwrap();
+            let generation = shared.generation.load(Ordering::Relaxed);
+
+            (generation, requests.seek.take(), requests.looping)
+        };
+
+        if let Some(position) = seek {
+            if let Err(e) = decoder.seek(position, channels, sample_rate) {
+                warn!("Failed to seek music: {}", e);
+                return;
+            }
+        }
+
+        let loop_end = match looping {
+            true => decoder.track.loop_end.map(to_frame),
+            false => None,
+        };
+        let frames = match loop_end {
+            Some(loop_end) => (loop_end.saturating_sub(decoder.frame) as usize).min(CHUNK_FRAMES),
+            None => CHUNK_FRAMES,
+        };
+
+        let sample_count = frames * channels as usize;
+        let samples: Vec<i16> = decoder.source.by_ref().take(sample_count).collect();
+        let start_frame = decoder.frame;
+        decoder.frame += (samples.len() / channels as usize) as u64;
+
+        // Seeking or looping again past the loop end jumps back to the loop start too
+        let ended = samples.len() < sample_count
+            || loop_end.is_some_and(|loop_end| decoder.frame >= loop_end);
+
+        if !samples.is_empty() {
+            let chunk = Chunk {
+                generation,
+                samples,
+                start_frame,
+                end: None,
+            };
+
+            // The stream was dropped
+            if sender.send(chunk).is_err() {
+                return;
+            }
+        }
+
+        if !ended {
+            continue;
+        }
+
+        let next = match looping {
+            true => None,
+            false => {
+                // Playback hasn't caught up with the end yet, so a track may still be queued,
+                // or the stream seeked or set looping, until the stream is dropped
+                let mut requests = shared.requests.lock().unwrap();
+
+                while !requests.has_work() {
+                    let version = shared.version.load(Ordering::Relaxed);
+                    drop(requests);
+
+                    let end = Chunk {
+                        generation,
+                        samples: vec![],
+                        start_frame: decoder.frame,
+                        end: Some(version),
+                    };
+
+                    if sender.send(end).is_err() {
+                        return;
+                    }
+
+                    requests = shared
+                        .changed
+                        .wait_while(shared.requests.lock().unwrap(), |requests| {
+                            shared.version.load(Ordering::Relaxed) == version && !requests.closed
+                        })
+                        .unwrap();
+                }
+
+                if requests.closed {
+                    return;
+                }
+
+                match requests.next.take() {
+                    Some(next) => Some(next),
+                    None => continue,
+                }
+            }
+        };
+
+        let result = match next {
+            Some(next) => {
+                TrackDecoder::open(next, channels, sample_rate).map(|next| decoder = next)
+            }
+            None => {
+                let loop_start = decoder.track.loop_start;
+                decoder.seek(loop_start, channels, sample_rate)
+            }
+        };
+
+        if let Err(e) = result {
+            warn!("Failed to stream music: {}", e);
+            return;
+        }
+    }
+}
+
+impl Drop for MusicStream {
+    fn drop(&mut self) {
+        let mut requests = self.shared.requests.lock().unwrap();
+
+        requests.closed = true;
+        requests.changed(&self.shared);
+    }
+}
+
+impl MusicStream {
+    /// The next sample without waiting for decoding, `Poll::Pending` when it has fallen behind
+    /// For the audio thread, which can't wait, where `next` would
+    pub fn try_next(&mut self) -> Poll<Option<i16>> {
+        match self.pull(false) {
+            Ok(sample) => Poll::Ready(Some(sample)),
+            Err(TryRecvError::Empty) => Poll::Pending,
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+        }
+    }
+
+    // Ends with `Disconnected`, and only runs out with `Empty` when it doesn't wait
+    fn pull(&mut self, wait: bool) -> Result<i16, TryRecvError> {
+        let generation = self.shared.generation.load(Ordering::Relaxed);
+
+        loop {
+            if self.chunk.generation == generation {
+                if let Some(&sample) = self.chunk.samples.get(self.index) {
+                    let frame =
+                        self.chunk.start_frame + (self.index / self.channels as usize) as u64;
+                    self.shared.position.store(frame, Ordering::Relaxed);
+                    self.index += 1;
+
+                    return Ok(sample);
+                }
+
+                if self.chunk.end == Some(self.shared.version.load(Ordering::Relaxed)) {
+                    return Err(TryRecvError::Disconnected);
+                }
+            }
+
+            // Decoding runs well ahead of playback, so this rarely has to wait
+            self.chunk = match wait {
+                true => self.chunks.recv().map_err(|_| TryRecvError::Disconnected)?,
+                false => self.chunks.try_recv()?,
+            };
+            self.index = 0;
+        }
+    }
+}
+
+impl Iterator for MusicStream {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        self.pull(true).ok()
+    }
+}
+
+impl Source for MusicStream {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,44 @@
+//! Fixtures for the tests of this crate, and of crates using it with the `test-support` feature
+
+use std::path::PathBuf;
+
+/// A path in the temporary directory unique to this test process, keeping the extension of `name`
+pub fn temp_path(name: &str) -> PathBuf {
+    let name = match name.split_once('.') {
+        Some((stem, extension)) => format!("{}-{}.{}", stem, std::process::id(), extension),
+        None => format!("{}-{}", name, std::process::id()),
+    };
+
+    std::env::temp_dir().join(name)
+}
+
+/// Creates the directory at [`temp_path`]
+pub fn temp_dir(name: &str) -> PathBuf {
+    let directory = temp_path(name);
+    std::fs::create_dir_all(&directory).unwrap();
+
+    directory
+}
+
+/// A 16 bit PCM WAV file of the interleaved samples
+pub fn wav(channels: u16, sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+    let data_size = samples.len() as u32 * 2;
+    let block_align = channels * 2;
+
+    let mut bytes = vec![];
+    bytes.extend(b"RIFF");
+    bytes.extend((36 + data_size).to_le_bytes());
+    bytes.extend(b"WAVEfmt ");
+    bytes.extend(16_u32.to_le_bytes());
+    bytes.extend(1_u16.to_le_bytes());
+    bytes.extend(channels.to_le_bytes());
+    bytes.extend(sample_rate.to_le_bytes());
+    bytes.extend((sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend(block_align.to_le_bytes());
+    bytes.extend(16_u16.to_le_bytes());
+    bytes.extend(b"data");
+    bytes.extend(data_size.to_le_bytes());
+    bytes.extend(samples.iter().flat_map(|sample| sample.to_le_bytes()));
+
+    bytes
+}
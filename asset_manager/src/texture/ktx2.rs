@@ -0,0 +1,164 @@
+use super::{ColorSpace, TextureData, TextureFormat};
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+// Sizes of the fixed header, the index that follows it and one entry of the level index
+const HEADER_SIZE: usize = 48;
+const INDEX_SIZE: usize = 32;
+const LEVEL_SIZE: usize = 24;
+
+// Offset of the transfer function in the data format descriptor, and the value meaning sRGB
+const DFD_TRANSFER_OFFSET: usize = 14;
+const KHR_DF_TRANSFER_SRGB: u8 = 2;
+
+/// Read a 2D KTX2 texture, keeping its format and mip levels exactly as stored
+/// Supercompressed files, arrays, cubemaps and 3D textures are not supported
+pub(super) fn read(bytes: &[u8]) -> Result<TextureData, String> {
+    if bytes.len() < HEADER_SIZE + INDEX_SIZE || bytes[..12] != IDENTIFIER {
+        return Err("Not a KTX2 file".to_owned());
+    }
+
+    let vk_format = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?;
+    let depth = read_u32(bytes, 28)?;
+    let layer_count = read_u32(bytes, 32)?;
+    let face_count = read_u32(bytes, 36)?;
+    let level_count = read_u32(bytes, 40)?;
+    let supercompression_scheme = read_u32(bytes, 44)?;
+
+    if vk_format == 0 {
+        return Err("KTX2 files without a Vulkan format are not supported".to_owned());
+    }
+    if supercompression_scheme != 0 {
+        return Err("Supercompressed KTX2 files are not supported".to_owned());
+    }
+    if depth > 1 || layer_count > 1 || face_count != 1 || height == 0 {
+        return Err("Only 2D KTX2 textures are supported".to_owned());
+    }
+
+    let dfd_offset = read_u32(bytes, HEADER_SIZE)? as usize;
+    let color_space = match dfd_offset
+        .checked_add(DFD_TRANSFER_OFFSET)
+        .and_then(|offset| bytes.get(offset))
+    {
+        Some(&KHR_DF_TRANSFER_SRGB) => ColorSpace::Srgb,
+        _ => ColorSpace::Linear,
+    };
+
+    // A level count of 0 asks the loader to generate mips, we only keep the base level
+    let mips = (0..level_count.max(1) as usize)
+        .map(|level| {
+            let entry = HEADER_SIZE + INDEX_SIZE + level * LEVEL_SIZE;
+
+            let offset = read_u64(bytes, entry)?;
+            let length = read_u64(bytes, entry + 8)?;
+
+            // Both come from the file, so their sum may not even fit
+            let range = offset
+                .checked_add(length)
+                .and_then(|end| Some(usize::try_from(offset).ok()?..usize::try_from(end).ok()?));
+
+            match range.and_then(|range| bytes.get(range)) {
+                Some(data) => Ok(data.to_vec()),
+                None => Err(format!("KTX2 mip level {} is out of bounds", level)),
+            }
+        })
+        .collect::<Result<Vec<Vec<u8>>, String>>()?;
+
+    Ok(TextureData {
+        width,
+        height,
+        format: TextureFormat::Vulkan(vk_format),
+        color_space,
+        mips,
+    })
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, String> {
+    match bytes.get(offset..offset + 4) {
+        Some(value) => Ok(u32::from_le_bytes(value.try_into().unwrap())),
+        None => Err("Unexpected end of KTX2 file".to_owned()),
+    }
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, String> {
+    match bytes.get(offset..offset + 8) {
+        Some(value) => Ok(u64::from_le_bytes(value.try_into().unwrap())),
+        None => Err("Unexpected end of KTX2 file".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // VK_FORMAT_R8G8B8A8_SRGB
+    const R8G8B8A8_SRGB: u32 = 43;
+
+    fn ktx2(levels: &[&[u8]], transfer_function: u8) -> Vec<u8> {
+        let level_index_end = HEADER_SIZE + INDEX_SIZE + levels.len() * LEVEL_SIZE;
+        let dfd_offset = level_index_end;
+        let dfd = {
+            let mut dfd = vec![0; 24];
+            dfd[DFD_TRANSFER_OFFSET] = transfer_function;
+            dfd
+        };
+
+        let mut bytes = IDENTIFIER.to_vec();
+        for value in [R8G8B8A8_SRGB, 1, 2, 1, 0, 0, 1, levels.len() as u32, 0] {
+            bytes.extend(u32::to_le_bytes(value));
+        }
+        for value in [dfd_offset as u32, dfd.len() as u32, 0, 0] {
+            bytes.extend(u32::to_le_bytes(value));
+        }
+        bytes.extend([0; 16]);
+
+        let mut offset = dfd_offset + dfd.len();
+        for level in levels {
+            for value in [offset, level.len(), level.len()] {
+                bytes.extend(u64::to_le_bytes(value as u64));
+            }
+            offset += level.len();
+        }
+
+        bytes.extend(dfd);
+        for level in levels {
+            bytes.extend(*level);
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn test_read_levels() {
+        let bytes = ktx2(&[&[1; 8], &[2; 4]], KHR_DF_TRANSFER_SRGB);
+
+        let data = read(&bytes).unwrap();
+
+        assert_eq!((data.width, data.height), (2, 1));
+        assert_eq!(data.format, TextureFormat::Vulkan(R8G8B8A8_SRGB));
+        assert_eq!(data.color_space, ColorSpace::Srgb);
+        assert_eq!(data.mips, vec![vec![1; 8], vec![2; 4]]);
+    }
+
+    #[test]
+    fn test_read_rejects_truncated() {
+        let bytes = ktx2(&[&[1; 8]], 1);
+
+        assert!(read(&bytes[..bytes.len() - 1]).is_err());
+        assert!(read(&bytes[..20]).is_err());
+    }
+
+    #[test]
+    fn test_read_rejects_overflowing_level() {
+        let mut bytes = ktx2(&[&[1; 8]], 1);
+
+        let entry = HEADER_SIZE + INDEX_SIZE;
+        bytes[entry..entry + 8].copy_from_slice(&u64::to_le_bytes(u64::MAX - 4));
+
+        assert!(read(&bytes).is_err());
+    }
+}
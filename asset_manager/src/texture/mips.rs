@@ -0,0 +1,107 @@
+use super::ColorSpace;
+
+/// Build the full mip chain of an RGBA8 image down to 1x1, starting with the image itself
+/// Each level averages 2x2 blocks of the last, sRGB colors are averaged as linear light
+pub fn generate_mips(
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+    color_space: ColorSpace,
+) -> Vec<Vec<u8>> {
+    let to_linear: Vec<f32> = (0..=255u8)
+        .map(|value| decode(value, color_space))
+        .collect();
+
+    let mut mips = vec![rgba];
+    let (mut width, mut height) = (width as usize, height as usize);
+
+    while width > 1 || height > 1 {
+        let previous = mips.last().unwrap();
+        let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+
+        let mut next = Vec::with_capacity(next_width * next_height * 4);
+
+        for y in 0..next_height {
+            for x in 0..next_width {
+                // Odd sized levels fold their last row and column into the one before
+                let xs = [(x * 2).min(width - 1), (x * 2 + 1).min(width - 1)];
+                let ys = [(y * 2).min(height - 1), (y * 2 + 1).min(height - 1)];
+
+                for channel in 0..4 {
+                    let mut sum = 0.0;
+
+                    for sample_y in ys {
+                        for sample_x in xs {
+                            let value = previous[(sample_y * width + sample_x) * 4 + channel];
+
+                            sum += match channel {
+                                3 => value as f32 / 255.0,
+                                _ => to_linear[value as usize],
+                            };
+                        }
+                    }
+
+                    let average = sum / 4.0;
+
+                    next.push(match channel {
+                        3 => (average * 255.0).round() as u8,
+                        _ => encode(average, color_space),
+                    });
+                }
+            }
+        }
+
+        mips.push(next);
+        width = next_width;
+        height = next_height;
+    }
+
+    mips
+}
+
+fn decode(value: u8, color_space: ColorSpace) -> f32 {
+    let value = value as f32 / 255.0;
+
+    match color_space {
+        ColorSpace::Linear => value,
+        ColorSpace::Srgb if value <= 0.04045 => value / 12.92,
+        ColorSpace::Srgb => ((value + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn encode(value: f32, color_space: ColorSpace) -> u8 {
+    let value = match color_space {
+        ColorSpace::Linear => value,
+        ColorSpace::Srgb if value <= 0.0031308 => value * 12.92,
+        ColorSpace::Srgb => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+    };
+
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mip_chain_sizes() {
+        let mips = generate_mips(5, 2, vec![0; 5 * 2 * 4], ColorSpace::Linear);
+
+        let sizes: Vec<usize> = mips.iter().map(|mip| mip.len() / 4).collect();
+
+        assert_eq!(sizes, vec![10, 2, 1]);
+    }
+
+    #[test]
+    fn test_srgb_averages_linear_light() {
+        // Half black and half white, 2x1
+        let rgba = vec![0, 0, 0, 0, 255, 255, 255, 255];
+
+        let linear = generate_mips(2, 1, rgba.clone(), ColorSpace::Linear);
+        let srgb = generate_mips(2, 1, rgba, ColorSpace::Srgb);
+
+        assert_eq!(linear[1], vec![128, 128, 128, 128]);
+        // 50% linear light is much brighter than 50% in sRGB, alpha stays linear
+        assert_eq!(srgb[1], vec![188, 188, 188, 128]);
+    }
+}
@@ -0,0 +1,227 @@
+mod ktx2;
+mod mips;
+
+use std::path::Path;
+
+use gltf::image::Format;
+
+pub use mips::generate_mips;
+
+use gpu_info::TextureImage;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::asset_info::{source_path, AssetInfo, AssetStatus};
+use crate::gltf_import::{open_gltf, read_buffers};
+use crate::{AssetLoader, Vfs};
+
+/// How the values of a texture's color channels are encoded
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorSpace {
+    /// Colors meant to be seen, like base color and emissive maps
+    #[default]
+    Srgb,
+    /// Data, like normal, occlusion, roughness and metallic maps
+    Linear,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    /// 8 bits per channel RGBA, what every decoded PNG and JPEG is converted to
+    Rgba8,
+    /// Stored as-is from a KTX2 file, the raw VkFormat value
+    Vulkan(u32),
+}
+
+pub struct Texture {
+    pub asset_info: AssetInfo,
+    pub gpu_info: Option<TextureImage>,
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    pub color_space: ColorSpace,
+    /// The pixel data of every mip level, largest first
+    pub mips: Vec<Vec<u8>>,
+}
+
+/// A decoded texture, waiting to be stored in its asset
+pub struct TextureData {
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    color_space: ColorSpace,
+    mips: Vec<Vec<u8>>,
+}
+
+impl AssetLoader for Texture {
+    /// PNG and JPEG colors are read in this color space, KTX2 files record their own
+    type Settings = ColorSpace;
+    type Data = TextureData;
+
+    /// glTF files for the images embedded in them, like `scene.glb#image0`
+    const EXTENSIONS: &'static [&'static str] = &["png", "jpg", "jpeg", "ktx2", "gltf", "glb"];
+
+    fn unloaded(asset_info: AssetInfo, color_space: ColorSpace) -> Texture {
+        Texture {
+            asset_info,
+            gpu_info: None,
+            width: 0,
+            height: 0,
+            format: TextureFormat::Rgba8,
+            color_space,
+            mips: vec![],
+        }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+
+    fn asset_info_mut(&mut self) -> &mut AssetInfo {
+        &mut self.asset_info
+    }
+
+    fn settings(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    fn read(id: &str, color_space: &ColorSpace, vfs: &Vfs) -> Result<TextureData, String> {
+        Texture::read_data(id, *color_space, vfs)
+    }
+
+    fn store(&mut self, data: TextureData) {
+        self.width = data.width;
+        self.height = data.height;
+        self.format = data.format;
+        self.color_space = data.color_space;
+        self.mips = data.mips;
+    }
+}
+
+impl Texture {
+    fn read_data(id: &str, color_space: ColorSpace, vfs: &Vfs) -> Result<TextureData, String> {
+        if let Some((path, image_index)) = id.split_once("#image") {
+            let (width, height, rgba) = match image_index.parse() {
+                Ok(image_index) => Texture::read_gltf_image(path, image_index, vfs)?,
+                Err(_) => return Err(format!("Invalid glTF image id: {}", id)),
+            };
+
+            return Ok(TextureData {
+                width,
+                height,
+                format: TextureFormat::Rgba8,
+                color_space,
+                mips: generate_mips(width, height, rgba, color_space),
+            });
+        }
+
+        let path = source_path(id);
+
+        let is_ktx2 = Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("ktx2"));
+
+        let bytes = vfs.read(path)?;
+
+        if is_ktx2 {
+            return match ktx2::read(&bytes) {
+                Ok(data) => Ok(data),
+                Err(e) => Err(format!("{}: {}", path, e)),
+            };
+        }
+
+        let image = match image::load_from_memory(&bytes) {
+            Ok(image) => image.into_rgba8(),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        };
+
+        let (width, height) = image.dimensions();
+
+        Ok(TextureData {
+            width,
+            height,
+            format: TextureFormat::Rgba8,
+            color_space,
+            mips: generate_mips(width, height, image.into_raw(), color_space),
+        })
+    }
+
+    // Decode an image embedded in a glTF file, returning its size and RGBA8 pixels
+    fn read_gltf_image(
+        path: &str,
+        image_index: usize,
+        vfs: &Vfs,
+    ) -> Result<(u32, u32, Vec<u8>), String> {
+        let gltf::Gltf { document, mut blob } = open_gltf(path, vfs)?;
+
+        let image = match document.images().nth(image_index) {
+            Some(image) => image,
+            None => return Err(format!("{} has no image {}", path, image_index)),
+        };
+
+        let buffers = read_buffers(path, &document, &mut blob, vfs)?;
+
+        // Images in their own files get plain file ids, so only embedded images are read here
+        let data = match gltf::image::Data::from_source(image.source(), None, &buffers) {
+            Ok(data) => data,
+            Err(e) => return Err(format!("{} image {}: {}", path, image_index, e)),
+        };
+
+        let rgba = match data.format {
+            Format::R8G8B8A8 => data.pixels,
+            Format::R8G8B8 => data
+                .pixels
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+            // Grayscale, with alpha in the second channel for R8G8
+            Format::R8 => data
+                .pixels
+                .iter()
+                .flat_map(|&gray| [gray, gray, gray, 255])
+                .collect(),
+            Format::R8G8 => data
+                .pixels
+                .chunks_exact(2)
+                .flat_map(|gray_alpha| [gray_alpha[0], gray_alpha[0], gray_alpha[0], gray_alpha[1]])
+                .collect(),
+            format => {
+                return Err(format!(
+                    "{} image {} has unsupported format {:?}",
+                    path, image_index, format
+                ))
+            }
+        };
+
+        Ok((data.width, data.height, rgba))
+    }
+
+    // The texture has been uploaded to the GPU and we are storing the GPU info for later reference
+    // Returns the image this replaced when the texture was reloaded, the caller must free it
+    pub fn add_gpu_info(&mut self, gpu_info: TextureImage) -> Option<TextureImage> {
+        let previous = self.gpu_info.replace(gpu_info);
+        self.asset_info.status = AssetStatus::Uploaded;
+
+        // Free the cpu side data since we no longer need it
+        self.mips = vec![];
+
+        previous
+    }
+
+    /// The texture couldn't be uploaded, don't try again until it is reloaded
+    pub fn upload_failed(&mut self) {
+        self.mips = vec![];
+        self.asset_info.status = match self.gpu_info {
+            Some(_) => AssetStatus::Uploaded,
+            None => AssetStatus::Invalid,
+        };
+    }
+
+    pub fn remove_gpu_info(&mut self) {
+        self.gpu_info = None;
+        self.asset_info.status = AssetStatus::Unloaded;
+    }
+
+    pub fn needs_uploaded(&self) -> bool {
+        self.asset_info.status == AssetStatus::Loaded
+    }
+}
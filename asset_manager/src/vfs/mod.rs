@@ -0,0 +1,440 @@
+mod pak;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use config::Config;
+use log::{trace, warn};
+
+use crate::asset_info::source_path;
+
+pub use pak::{pack_directory, write_pak, PakArchive};
+
+enum MountSource {
+    Directory(PathBuf),
+    Pak(PakArchive),
+}
+
+struct Mount {
+    scheme: String,
+    source: MountSource,
+}
+
+// Where a file was found
+enum Location<'a> {
+    File(PathBuf),
+    Pak(&'a PakArchive, String),
+}
+
+/// Resolves asset ids to files, so loading doesn't depend on the working directory
+///
+/// Ids like `assets://models/monkey.glb` are read from what is mounted at `assets`,
+/// a directory during development or a pak archive in shipping builds.
+/// Ids without a scheme are plain paths, relative to the working directory.
+pub struct Vfs {
+    mounts: RwLock<Vec<Mount>>,
+}
+
+impl Vfs {
+    pub fn new() -> Vfs {
+        Vfs {
+            mounts: RwLock::new(vec![]),
+        }
+    }
+
+    /// Mount the user data directory at `user` and its cache directory at `cache`,
+    /// then every mount in the config
+    pub fn from_config(config: &Config) -> Vfs {
+        let vfs = Vfs::new();
+
+        if let Some(directory) = user_data_dir(&config.info.name) {
+            for (scheme, path) in [
+                ("user", directory.clone()),
+                ("cache", directory.join("cache")),
+            ] {
+                if let Err(e) = vfs.mount(scheme, &path) {
+                    warn!("Failed to mount {}://: {}", scheme, e);
+                }
+            }
+        }
+
+        for mount in &config.assets.mounts {
+            if let Err(e) = vfs.mount(&mount.scheme, &resolve_mount_path(&mount.path)) {
+                warn!("Failed to mount {}://: {}", mount.scheme, e);
+            }
+        }
+
+        vfs
+    }
+
+    /// Mount a directory or pak archive at the scheme
+    /// Mounts are searched newest first, so a patch can be mounted over a base archive
+    pub fn mount(&self, scheme: &str, path: &Path) -> Result<(), String> {
+        trace!("Mounting {} at {}://", path.display(), scheme);
+
+        let source = if path.is_file() {
+            MountSource::Pak(PakArchive::open(path)?)
+        } else {
+            MountSource::Directory(path.to_path_buf())
+        };
+
+        self.mounts.write().unwrap().push(Mount {
+            scheme: scheme.to_owned(),
+            source,
+        });
+
+        Ok(())
+    }
+
+    pub fn is_mounted(&self, scheme: &str) -> bool {
+        self.mounts
+            .read()
+            .unwrap()
+            .iter()
+            .any(|mount| mount.scheme == scheme)
+    }
+
+    /// Read the whole file, parts of files like `scene.glb#mesh0` read the file they are in
+    pub fn read(&self, id: &str) -> Result<Vec<u8>, String> {
+        let mounts = self.mounts.read().unwrap();
+
+        match find(&mounts, source_path(id))? {
+            Location::File(path) => match std::fs::read(&path) {
+                Ok(bytes) => Ok(bytes),
+                Err(e) => Err(format!("{}: {}", source_path(id), e)),
+            },
+            Location::Pak(pak, name) => pak.read(&name),
+        }
+    }
+
+    pub fn read_to_string(&self, id: &str) -> Result<String, String> {
+        match String::from_utf8(self.read(id)?) {
+            Ok(text) => Ok(text),
+            Err(e) => Err(format!("{}: {}", source_path(id), e)),
+        }
+    }
+
+    /// Write the file to the newest directory mounted at its scheme, creating missing directories
+    pub fn write(&self, id: &str, bytes: &[u8]) -> Result<(), String> {
+        let path = match split_scheme(id) {
+            Some((scheme, path)) => {
+                let mounts = self.mounts.read().unwrap();
+
+                let directory = mounts
+                    .iter()
+                    .rev()
+                    .filter(|mount| mount.scheme == scheme)
+                    .find_map(|mount| match &mount.source {
+                        MountSource::Directory(directory) => Some(directory.clone()),
+                        MountSource::Pak(_) => None,
+                    });
+
+                match directory {
+                    Some(directory) => directory.join(mounted_name(id, scheme, path)?),
+                    None => {
+                        return Err(format!("{}: no directory is mounted at {}://", id, scheme))
+                    }
+                }
+            }
+            None => PathBuf::from(id),
+        };
+
+        let result = match path.parent() {
+            Some(parent) => std::fs::create_dir_all(parent),
+            None => Ok(()),
+        }
+        .and_then(|_| std::fs::write(&path, bytes));
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) => Err(format!("{}: {}", id, e)),
+        }
+    }
+
+    pub fn exists(&self, id: &str) -> bool {
+        find(&self.mounts.read().unwrap(), source_path(id)).is_ok()
+    }
+
+    /// The file on disk the id is read from, `None` when it is read from a pak archive
+    pub fn real_path(&self, id: &str) -> Option<PathBuf> {
+        match find(&self.mounts.read().unwrap(), source_path(id)) {
+            Ok(Location::File(path)) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Whether an asset id is read from the file at the canonical path
+    pub fn is_same_file(&self, id: &str, canonical_path: &Path) -> bool {
+        match self.real_path(id).map(|path| path.canonicalize()) {
+            Some(Ok(path)) => path == canonical_path,
+            _ => false,
+        }
+    }
+}
+
+impl Default for Vfs {
+    fn default() -> Self {
+        Vfs::new()
+    }
+}
+
+fn split_scheme(id: &str) -> Option<(&str, &str)> {
+    id.split_once("://")
+}
+
+// The name of a file inside a mount, which can't leave it by climbing out with `..` or by
+// being absolute, which `Path::join` would put in place of the mount's directory
+fn mounted_name(id: &str, scheme: &str, name: &str) -> Result<String, String> {
+    let name = normalize(name);
+
+    // UNC names like `\\server\share` start with `/` once normalized, drive letters don't
+    let has_drive =
+        name.as_bytes().get(1) == Some(&b':') && name.as_bytes()[0].is_ascii_alphabetic();
+
+    match name == ".." || name.starts_with("../") || name.starts_with('/') || has_drive {
+        true => Err(format!("{}: outside of {}://", id, scheme)),
+        false => Ok(name),
+    }
+}
+
+fn find<'a>(mounts: &'a [Mount], path: &str) -> Result<Location<'a>, String> {
+    let (scheme, name) = match split_scheme(path) {
+        Some((scheme, name)) => (scheme, mounted_name(path, scheme, name)?),
+        None => return Ok(Location::File(PathBuf::from(path))),
+    };
+
+    let mut mounted = false;
+
+    for mount in mounts.iter().rev().filter(|mount| mount.scheme == scheme) {
+        mounted = true;
+
+        match &mount.source {
+            MountSource::Directory(directory) => {
+                let file = directory.join(&name);
+
+                if file.is_file() {
+                    return Ok(Location::File(file));
+                }
+            }
+            MountSource::Pak(pak) => {
+                if pak.contains(&name) {
+                    return Ok(Location::Pak(pak, name));
+                }
+            }
+        }
+    }
+
+    match mounted {
+        true => Err(format!("{}: file not found", path)),
+        false => Err(format!("{}: nothing is mounted at {}://", path, scheme)),
+    }
+}
+
+// Resolve `.` and `..` and use `/` only, so the same file always has the same name
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut components: Vec<&str> = vec![];
+
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." if components.last().is_some_and(|&last| last != "..") => {
+                components.pop();
+            }
+            _ => components.push(component),
+        }
+    }
+
+    let normalized = components.join("/");
+
+    match path.starts_with('/') {
+        true => "/".to_owned() + &normalized,
+        false => normalized,
+    }
+}
+
+/// The id of a file referenced from inside another, like a texture named in a material library
+/// References are relative to the referencing file's directory, and may use `\` as separator
+/// Like a URL, a reference can't climb above the root of its scheme, extra `..` stop there
+pub fn relative_id(referencing_id: &str, reference: &str) -> String {
+    if split_scheme(reference).is_some() || Path::new(reference).is_absolute() {
+        return reference.to_owned();
+    }
+
+    let path = source_path(referencing_id);
+
+    let (scheme, path) = match split_scheme(path) {
+        Some((scheme, path)) => (Some(scheme), path),
+        None => (None, path),
+    };
+
+    let directory = match path.rfind('/') {
+        Some(end) => &path[..end + 1],
+        None => "",
+    };
+
+    let normalized = normalize(&(directory.to_owned() + reference));
+
+    match scheme {
+        Some(scheme) => {
+            let mut name = normalized.as_str();
+
+            while let Some(rest) = name.strip_prefix("../") {
+                name = rest;
+            }
+
+            if name == ".." {
+                name = "";
+            }
+
+            format!("{}://{}", scheme, name)
+        }
+        None => normalized,
+    }
+}
+
+// Relative mount paths are looked up next to the executable first, where shipping builds
+// keep their data, then in the working directory, where development runs usually start
+pub(crate) fn resolve_mount_path(path: &str) -> PathBuf {
+    let path = Path::new(path);
+
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    let beside_executable = std::env::current_exe()
+        .ok()
+        .and_then(|executable| executable.parent().map(|directory| directory.join(path)));
+
+    match beside_executable {
+        Some(beside_executable) if beside_executable.exists() => beside_executable,
+        _ => path.to_path_buf(),
+    }
+}
+
+// Where saves and settings go, following each platform's convention
+fn user_data_dir(app_name: &str) -> Option<PathBuf> {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(".local/share")))
+    };
+
+    base.map(|base| base.join(app_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_relative_id() {
+        assert_eq!(
+            relative_id(
+                "assets://models/bottle.gltf#mesh0",
+                "textures/../bottle.png"
+            ),
+            "assets://models/bottle.png"
+        );
+        assert_eq!(
+            relative_id("assets://cube.obj", "..\\shared\\cube.mtl"),
+            "assets://shared/cube.mtl"
+        );
+        assert_eq!(
+            relative_id("assets://models/cube.obj", "../../.."),
+            "assets://"
+        );
+        assert_eq!(
+            relative_id("models/cube.obj", "../../cube.mtl"),
+            "../cube.mtl"
+        );
+        assert_eq!(
+            relative_id("models/cube.obj", "./cube.mtl"),
+            "models/cube.mtl"
+        );
+        assert_eq!(
+            relative_id("models/cube.obj", "user://cube.png"),
+            "user://cube.png"
+        );
+    }
+
+    #[test]
+    fn test_newer_mounts_override_older() {
+        let base = temp_dir("vfs-base");
+        let patch = temp_dir("vfs-patch");
+        std::fs::write(base.join("a.txt"), "base a").unwrap();
+        std::fs::write(base.join("b.txt"), "base b").unwrap();
+
+        let pak = patch.join("patch.pak");
+        write_pak(
+            &mut std::fs::File::create(&pak).unwrap(),
+            &[("b.txt".to_owned(), b"patched b".to_vec())],
+        )
+        .unwrap();
+
+        let vfs = Vfs::new();
+        vfs.mount("assets", &base).unwrap();
+        vfs.mount("assets", &pak).unwrap();
+
+        assert_eq!(vfs.read_to_string("assets://a.txt").unwrap(), "base a");
+        assert_eq!(vfs.read_to_string("assets://./b.txt").unwrap(), "patched b");
+        assert_eq!(vfs.real_path("assets://a.txt"), Some(base.join("a.txt")));
+        assert_eq!(vfs.real_path("assets://b.txt"), None);
+        assert!(vfs.read("assets://c.txt").is_err());
+        assert!(vfs.read("other://a.txt").is_err());
+
+        std::fs::remove_dir_all(base).unwrap();
+        std::fs::remove_dir_all(patch).unwrap();
+    }
+
+    #[test]
+    fn test_write_to_directory_mount() {
+        let directory = temp_dir("vfs-write");
+
+        let vfs = Vfs::new();
+        vfs.mount("user", &directory).unwrap();
+
+        vfs.write("user://saves/slot0.sav", b"save").unwrap();
+
+        assert_eq!(vfs.read("user://saves/slot0.sav").unwrap(), b"save");
+        assert!(directory.join("saves/slot0.sav").is_file());
+        assert!(vfs.write("assets://a.txt", b"").is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_absolute_names_stay_inside_mounts() {
+        let directory = temp_dir("vfs-absolute");
+        let outside = temp_dir("vfs-absolute-outside");
+        std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+
+        let vfs = Vfs::new();
+        vfs.mount("user", &directory).unwrap();
+
+        let outside_id = format!("user://{}", outside.join("secret.txt").display());
+        assert!(vfs.read(&outside_id).is_err());
+        assert!(!vfs.exists(&outside_id));
+        assert!(vfs.read("user://C:\\secret.txt").is_err());
+        assert!(vfs.read("user://\\\\server\\share\\secret.txt").is_err());
+
+        let written = outside.join("written.txt");
+        assert!(vfs
+            .write(&format!("user://{}", written.display()), b"")
+            .is_err());
+        assert!(vfs.write("user://../written.txt", b"").is_err());
+        assert!(!written.exists());
+
+        std::fs::remove_dir_all(directory).unwrap();
+        std::fs::remove_dir_all(outside).unwrap();
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+const MAGIC: &[u8; 4] = b"RDPK";
+const VERSION: u32 = 1;
+
+struct PakEntry {
+    offset: u64,
+    size: u64,
+}
+
+/// A single file holding many assets, with an index of where each one is
+///
+/// The layout is a header (`RDPK`, version and entry count), then one index entry per file
+/// (path length, path, offset and size) and finally the file contents, all little endian.
+/// Paths use `/` and are relative to the mount point the archive is mounted at.
+pub struct PakArchive {
+    path: PathBuf,
+    file: Mutex<File>,
+    entries: HashMap<String, PakEntry>,
+}
+
+impl PakArchive {
+    pub fn open(path: &Path) -> Result<PakArchive, String> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+
+        let entries = match read_index(&mut file) {
+            Ok(entries) => entries,
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+
+        Ok(PakArchive {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            entries,
+        })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// Every file in the archive, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|name| name.as_str())
+    }
+
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, String> {
+        let entry = match self.entries.get(name) {
+            Some(entry) => entry,
+            None => return Err(format!("{} has no file {}", self.path.display(), name)),
+        };
+
+        let mut file = self.file.lock().unwrap();
+        let mut bytes = vec![0; entry.size as usize];
+
+        let result = file
+            .seek(SeekFrom::Start(entry.offset))
+            .and_then(|_| file.read_exact(&mut bytes));
+
+        match result {
+            Ok(()) => Ok(bytes),
+            Err(e) => Err(format!("{} in {}: {}", name, self.path.display(), e)),
+        }
+    }
+}
+
+fn read_bytes(reader: &mut impl Read, count: usize) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![0; count];
+
+    match reader.read_exact(&mut bytes) {
+        Ok(()) => Ok(bytes),
+        Err(_) => Err("truncated pak index".to_owned()),
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, String> {
+    Ok(u32::from_le_bytes(
+        read_bytes(reader, 4)?.try_into().unwrap(),
+    ))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, String> {
+    Ok(u64::from_le_bytes(
+        read_bytes(reader, 8)?.try_into().unwrap(),
+    ))
+}
+
+fn read_index(file: &mut File) -> Result<HashMap<String, PakEntry>, String> {
+    let file_size = match file.metadata() {
+        Ok(metadata) => metadata.len(),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    if read_bytes(file, MAGIC.len()).ok().as_deref() != Some(&MAGIC[..]) {
+        return Err("not a pak archive".to_owned());
+    }
+
+    let version = read_u32(file)?;
+    if version != VERSION {
+        return Err(format!("unsupported pak version {}", version));
+    }
+
+    let count = read_u32(file)?;
+    let mut entries = HashMap::new();
+
+    for _ in 0..count {
+        let name_length = read_u32(file)? as u64;
+        if name_length > file_size {
+            return Err("truncated pak index".to_owned());
+        }
+
+        let name = match String::from_utf8(read_bytes(file, name_length as usize)?) {
+            Ok(name) => name,
+            Err(_) => return Err("invalid file name in pak index".to_owned()),
+        };
+
+        let entry = PakEntry {
+            offset: read_u64(file)?,
+            size: read_u64(file)?,
+        };
+
+        if entry
+            .offset
+            .checked_add(entry.size)
+            .is_none_or(|end| end > file_size)
+        {
+            return Err(format!("{} is out of bounds", name));
+        }
+
+        entries.insert(name, entry);
+    }
+
+    Ok(entries)
+}
+
+/// Write the files as a pak archive, in the given order
+pub fn write_pak(writer: &mut impl Write, files: &[(String, Vec<u8>)]) -> std::io::Result<()> {
+    let index_size: usize = files.iter().map(|(name, _)| 4 + name.len() + 8 + 8).sum();
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&(files.len() as u32).to_le_bytes())?;
+
+    let mut offset = (MAGIC.len() + 4 + 4 + index_size) as u64;
+
+    for (name, bytes) in files {
+        writer.write_all(&(name.len() as u32).to_le_bytes())?;
+        writer.write_all(name.as_bytes())?;
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+
+        offset += bytes.len() as u64;
+    }
+
+    for (_, bytes) in files {
+        writer.write_all(bytes)?;
+    }
+
+    Ok(())
+}
+
+/// Pack every file under the directory into one archive, for shipping builds
+pub fn pack_directory(directory: &Path, output: &Path) -> Result<(), String> {
+    let mut files = vec![];
+
+    if let Err(e) = collect_files(directory, "", &mut files) {
+        return Err(format!("Failed to pack {}: {}", directory.display(), e));
+    }
+
+    // Sorted so packing the same files twice gives the same archive
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let result = File::create(output).and_then(|mut file| write_pak(&mut file, &files));
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("Failed to write {}: {}", output.display(), e)),
+    }
+}
+
+fn collect_files(
+    directory: &Path,
+    prefix: &str,
+    files: &mut Vec<(String, Vec<u8>)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = prefix.to_owned() + &entry.file_name().to_string_lossy();
+
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), &(name + "/"), files)?;
+        } else {
+            files.push((name, std::fs::read(entry.path())?));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_path;
+
+    #[test]
+    fn test_write_and_read() {
+        let path = temp_path("write-read.pak");
+        let files = vec![
+            ("models/cube.obj".to_owned(), b"v 0 0 0".to_vec()),
+            ("empty.txt".to_owned(), vec![]),
+        ];
+
+        write_pak(&mut File::create(&path).unwrap(), &files).unwrap();
+        let pak = PakArchive::open(&path).unwrap();
+
+        assert_eq!(pak.read("models/cube.obj").unwrap(), b"v 0 0 0");
+        assert_eq!(pak.read("empty.txt").unwrap(), b"");
+        assert!(!pak.contains("cube.obj"));
+        assert!(pak.read("cube.obj").is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_rejects_truncated() {
+        let path = temp_path("truncated.pak");
+        let mut bytes = vec![];
+        write_pak(&mut bytes, &[("a".to_owned(), vec![1, 2, 3])]).unwrap();
+        bytes.pop();
+        std::fs::write(&path, bytes).unwrap();
+
+        assert!(PakArchive::open(&path).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
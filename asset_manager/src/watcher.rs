@@ -0,0 +1,69 @@
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use log::{trace, warn};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Watches the asset roots and collects every file that was written to since the last poll
+pub struct AssetWatcher {
+    _watcher: RecommendedWatcher,
+    changed: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl AssetWatcher {
+    pub fn new(roots: &[PathBuf]) -> Result<AssetWatcher, String> {
+        let changed = Arc::new(Mutex::new(HashSet::new()));
+
+        let closure_changed = changed.clone();
+        let event_handler = move |result: notify::Result<notify::Event>| {
+            let event = match result {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Asset watcher error: {}", e);
+                    return;
+                }
+            };
+
+            // Editors often save by writing a new file and renaming it over the old one,
+            // so creation counts as a change too
+            if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
+                closure_changed.lock().unwrap().extend(event.paths);
+            }
+        };
+
+        let mut watcher = match notify::recommended_watcher(event_handler) {
+            Ok(watcher) => watcher,
+            Err(e) => return Err("Failed to create asset watcher: ".to_owned() + &e.to_string()),
+        };
+
+        for root in roots {
+            trace!("Watching asset root: {}", root.display());
+
+            if let Err(e) = watcher.watch(root, RecursiveMode::Recursive) {
+                return Err(format!(
+                    "Failed to watch asset root {}: {}",
+                    root.display(),
+                    e
+                ));
+            }
+        }
+
+        Ok(AssetWatcher {
+            _watcher: watcher,
+            changed,
+        })
+    }
+
+    /// Take the canonical paths of every file changed since the last call
+    pub fn poll(&self) -> Vec<PathBuf> {
+        self.changed
+            .lock()
+            .unwrap()
+            .drain()
+            .filter_map(|path| path.canonicalize().ok())
+            .collect()
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+};
+
+use log::{error, trace};
+
+/// Order in which queued loads are picked up by the loader threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LoadPriority {
+    Low,
+    Normal,
+    High,
+}
+
+type Work = Box<dyn FnOnce() + Send + 'static>;
+
+struct Job {
+    priority: LoadPriority,
+    sequence: u64,
+    work: Work,
+}
+
+// Highest priority first, then first in first out within the same priority
+impl Ord for Job {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.sequence == other.sequence
+    }
+}
+
+impl Eq for Job {}
+
+struct JobQueue {
+    jobs: BinaryHeap<Job>,
+    next_sequence: u64,
+    shutdown: bool,
+}
+
+struct Shared {
+    queue: Mutex<JobQueue>,
+    job_available: Condvar,
+}
+
+/// A fixed number of threads pulling load jobs off a shared priority queue
+pub struct WorkerPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    pub fn new(thread_count: usize) -> WorkerPool {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(JobQueue {
+                jobs: BinaryHeap::new(),
+                next_sequence: 0,
+                shutdown: false,
+            }),
+            job_available: Condvar::new(),
+        });
+
+        let workers = (0..thread_count.max(1))
+            .map(|index| {
+                let worker_shared = shared.clone();
+
+                thread::Builder::new()
+                    .name(format!("asset-loader-{}", index))
+                    .spawn(move || WorkerPool::run_worker(&worker_shared))
+                    .expect("Failed to spawn asset loader thread")
+            })
+            .collect();
+
+        WorkerPool { shared, workers }
+    }
+
+    pub fn submit(&self, priority: LoadPriority, work: impl FnOnce() + Send + 'static) {
+        let mut queue = self.shared.queue.lock().unwrap();
+
+        let sequence = queue.next_sequence;
+        queue.next_sequence += 1;
+
+        queue.jobs.push(Job {
+            priority,
+            sequence,
+            work: Box::new(work),
+        });
+
+        self.shared.job_available.notify_one();
+    }
+
+    fn run_worker(shared: &Shared) {
+        loop {
+            let job = {
+                let mut queue = shared.queue.lock().unwrap();
+
+                while queue.jobs.is_empty() && !queue.shutdown {
+                    queue = shared.job_available.wait(queue).unwrap();
+                }
+
+                if queue.shutdown {
+                    return;
+                }
+
+                queue.jobs.pop().unwrap()
+            };
+
+            // A job that panics is lost, the thread carries on with the next one
+            if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(job.work)) {
+                error!(
+                    "Asset loader job panicked: {}",
+                    crate::panic_message(&*panic)
+                );
+            }
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        trace!("Cleaning: Asset loader threads");
+
+        {
+            let mut queue = self.shared.queue.lock().unwrap();
+
+            // Anything not started yet is abandoned, there is nobody left to use it
+            queue.jobs.clear();
+            queue.shutdown = true;
+        }
+
+        self.shared.job_available.notify_all();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    #[test]
+    fn test_jobs_run_by_priority_then_order() {
+        let pool = WorkerPool::new(1);
+
+        // Keep the only worker busy so everything below is queued before it runs
+        let (release_sender, release_receiver) = channel::<()>();
+        pool.submit(LoadPriority::High, move || {
+            release_receiver.recv().unwrap();
+        });
+
+        let (order_sender, order_receiver) = channel();
+        for (priority, name) in [
+            (LoadPriority::Low, "low"),
+            (LoadPriority::Normal, "normal 1"),
+            (LoadPriority::High, "high"),
+            (LoadPriority::Normal, "normal 2"),
+        ] {
+            let sender = order_sender.clone();
+            pool.submit(priority, move || sender.send(name).unwrap());
+        }
+
+        release_sender.send(()).unwrap();
+
+        let order: Vec<&str> = order_receiver.iter().take(4).collect();
+
+        assert_eq!(order, vec!["high", "normal 1", "normal 2", "low"]);
+    }
+
+    #[test]
+    fn test_panicking_job_keeps_the_worker() {
+        let pool = WorkerPool::new(1);
+
+        pool.submit(LoadPriority::Normal, || panic!("broken loader"));
+
+        let (sender, receiver) = channel();
+        pool.submit(LoadPriority::Normal, move || sender.send(1).unwrap());
+
+        assert_eq!(receiver.recv().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_zero_threads_still_runs_jobs() {
+        let pool = WorkerPool::new(0);
+
+        let (sender, receiver) = channel();
+        pool.submit(LoadPriority::Normal, move || sender.send(1).unwrap());
+
+        assert_eq!(receiver.recv().unwrap(), 1);
+    }
+}
@@ -2,6 +2,10 @@
 pub struct Config {
     pub info: InfoConfig,
     pub renderer: RendererConfig,
+    #[serde(default)]
+    pub assets: AssetsConfig,
+    #[serde(default)]
+    pub audio: AudioConfig,
 }
 
 #[derive(serde_derive::Deserialize, Clone)]
@@ -17,6 +21,167 @@ pub struct RendererConfig {
     pub frame_overlap: u32,
 }
 
+#[derive(serde_derive::Deserialize, Clone)]
+#[serde(default)]
+pub struct AssetsConfig {
+    /// Number of threads used to load assets in the background
+    pub loader_threads: usize,
+    /// Reload meshes, sounds and shaders when they change on disk
+    pub hot_reload: bool,
+    /// Directories watched for changes when hot reloading is enabled
+    pub hot_reload_roots: Vec<String>,
+    /// Where ids like `assets://models/monkey.glb` are read from
+    pub mounts: Vec<MountConfig>,
+}
+
+#[derive(serde_derive::Deserialize, Clone)]
+pub struct MountConfig {
+    pub scheme: String,
+    /// A directory or pak archive, relative paths are looked up next to the executable first
+    pub path: String,
+}
+
+impl Default for AssetsConfig {
+    fn default() -> Self {
+        AssetsConfig {
+            loader_threads: 4,
+            hot_reload: false,
+            hot_reload_roots: vec!["assets".to_string()],
+            mounts: vec![MountConfig {
+                scheme: "assets".to_string(),
+                path: "assets".to_string(),
+            }],
+        }
+    }
+}
+
+#[derive(serde_derive::Deserialize, Clone)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Mix without playing anything, for machines without sound hardware
+    pub null_output: bool,
+    /// Sample rate of the mix, sounds are resampled to it
+    pub sample_rate: u32,
+    /// Buses sounds play through, all of them mix into a master bus
+    pub buses: Vec<BusConfig>,
+    /// Named sets of bus changes applied together, like muffling everything while paused
+    pub snapshots: Vec<SnapshotConfig>,
+}
+
+#[derive(serde_derive::Deserialize, Clone)]
+pub struct BusConfig {
+    pub name: String,
+    #[serde(default = "full_volume")]
+    pub volume: f32,
+    #[serde(default)]
+    pub muted: bool,
+    /// Cutoff frequency in Hz of a low-pass filter on the bus
+    #[serde(default)]
+    pub low_pass: Option<f32>,
+    /// How much of the bus is sent to the shared reverb, from 0 to 1
+    #[serde(default)]
+    pub reverb_send: f32,
+    /// Turn the bus down while another bus is playing, like music under dialogue
+    #[serde(default)]
+    pub ducking: Option<DuckingConfig>,
+}
+
+#[derive(serde_derive::Deserialize, Clone)]
+pub struct DuckingConfig {
+    /// The bus whose signal ducks this one
+    pub by: String,
+    /// Volume while ducked
+    pub volume: f32,
+    /// Level of the other bus, from 0 to 1, above which this bus ducks
+    #[serde(default = "duck_threshold")]
+    pub threshold: f32,
+    /// Seconds to duck once the other bus starts
+    #[serde(default = "duck_attack")]
+    pub attack: f32,
+    /// Seconds to come back once the other bus stops
+    #[serde(default = "duck_release")]
+    pub release: f32,
+}
+
+#[derive(serde_derive::Deserialize, Clone)]
+pub struct SnapshotConfig {
+    pub name: String,
+    pub buses: Vec<SnapshotBusConfig>,
+}
+
+/// Changes to a bus while a snapshot is applied, on top of its own settings
+#[derive(serde_derive::Deserialize, Clone)]
+pub struct SnapshotBusConfig {
+    pub bus: String,
+    #[serde(default = "full_volume")]
+    pub volume: f32,
+    #[serde(default)]
+    pub low_pass: Option<f32>,
+}
+
+fn full_volume() -> f32 {
+    1.0
+}
+
+fn duck_threshold() -> f32 {
+    0.05
+}
+
+fn duck_attack() -> f32 {
+    0.1
+}
+
+fn duck_release() -> f32 {
+    0.5
+}
+
+impl BusConfig {
+    pub fn new(name: &str) -> BusConfig {
+        BusConfig {
+            name: name.to_string(),
+            volume: 1.0,
+            muted: false,
+            low_pass: None,
+            reverb_send: 0.0,
+            ducking: None,
+        }
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            null_output: false,
+            sample_rate: 44100,
+            buses: vec![
+                BusConfig {
+                    ducking: Some(DuckingConfig {
+                        by: "voice".to_string(),
+                        volume: 0.4,
+                        threshold: duck_threshold(),
+                        attack: duck_attack(),
+                        release: duck_release(),
+                    }),
+                    ..BusConfig::new("music")
+                },
+                BusConfig::new("sfx"),
+                BusConfig::new("voice"),
+                BusConfig::new("ui"),
+            ],
+            snapshots: vec![SnapshotConfig {
+                name: "paused".to_string(),
+                buses: ["music", "sfx", "voice"]
+                    .map(|bus| SnapshotBusConfig {
+                        bus: bus.to_string(),
+                        volume: 0.5,
+                        low_pass: Some(800.0),
+                    })
+                    .to_vec(),
+            }],
+        }
+    }
+}
+
 impl Config {
     pub fn from_file(path: &str) -> Config {
         let contents = std::fs::read_to_string(path).expect("Failed to load config file");
@@ -39,6 +204,8 @@ impl Default for Config {
                 window_height: 600,
                 frame_overlap: 2,
             },
+            assets: AssetsConfig::default(),
+            audio: AudioConfig::default(),
         }
     }
 }
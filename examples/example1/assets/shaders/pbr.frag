@@ -0,0 +1,55 @@
+#version 450
+
+layout(location = 0) in vec3 inNormal;
+layout(location = 1) in vec4 inTangent;
+layout(location = 2) in vec2 inUv0;
+layout(location = 3) in vec4 inColor;
+
+layout(location = 0) out vec4 outFragColor;
+
+const uint ALPHA_MODE_OPAQUE = 0;
+const uint ALPHA_MODE_MASK = 1;
+
+layout(set = 0, binding = 0) uniform MaterialUniforms {
+    vec4 baseColorFactor;
+    vec4 emissiveFactor;
+    float metallicFactor;
+    float roughnessFactor;
+    float normalScale;
+    float occlusionStrength;
+    float alphaCutoff;
+    uint alphaMode;
+    uint doubleSided;
+} material;
+
+layout(set = 0, binding = 1) uniform sampler2D baseColorTexture;
+layout(set = 0, binding = 2) uniform sampler2D metallicRoughnessTexture;
+layout(set = 0, binding = 3) uniform sampler2D normalTexture;
+layout(set = 0, binding = 4) uniform sampler2D occlusionTexture;
+layout(set = 0, binding = 5) uniform sampler2D emissiveTexture;
+
+void main() {
+    vec4 baseColor = material.baseColorFactor * inColor * texture(baseColorTexture, inUv0);
+
+    if (material.alphaMode == ALPHA_MODE_MASK && baseColor.a < material.alphaCutoff) {
+        discard;
+    }
+
+    vec3 normal = normalize(inNormal);
+    vec3 tangent = normalize(inTangent.xyz);
+    vec3 bitangent = cross(normal, tangent) * inTangent.w;
+    vec3 tangentNormal = texture(normalTexture, inUv0).xyz * 2.0 - 1.0;
+    tangentNormal.xy *= material.normalScale;
+    normal = normalize(mat3(tangent, bitangent, normal) * tangentNormal);
+
+    float occlusion = 1.0 + material.occlusionStrength * (texture(occlusionTexture, inUv0).r - 1.0);
+
+    vec3 lightDirection = normalize(vec3(0.5, 1.0, 0.3));
+    float diffuse = max(dot(normal, lightDirection), 0.0) * 0.8 + 0.2 * occlusion;
+
+    vec3 emissive = material.emissiveFactor.rgb * texture(emissiveTexture, inUv0).rgb;
+    vec3 color = baseColor.rgb * diffuse + emissive;
+    float alpha = material.alphaMode == ALPHA_MODE_OPAQUE ? 1.0 : baseColor.a;
+
+    outFragColor = vec4(color, alpha);
+}
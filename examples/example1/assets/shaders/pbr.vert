@@ -0,0 +1,26 @@
+#version 450
+
+layout(location = 0) in vec3 vPosition;
+layout(location = 1) in vec3 vNormal;
+layout(location = 2) in vec4 vTangent;
+layout(location = 3) in vec2 vUv0;
+layout(location = 4) in vec2 vUv1;
+layout(location = 5) in vec4 vColor;
+
+layout(location = 0) out vec3 outNormal;
+layout(location = 1) out vec4 outTangent;
+layout(location = 2) out vec2 outUv0;
+layout(location = 3) out vec4 outColor;
+
+layout(push_constant) uniform constants {
+    vec4 data;
+    mat4 render_matrix;
+} PushConstants;
+
+void main() {
+    gl_Position = PushConstants.render_matrix * vec4(vPosition, 1.0);
+    outNormal = vNormal;
+    outTangent = vTangent;
+    outUv0 = vUv0;
+    outColor = vColor;
+}
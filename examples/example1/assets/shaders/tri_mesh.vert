@@ -1,17 +0,0 @@
-#version 450
-
-layout(location = 0) in vec3 vPosition;
-layout(location = 1) in vec3 vNormal;
-layout(location = 2) in vec3 vColor;
-
-layout(location = 0) out vec3 outColor;
-
-layout(push_constant) uniform constants {
-    vec4 data;
-    mat4 render_matrix;
-} PushConstants;
-
-void main() {
-    gl_Position = PushConstants.render_matrix * vec4(vPosition, 1.0);
-    outColor = vColor;
-}
\ No newline at end of file
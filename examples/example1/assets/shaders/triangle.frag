@@ -1,9 +0,0 @@
-#version 450
-
-//output write
-layout(location = 0) out vec4 outFragColor;
-
-void main() {
-	//return red
-    outFragColor = vec4(1., 0., 0., 1.0);
-}
\ No newline at end of file
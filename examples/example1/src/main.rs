@@ -1,12 +1,31 @@
 use std::f32::consts::PI;
 
 use raindrop::{
-    bevy_ecs::system::{Commands, Res},
-    components::{Camera, Material, Mesh, Player, Transform},
-    glm, Config, GameConfig, Raindrop, ScheduleType,
+    asset_manager::{self, LoadPriority, MeshSettings},
+    bevy_ecs::system::{Commands, NonSend, Res, ResMut},
+    components::{Camera, Lod, Material, Mesh, Player, Transform},
+    glm, AssetManagerResource, Config, GameConfig, Raindrop, RendererResource, ScheduleType,
 };
 
-fn init_scene(mut commands: Commands, config: Res<GameConfig>) {
+fn init_scene(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    mut asset_manager: ResMut<AssetManagerResource>,
+    renderer: NonSend<RendererResource>,
+) {
+    // The far away monkeys of the grid are drawn with simplified meshes
+    let monkey_mesh = asset_manager
+        .asset_manager
+        .load_with_settings::<asset_manager::Mesh>(
+            "assets://models/monkey/monkey.glb",
+            MeshSettings {
+                lod_levels: 3,
+                ..Default::default()
+            },
+            LoadPriority::Normal,
+        );
+    let default_material = renderer.renderer.get_material("defaultmesh").unwrap();
+
     commands.spawn((
         Camera::new(
             (config.config.renderer.window_width as f32)
@@ -21,11 +40,9 @@ fn init_scene(mut commands: Commands, config: Res<GameConfig>) {
 
     commands.spawn((
         Transform::new(),
-        Mesh {
-            id: "assets/models/monkey/monkey.glb".to_string(),
-        },
+        Mesh::new(monkey_mesh),
         Material {
-            id: "defaultmesh".to_string(),
+            id: default_material,
         },
     ));
 
@@ -35,16 +52,13 @@ fn init_scene(mut commands: Commands, config: Res<GameConfig>) {
             transform.set_translation(glm::vec3(x as f32 * 2.0, 0.0, y as f32 * 2.0));
             transform.set_scale(glm::vec3(0.2, 0.2, 0.2));
 
-            let mesh_str = "assets/models/monkey/monkey.glb";
-            
             commands.spawn((
                 transform,
-                Mesh {
-                    id: mesh_str.to_string(),
-                },
+                Mesh::new(monkey_mesh),
                 Material {
-                    id: "defaultmesh".to_string(),
+                    id: default_material,
                 },
+                Lod::new(vec![6.0, 12.0, 18.0]),
             ));
         }
     }
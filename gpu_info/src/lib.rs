@@ -1,5 +1,9 @@
 mod buffer;
 mod image;
+mod mesh_buffers;
+mod texture_image;
 
 pub use buffer::Buffer;
 pub use image::Image;
+pub use mesh_buffers::MeshBuffers;
+pub use texture_image::TextureImage;
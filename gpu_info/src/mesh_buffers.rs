@@ -0,0 +1,8 @@
+use crate::Buffer;
+
+pub struct MeshBuffers {
+    pub vertex_buffer: Buffer,
+    pub index_buffer: Buffer,
+    pub index_type: ash::vk::IndexType,
+    pub index_count: u32,
+}
@@ -0,0 +1,8 @@
+use crate::Image;
+
+pub struct TextureImage {
+    pub image: Image,
+    pub view: ash::vk::ImageView,
+    pub sampler: ash::vk::Sampler,
+    pub mip_levels: u32,
+}
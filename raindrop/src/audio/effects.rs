@@ -0,0 +1,186 @@
+use std::f32::consts::TAU;
+
+/// A one-pole low-pass filter on both channels, muffles a bus like it was behind a wall
+pub struct LowPass {
+    coefficient: f32,
+    state: [f32; 2],
+}
+
+impl LowPass {
+    pub fn new(cutoff: f32, sample_rate: u32) -> LowPass {
+        let mut low_pass = LowPass {
+            coefficient: 1.0,
+            state: [0.0; 2],
+        };
+        low_pass.set_cutoff(cutoff, sample_rate);
+
+        low_pass
+    }
+
+    /// Keeps the filter's state, so the cutoff can move while the bus plays
+    pub fn set_cutoff(&mut self, cutoff: f32, sample_rate: u32) {
+        self.coefficient = 1.0 - (-TAU * cutoff.max(0.0) / sample_rate as f32).exp();
+    }
+
+    /// Filter interleaved stereo frames in place
+    pub fn process(&mut self, frames: &mut [f32]) {
+        for frame in frames.chunks_exact_mut(2) {
+            for (sample, state) in frame.iter_mut().zip(&mut self.state) {
+                *state += self.coefficient * (*sample - *state);
+                *sample = *state;
+            }
+        }
+    }
+}
+
+// A delay line feeding back into itself through a low-pass, rings at its length
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    damped: f32,
+}
+
+impl Comb {
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+
+        self.damped = output * (1.0 - damping) + self.damped * damping;
+        self.buffer[self.index] = input + self.damped * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+
+        output
+    }
+}
+
+// Smears echoes out in time without coloring them
+struct AllPass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl AllPass {
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        let output = delayed - input;
+
+        self.buffer[self.index] = input + delayed * 0.5;
+        self.index = (self.index + 1) % self.buffer.len();
+
+        output
+    }
+}
+
+// Delay lengths in frames at 44.1kHz, the right channel's are a little longer so the
+// sides don't ring together
+const COMB_LENGTHS: [usize; 4] = [1116, 1188, 1277, 1356];
+const ALL_PASS_LENGTHS: [usize; 2] = [556, 441];
+const STEREO_SPREAD: usize = 23;
+
+/// A small room reverb, buses send part of their signal to the one the mixer shares
+pub struct Reverb {
+    combs: [Vec<Comb>; 2],
+    all_passes: [Vec<AllPass>; 2],
+    /// How long the reverb rings, from 0 to 1
+    pub room_size: f32,
+    /// How fast high frequencies die out, from 0 to 1
+    pub damping: f32,
+}
+
+impl Reverb {
+    pub fn new(sample_rate: u32) -> Reverb {
+        let scale = |length: usize| (length * sample_rate as usize / 44100).max(1);
+
+        let channel = |spread: usize| {
+            let combs = Vec::from(COMB_LENGTHS.map(|length| Comb {
+                buffer: vec![0.0; scale(length + spread)],
+                index: 0,
+                damped: 0.0,
+            }));
+            let all_passes = Vec::from(ALL_PASS_LENGTHS.map(|length| AllPass {
+                buffer: vec![0.0; scale(length + spread)],
+                index: 0,
+            }));
+
+            (combs, all_passes)
+        };
+
+        let (left_combs, left_all_passes) = channel(0);
+        let (right_combs, right_all_passes) = channel(STEREO_SPREAD);
+
+        Reverb {
+            combs: [left_combs, right_combs],
+            all_passes: [left_all_passes, right_all_passes],
+            room_size: 0.5,
+            damping: 0.5,
+        }
+    }
+
+    /// Add the reverb of the interleaved stereo input to the output
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) {
+        let feedback = 0.7 + self.room_size.clamp(0.0, 1.0) * 0.28;
+        let damping = self.damping.clamp(0.0, 1.0) * 0.4;
+
+        for (input, output) in input.chunks_exact(2).zip(output.chunks_exact_mut(2)) {
+            // Both sides ring from the same mono input, it's their delays that differ
+            let mono = (input[0] + input[1]) * 0.5;
+
+            for (channel, output) in output.iter_mut().enumerate() {
+                let mut wet: f32 = self.combs[channel]
+                    .iter_mut()
+                    .map(|comb| comb.process(mono, feedback, damping))
+                    .sum();
+
+                for all_pass in &mut self.all_passes[channel] {
+                    wet = all_pass.process(wet);
+                }
+
+                *output += wet / COMB_LENGTHS.len() as f32;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Interleaved stereo frames of a sine wave at the frequency
+    fn sine(frequency: f32, sample_rate: u32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|frame| (TAU * frequency * frame as f32 / sample_rate as f32).sin())
+            .flat_map(|sample| [sample, sample])
+            .collect()
+    }
+
+    fn peak(frames: &[f32]) -> f32 {
+        frames
+            .iter()
+            .fold(0.0, |peak, sample| sample.abs().max(peak))
+    }
+
+    #[test]
+    fn test_low_pass_keeps_low_frequencies() {
+        let mut low = sine(100.0, 44100, 4410);
+        let mut high = sine(10000.0, 44100, 4410);
+
+        LowPass::new(500.0, 44100).process(&mut low);
+        LowPass::new(500.0, 44100).process(&mut high);
+
+        // Past the filter settling in
+        assert!(peak(&low[4000..]) > 0.9);
+        assert!(peak(&high[4000..]) < 0.1);
+    }
+
+    #[test]
+    fn test_reverb_rings_after_the_input() {
+        let mut input = vec![0.0; 44100 * 2];
+        input[..2].copy_from_slice(&[1.0, 1.0]);
+
+        let mut output = vec![0.0; input.len()];
+        Reverb::new(44100).process(&input, &mut output);
+
+        // Nothing comes back before the shortest delay, then it keeps ringing
+        assert_eq!(peak(&output[..1000]), 0.0);
+        assert!(peak(&output[2000..10000]) > 0.01);
+    }
+}
@@ -0,0 +1,703 @@
+use std::{collections::HashMap, task::Poll};
+
+use asset_manager::{MusicControl, MusicStream, SoundBuffer};
+use config::{AudioConfig, BusConfig};
+use log::warn;
+use rodio::Source;
+
+use super::effects::{LowPass, Reverb};
+
+/// A voice playing in the mixer, stays valid after the voice has finished
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VoiceId(u64);
+
+// Music pulled from its stream a frame at a time as the mix needs it
+struct StreamVoice {
+    stream: MusicStream,
+    control: MusicControl,
+    /// What the stream was last told, so it is only told again when the voice changes
+    looping: Option<bool>,
+    /// The stereo frame being played and the one after it, `None` past the end
+    frames: [Option<[f32; 2]>; 2],
+    /// Between the two frames while resampling, past them until the first frames are pulled
+    fraction: f64,
+}
+
+impl StreamVoice {
+    // Nothing is pulled yet, so starting the voice doesn't wait for the stream to decode
+    fn new(stream: MusicStream) -> StreamVoice {
+        StreamVoice {
+            control: stream.control(),
+            stream,
+            looping: None,
+            frames: [None, None],
+            fraction: 2.0,
+        }
+    }
+}
+
+// Mono is played on both sides, anything past stereo is left out
+// Never waits for decoding, the mixer runs on the audio thread with its lock held
+// Decoding sends whole frames, so it only falls behind between two frames
+fn next_frame(stream: &mut MusicStream) -> Poll<Option<[f32; 2]>> {
+    let channels = stream.channels().max(1) as usize;
+    let mut frame = [0.0; 2];
+
+    for channel in 0..channels {
+        let sample = match stream.try_next() {
+            Poll::Ready(Some(sample)) => sample as f32,
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        if let Some(side) = frame.get_mut(channel) {
+            *side = sample;
+        }
+    }
+
+    if channels == 1 {
+        frame[1] = frame[0];
+    }
+
+    Poll::Ready(Some(frame))
+}
+
+// What a voice plays
+enum VoiceSource {
+    /// Frames into the buffer, between two while resampling
+    Buffer(SoundBuffer, f64),
+    Stream(Box<StreamVoice>),
+}
+
+/// A sound or music playing through a bus
+pub struct Voice {
+    source: VoiceSource,
+    bus: usize,
+    pub volume: f32,
+    /// Playback speed on top of the resampling to the mix's sample rate
+    pub pitch: f32,
+    /// Volume of the left and right channel
+    pub pan_gains: [f32; 2],
+    /// Start over at the end, otherwise the voice is removed
+    pub looping: bool,
+    pub paused: bool,
+}
+
+impl Voice {
+    // Mix the voice into the interleaved stereo frames, returns whether it has finished
+    fn mix(&mut self, frames: &mut [f32], sample_rate: u32) -> bool {
+        let gains = self.pan_gains.map(|gain| gain * self.volume / 32768.0);
+        let pitch = self.pitch.max(0.0) as f64;
+
+        match &mut self.source {
+            VoiceSource::Buffer(buffer, position) => Voice::mix_buffer(
+                buffer,
+                position,
+                self.looping,
+                frames,
+                pitch / sample_rate as f64,
+                gains,
+            ),
+            VoiceSource::Stream(stream) => {
+                // The stream loops on its own, between the music's loop points
+                if stream.looping != Some(self.looping) {
+                    stream.control.set_looping(self.looping);
+                    stream.looping = Some(self.looping);
+                }
+
+                Voice::mix_stream(stream, frames, pitch / sample_rate as f64, gains)
+            }
+        }
+    }
+
+    // The step is how far the voice moves per output frame, per frame per second of the source
+    fn mix_buffer(
+        buffer: &SoundBuffer,
+        position: &mut f64,
+        looping: bool,
+        frames: &mut [f32],
+        step: f64,
+        gains: [f32; 2],
+    ) -> bool {
+        let frame_count = buffer.frame_count();
+        let channels = buffer.channels as usize;
+
+        if frame_count == 0 {
+            return true;
+        }
+
+        let step = step * buffer.sample_rate as f64;
+
+        // Mono is played on both sides, anything past stereo is left out
+        let sample = |frame: usize, channel: usize| {
+            buffer.samples[frame * channels + channel.min(channels - 1)] as f32
+        };
+
+        for output in frames.chunks_exact_mut(2) {
+            if *position >= frame_count as f64 {
+                match looping {
+                    true => *position %= frame_count as f64,
+                    false => return true,
+                }
+            }
+
+            let frame = *position as usize;
+            let fraction = (*position - frame as f64) as f32;
+            let next = match (frame + 1 < frame_count, looping) {
+                (true, _) => Some(frame + 1),
+                (false, true) => Some(0),
+                (false, false) => None,
+            };
+
+            for (channel, output) in output.iter_mut().enumerate() {
+                let current = sample(frame, channel);
+                let next = next.map_or(0.0, |next| sample(next, channel));
+
+                *output += (current + (next - current) * fraction) * gains[channel];
+            }
+
+            *position += step;
+        }
+
+        false
+    }
+
+    fn mix_stream(
+        stream: &mut StreamVoice,
+        frames: &mut [f32],
+        step: f64,
+        gains: [f32; 2],
+    ) -> bool {
+        let step = step * stream.stream.sample_rate() as f64;
+
+        for output in frames.chunks_exact_mut(2) {
+            while stream.fraction >= 1.0 {
+                match next_frame(&mut stream.stream) {
+                    Poll::Ready(frame) => {
+                        stream.frames = [stream.frames[1], frame];
+                        stream.fraction -= 1.0;
+                    }
+                    // Silent until decoding catches up
+                    Poll::Pending => break,
+                }
+            }
+
+            if stream.fraction >= 1.0 {
+                continue;
+            }
+
+            let [Some(current), next] = stream.frames else {
+                return true;
+            };
+            let next = next.unwrap_or([0.0; 2]);
+            let fraction = stream.fraction as f32;
+
+            for (channel, output) in output.iter_mut().enumerate() {
+                let (current, next) = (current[channel], next[channel]);
+
+                *output += (current + (next - current) * fraction) * gains[channel];
+            }
+
+            stream.fraction += step;
+        }
+
+        false
+    }
+}
+
+// Turns a bus down while another bus is louder than the threshold
+struct Ducking {
+    by: usize,
+    volume: f32,
+    threshold: f32,
+    attack: f32,
+    release: f32,
+    /// Where the ducking is between 1 and `volume`
+    gain: f32,
+}
+
+/// A group of voices with its own volume and effects, mixed into the master bus
+pub struct Bus {
+    pub name: String,
+    pub volume: f32,
+    pub muted: bool,
+    /// Cutoff frequency in Hz of a low-pass filter on the bus
+    pub low_pass: Option<f32>,
+    /// How much of the bus is sent to the shared reverb, from 0 to 1
+    pub reverb_send: f32,
+    ducking: Option<Ducking>,
+    filter: Option<LowPass>,
+    // The gain applied at the end of the last block, so volume changes ramp instead of click
+    gain: f32,
+    /// Peak of the bus's voices in the last block, what ducks other buses
+    level: f32,
+    frames: Vec<f32>,
+}
+
+impl Bus {
+    fn new(config: &BusConfig) -> Bus {
+        Bus {
+            name: config.name.clone(),
+            volume: config.volume,
+            muted: config.muted,
+            low_pass: config.low_pass,
+            reverb_send: config.reverb_send,
+            ducking: None,
+            filter: None,
+            gain: match config.muted {
+                true => 0.0,
+                false => config.volume,
+            },
+            level: 0.0,
+            frames: vec![],
+        }
+    }
+
+    /// How loud the bus's voices were in the last mixed block, from 0 to 1
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    // Apply the volume, ducking and filter to the bus's frames
+    fn process(&mut self, volume: f32, low_pass: Option<f32>, ducked: bool, sample_rate: u32) {
+        let target = match self.muted {
+            true => 0.0,
+            false => self.volume * volume,
+        };
+
+        let frame_count = (self.frames.len() / 2).max(1);
+        let ramp = (target - self.gain) / frame_count as f32;
+
+        for (index, frame) in self.frames.chunks_exact_mut(2).enumerate() {
+            let mut gain = self.gain + ramp * (index + 1) as f32;
+
+            if let Some(ducking) = &mut self.ducking {
+                let (goal, time) = match ducked {
+                    true => (ducking.volume, ducking.attack),
+                    false => (1.0, ducking.release),
+                };
+                let step = (1.0 - ducking.volume).abs() / (time * sample_rate as f32).max(1.0);
+
+                ducking.gain = match goal < ducking.gain {
+                    true => (ducking.gain - step).max(goal),
+                    false => (ducking.gain + step).min(goal),
+                };
+                gain *= ducking.gain;
+            }
+
+            frame[0] *= gain;
+            frame[1] *= gain;
+        }
+
+        self.gain = target;
+
+        match low_pass {
+            Some(cutoff) => {
+                let filter = self
+                    .filter
+                    .get_or_insert_with(|| LowPass::new(cutoff, sample_rate));
+                filter.set_cutoff(cutoff, sample_rate);
+                filter.process(&mut self.frames);
+            }
+            None => self.filter = None,
+        }
+    }
+}
+
+// Bus changes a snapshot makes: the bus, a volume it is scaled by and a low-pass cutoff
+struct Snapshot {
+    name: String,
+    buses: Vec<(usize, f32, Option<f32>)>,
+}
+
+/// Mixes voices through buses into interleaved stereo frames, either for the audio device
+/// or into a buffer
+pub struct Mixer {
+    sample_rate: u32,
+    /// The master bus comes first, everything else mixes into it
+    buses: Vec<Bus>,
+    snapshots: Vec<Snapshot>,
+    snapshot: Option<usize>,
+    voices: HashMap<VoiceId, Voice>,
+    next_voice: u64,
+    reverb: Reverb,
+    reverb_frames: Vec<f32>,
+}
+
+impl Mixer {
+    pub const MASTER: usize = 0;
+
+    /// A master bus, configured too if the config has a bus named "master", and the
+    /// config's buses
+    pub fn new(config: &AudioConfig) -> Mixer {
+        let sample_rate = config.sample_rate.max(1);
+
+        let mut buses = vec![Bus::new(&BusConfig::new("master"))];
+        for bus in &config.buses {
+            match bus.name == "master" {
+                true => buses[Mixer::MASTER] = Bus::new(bus),
+                false => buses.push(Bus::new(bus)),
+            }
+        }
+
+        let find = |name: &str| buses.iter().position(|bus| bus.name == name);
+
+        let duckings: Vec<_> = config
+            .buses
+            .iter()
+            .filter_map(|bus| Some((find(&bus.name)?, bus.ducking.as_ref()?)))
+            .filter_map(|(bus, ducking)| match find(&ducking.by) {
+                Some(by) if by != bus => Some((
+                    bus,
+                    Ducking {
+                        by,
+                        volume: ducking.volume,
+                        threshold: ducking.threshold,
+                        attack: ducking.attack,
+                        release: ducking.release,
+                        gain: 1.0,
+                    },
+                )),
+                _ => {
+                    warn!("Bus {} can't be ducked by {}", buses[bus].name, ducking.by);
+                    None
+                }
+            })
+            .collect();
+
+        let snapshots = config
+            .snapshots
+            .iter()
+            .map(|snapshot| Snapshot {
+                name: snapshot.name.clone(),
+                buses: snapshot
+                    .buses
+                    .iter()
+                    .filter_map(|change| match find(&change.bus) {
+                        Some(bus) => Some((bus, change.volume, change.low_pass)),
+                        None => {
+                            warn!(
+                                "Snapshot {} changes unknown bus {}",
+                                snapshot.name, change.bus
+                            );
+                            None
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        for (bus, ducking) in duckings {
+            buses[bus].ducking = Some(ducking);
+        }
+
+        Mixer {
+            sample_rate,
+            buses,
+            snapshots,
+            snapshot: None,
+            voices: HashMap::new(),
+            next_voice: 0,
+            reverb: Reverb::new(sample_rate),
+            reverb_frames: vec![],
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The index of the bus with the name
+    pub fn bus(&self, name: &str) -> Option<usize> {
+        self.buses.iter().position(|bus| bus.name == name)
+    }
+
+    pub fn buses(&self) -> &[Bus] {
+        &self.buses
+    }
+
+    pub fn bus_mut(&mut self, name: &str) -> Option<&mut Bus> {
+        self.buses.iter_mut().find(|bus| bus.name == name)
+    }
+
+    pub fn reverb_mut(&mut self) -> &mut Reverb {
+        &mut self.reverb
+    }
+
+    /// Apply the snapshot's bus changes instead of the ones applied before
+    pub fn apply_snapshot(&mut self, name: &str) -> Result<(), String> {
+        match self
+            .snapshots
+            .iter()
+            .position(|snapshot| snapshot.name == name)
+        {
+            Some(snapshot) => {
+                self.snapshot = Some(snapshot);
+                Ok(())
+            }
+            None => Err(format!("No audio snapshot named {}", name)),
+        }
+    }
+
+    pub fn clear_snapshot(&mut self) {
+        self.snapshot = None;
+    }
+
+    /// Start playing the buffer from the start through the bus
+    pub fn play(&mut self, buffer: SoundBuffer, bus: usize) -> VoiceId {
+        self.add_voice(VoiceSource::Buffer(buffer, 0.0), bus)
+    }
+
+    /// Play the music through the bus, pulling it from the stream as it is mixed
+    /// The voice finishes when the stream ends, its looping is handed on to the stream
+    pub fn play_stream(&mut self, stream: MusicStream, bus: usize) -> VoiceId {
+        self.add_voice(VoiceSource::Stream(Box::new(StreamVoice::new(stream))), bus)
+    }
+
+    fn add_voice(&mut self, source: VoiceSource, bus: usize) -> VoiceId {
+        let id = VoiceId(self.next_voice);
+        self.next_voice += 1;
+
+        self.voices.insert(
+            id,
+            Voice {
+                source,
+                bus: bus.min(self.buses.len() - 1),
+                volume: 1.0,
+                pitch: 1.0,
+                pan_gains: [1.0, 1.0],
+                looping: false,
+                paused: false,
+            },
+        );
+
+        id
+    }
+
+    /// `None` once the voice has finished or was stopped
+    pub fn voice_mut(&mut self, id: VoiceId) -> Option<&mut Voice> {
+        self.voices.get_mut(&id)
+    }
+
+    pub fn stop(&mut self, id: VoiceId) {
+        self.voices.remove(&id);
+    }
+
+    /// Mix the next interleaved stereo frames, overwriting the output
+    pub fn render(&mut self, output: &mut [f32]) {
+        let sample_count = output.len() - output.len() % 2;
+
+        for bus in &mut self.buses {
+            bus.frames.clear();
+            bus.frames.resize(sample_count, 0.0);
+        }
+
+        let sample_rate = self.sample_rate;
+        let buses = &mut self.buses;
+        self.voices.retain(|_, voice| {
+            voice.paused || !voice.mix(&mut buses[voice.bus].frames, sample_rate)
+        });
+
+        for bus in &mut self.buses {
+            bus.level = bus
+                .frames
+                .iter()
+                .fold(0.0, |peak, sample| sample.abs().max(peak));
+        }
+
+        let levels: Vec<f32> = self.buses.iter().map(|bus| bus.level).collect();
+        let snapshot = self.snapshot.map(|snapshot| &self.snapshots[snapshot]);
+
+        self.reverb_frames.clear();
+        self.reverb_frames.resize(sample_count, 0.0);
+
+        // Buses mix into the master bus after their own effects, the master bus goes last
+        for index in (0..self.buses.len()).rev() {
+            let (volume, snapshot_low_pass) = snapshot
+                .and_then(|snapshot| snapshot.buses.iter().find(|change| change.0 == index))
+                .map_or((1.0, None), |change| (change.1, change.2));
+
+            let bus = &mut self.buses[index];
+
+            // The lower of the bus's own cutoff and the snapshot's muffles the most
+            let low_pass = match (bus.low_pass, snapshot_low_pass) {
+                (Some(own), Some(snapshot)) => Some(own.min(snapshot)),
+                (own, snapshot) => own.or(snapshot),
+            };
+            let ducked = bus
+                .ducking
+                .as_ref()
+                .is_some_and(|ducking| levels[ducking.by] > ducking.threshold);
+
+            if index == Mixer::MASTER {
+                self.reverb.process(&self.reverb_frames, &mut bus.frames);
+            }
+
+            bus.process(volume, low_pass, ducked, sample_rate);
+
+            if index == Mixer::MASTER {
+                break;
+            }
+
+            let send = bus.reverb_send;
+            let frames = std::mem::take(&mut bus.frames);
+
+            for (reverb, sample) in self.reverb_frames.iter_mut().zip(&frames) {
+                *reverb += sample * send;
+            }
+            for (master, sample) in self.buses[Mixer::MASTER].frames.iter_mut().zip(&frames) {
+                *master += sample;
+            }
+
+            self.buses[index].frames = frames;
+        }
+
+        for (output, sample) in output.iter_mut().zip(&self.buses[Mixer::MASTER].frames) {
+            *output = sample.clamp(-1.0, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use asset_manager::{
+        test_support::{temp_path, wav},
+        AssetLoader, Music, MusicSettings, Vfs,
+    };
+
+    use super::*;
+
+    // Half a second of full scale DC, easy to follow through gains
+    fn buffer() -> SoundBuffer {
+        SoundBuffer {
+            samples: vec![16384; 4000].into(),
+            sample_rate: 8000,
+            channels: 1,
+        }
+    }
+
+    fn config() -> AudioConfig {
+        AudioConfig {
+            sample_rate: 8000,
+            ..AudioConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_bus_volume_and_mute() {
+        let mut mixer = Mixer::new(&config());
+        let sfx = mixer.bus("sfx").unwrap();
+
+        let voice = mixer.play(buffer(), sfx);
+        mixer.voice_mut(voice).unwrap().pan_gains = [1.0, 0.5];
+
+        let mut output = vec![0.0; 200];
+        mixer.render(&mut output);
+        assert_eq!(&output[..4], [0.5, 0.25, 0.5, 0.25]);
+
+        mixer.bus_mut("sfx").unwrap().volume = 0.5;
+        mixer.render(&mut output);
+        mixer.render(&mut output);
+        assert_eq!(&output[..2], [0.25, 0.125]);
+
+        mixer.bus_mut("sfx").unwrap().muted = true;
+        mixer.render(&mut output);
+        mixer.render(&mut output);
+        assert!(output.iter().all(|&sample| sample == 0.0));
+
+        // Voices finish at the end of their buffer
+        let mut output = vec![0.0; 8000];
+        mixer.render(&mut output);
+        assert!(mixer.voice_mut(voice).is_none());
+    }
+
+    #[test]
+    fn test_stream_voice_plays_until_the_stream_ends() {
+        let path = temp_path("mixer-music.wav");
+        std::fs::write(&path, wav(1, 8000, &[16384; 4000])).unwrap();
+        let path = path.to_str().unwrap();
+
+        let track = Music::read(path, &MusicSettings::default(), &Vfs::new()).unwrap();
+
+        let mut mixer = Mixer::new(&config());
+        let music = mixer.bus("music").unwrap();
+        let voice = mixer.play_stream(MusicStream::new(track, false), music);
+
+        // Silent until the first chunk has decoded, the mixer doesn't wait for it
+        let mut output = vec![0.0; 200];
+        for _ in 0..1000 {
+            mixer.render(&mut output);
+
+            if output[0] != 0.0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(output.iter().all(|&sample| sample == 0.5));
+
+        let mut output = vec![0.0; 8000];
+        mixer.render(&mut output);
+        assert_eq!(output[8000 - 200 - 2], 0.5);
+        assert_eq!(output[8000 - 200], 0.0);
+        assert!(mixer.voice_mut(voice).is_none());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_voice_ducks_music() {
+        let mut mixer = Mixer::new(&config());
+        let (music, voice) = (mixer.bus("music").unwrap(), mixer.bus("voice").unwrap());
+
+        let song = mixer.play(buffer(), music);
+        mixer.voice_mut(song).unwrap().looping = true;
+
+        let mut output = vec![0.0; 200];
+        mixer.render(&mut output);
+        assert_eq!(output[0], 0.5);
+
+        // Only on the right, so the left side is the music alone
+        let line = mixer.play(buffer(), voice);
+        mixer.voice_mut(line).unwrap().looping = true;
+        mixer.voice_mut(line).unwrap().pan_gains = [0.0, 1.0];
+
+        // Ducks over the attack time while the voice bus plays, and comes back after
+        let mut output = vec![0.0; 8000 * 2];
+        mixer.render(&mut output);
+        assert!((output[output.len() - 2] - 0.5 * 0.4).abs() < 1e-6);
+
+        mixer.stop(line);
+        mixer.render(&mut output);
+        assert!(output[0] < 0.5);
+        assert_eq!(output[output.len() - 2], 0.5);
+    }
+
+    #[test]
+    fn test_snapshot_muffles_buses() {
+        let mut mixer = Mixer::new(&config());
+        let sfx = mixer.bus("sfx").unwrap();
+
+        // A square wave at the Nyquist frequency, as high as the mix goes
+        let buffer = SoundBuffer {
+            samples: (0..4000).map(|i| [16384, -16384][i % 2]).collect(),
+            sample_rate: 8000,
+            channels: 1,
+        };
+        let voice = mixer.play(buffer, sfx);
+        mixer.voice_mut(voice).unwrap().looping = true;
+
+        let mut output = vec![0.0; 2000];
+        mixer.render(&mut output);
+        assert_eq!(output[1998].abs(), 0.5);
+
+        assert!(mixer.apply_snapshot("unknown").is_err());
+        mixer.apply_snapshot("paused").unwrap();
+        mixer.render(&mut output);
+        mixer.render(&mut output);
+        assert!(output.iter().all(|sample| sample.abs() < 0.1));
+
+        mixer.clear_snapshot();
+        mixer.render(&mut output);
+        assert!((output[1998].abs() - 0.5).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,70 @@
+mod effects;
+mod mixer;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rodio::Source;
+
+pub use effects::{LowPass, Reverb};
+pub use mixer::{Bus, Mixer, Voice, VoiceId};
+
+// Frames mixed at a time for the audio device
+const BLOCK_FRAMES: usize = 512;
+
+/// Plays the mixer on the audio device, which pulls frames as it needs them
+pub(crate) struct MixerSource {
+    mixer: Arc<Mutex<Mixer>>,
+    sample_rate: u32,
+    block: Vec<f32>,
+    index: usize,
+}
+
+impl MixerSource {
+    pub fn new(mixer: Arc<Mutex<Mixer>>) -> MixerSource {
+        let sample_rate = mixer.lock().unwrap().sample_rate();
+
+        MixerSource {
+            mixer,
+            sample_rate,
+            block: vec![0.0; BLOCK_FRAMES * 2],
+            index: BLOCK_FRAMES * 2,
+        }
+    }
+}
+
+impl Iterator for MixerSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        // The mixer is only locked once a block, the game locks it to change voices
+        if self.index == self.block.len() {
+            self.mixer.lock().unwrap().render(&mut self.block);
+            self.index = 0;
+        }
+
+        self.index += 1;
+
+        Some(self.block[self.index - 1])
+    }
+}
+
+impl Source for MixerSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
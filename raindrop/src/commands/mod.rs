@@ -0,0 +1,3 @@
+pub mod spawn_gltf_scene;
+
+pub use spawn_gltf_scene::SpawnGltfScene;
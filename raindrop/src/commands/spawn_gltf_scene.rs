@@ -0,0 +1,159 @@
+use asset_manager::{GltfScene, Handle, SceneCamera, SceneNode};
+use bevy_ecs::{
+    entity::Entity,
+    system::Commands,
+    world::{Command, World},
+};
+use log::warn;
+
+use crate::{
+    components::{Camera, Children, Light, Material, Mesh, Name, Parent, Transform},
+    resources::{AssetManagerResource, GameConfig, RendererResource},
+};
+
+/// Spawn every node of a glTF file as its own entity, keeping the node hierarchy
+pub trait SpawnGltfScene {
+    /// Returns the root entity all of the scene's nodes are children of
+    /// The nodes are spawned when the commands are applied
+    fn spawn_gltf_scene(&mut self, path: &str) -> Entity;
+}
+
+impl SpawnGltfScene for Commands<'_, '_> {
+    fn spawn_gltf_scene(&mut self, path: &str) -> Entity {
+        let root = self.spawn((Transform::new(), Name(path.to_owned()))).id();
+
+        self.add(SpawnGltfSceneCommand {
+            path: path.to_owned(),
+            root,
+        });
+
+        root
+    }
+}
+
+struct SpawnGltfSceneCommand {
+    path: String,
+    root: Entity,
+}
+
+impl Command for SpawnGltfSceneCommand {
+    fn apply(self, world: &mut World) {
+        let vfs = world.resource::<AssetManagerResource>().asset_manager.vfs();
+
+        let scene = match GltfScene::read(&self.path, vfs) {
+            Ok(scene) => scene,
+            Err(e) => {
+                warn!("Failed to spawn glTF scene: {}", e);
+                return;
+            }
+        };
+
+        // glTF materials aren't imported yet, so everything uses the default material
+        let material = world
+            .get_non_send_resource::<RendererResource>()
+            .and_then(|renderer| renderer.renderer.get_material("defaultmesh"));
+
+        // The file is loaded once as a single mesh, every node draws its own part of it
+        let meshes = match scene.nodes.iter().any(has_mesh) {
+            true => Some(
+                world
+                    .resource_mut::<AssetManagerResource>()
+                    .asset_manager
+                    .load_mesh(&scene.meshes),
+            ),
+            false => None,
+        };
+
+        let children = scene
+            .nodes
+            .iter()
+            .map(|node| spawn_node(world, node, self.root, meshes, material))
+            .collect();
+
+        if let Some(mut root) = world.get_entity_mut(self.root) {
+            root.insert(Children(children));
+        }
+    }
+}
+
+fn has_mesh(node: &SceneNode) -> bool {
+    node.mesh.is_some() || node.children.iter().any(has_mesh)
+}
+
+fn spawn_node(
+    world: &mut World,
+    node: &SceneNode,
+    parent: Entity,
+    meshes: Option<Handle<asset_manager::Mesh>>,
+    material: Option<Handle<renderer::Material>>,
+) -> Entity {
+    let transform = Transform::from_trs(node.translation, node.rotation, node.scale);
+
+    let entity = world.spawn((transform, Parent(parent))).id();
+
+    if let Some(name) = &node.name {
+        world.entity_mut(entity).insert(Name(name.clone()));
+    }
+
+    if let (Some(part), Some(id)) = (node.mesh, meshes) {
+        world.entity_mut(entity).insert(Mesh::part(id, part));
+
+        if let Some(id) = material {
+            world.entity_mut(entity).insert(Material { id });
+        }
+    }
+
+    if let Some(camera) = &node.camera {
+        let camera = create_camera(world, camera);
+        world.entity_mut(entity).insert(camera);
+    }
+
+    if let Some(light) = &node.light {
+        world.entity_mut(entity).insert(Light {
+            kind: light.kind,
+            color: light.color,
+            intensity: light.intensity,
+            range: light.range,
+        });
+    }
+
+    let children: Vec<Entity> = node
+        .children
+        .iter()
+        .map(|child| spawn_node(world, child, entity, meshes, material))
+        .collect();
+
+    if !children.is_empty() {
+        world.entity_mut(entity).insert(Children(children));
+    }
+
+    entity
+}
+
+fn create_camera(world: &World, camera: &SceneCamera) -> Camera {
+    match *camera {
+        SceneCamera::Perspective {
+            aspect_ratio,
+            yfov,
+            znear,
+            zfar,
+        } => {
+            let aspect_ratio = aspect_ratio.unwrap_or_else(|| {
+                let config = &world.resource::<GameConfig>().config;
+
+                config.renderer.window_width as f32 / config.renderer.window_height as f32
+            });
+
+            match zfar {
+                Some(zfar) => Camera::new(aspect_ratio, yfov, znear, zfar),
+                None => Camera::infinite(aspect_ratio, yfov, znear),
+            }
+        }
+        SceneCamera::Orthographic {
+            xmag,
+            ymag,
+            znear,
+            zfar,
+        } => Camera::orthographic(xmag, ymag, znear, zfar),
+    }
+}
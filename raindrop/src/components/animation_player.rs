@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+
+use asset_manager::{AnimationClip, Handle, JointPose, Skin};
+use bevy_ecs::component::Component;
+
+/// How a layer's pose combines with the layers below it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Replace the pose below, by the layer's weight
+    Override,
+    /// Add how far the clips move the joints from the skin's rest pose, like a breathing
+    /// clip on top of walking
+    Additive,
+}
+
+/// A clip playing in a layer of an animation player
+pub struct PlayingClip {
+    pub clip: Handle<AnimationClip>,
+    /// Seconds into the clip
+    pub time: f32,
+    /// 1 plays at the clip's own speed, negative speeds play it backwards
+    pub speed: f32,
+    /// Start over at the end, otherwise the clip holds its last pose
+    pub looping: bool,
+    /// How much the clip counts in its layer, changed over time by crossfades
+    pub weight: f32,
+    // Weight change per second while crossfading
+    fade_rate: f32,
+}
+
+pub struct AnimationLayer {
+    pub blend_mode: BlendMode,
+    /// How much the layer counts over the layers below it
+    pub weight: f32,
+    /// Joint indices the layer moves, all of them when `None`, like only the upper body
+    pub mask: Option<Vec<usize>>,
+    clips: Vec<PlayingClip>,
+}
+
+impl AnimationLayer {
+    pub fn clips(&self) -> &[PlayingClip] {
+        &self.clips
+    }
+
+    fn moves(&self, joint: usize) -> bool {
+        match &self.mask {
+            Some(mask) => mask.contains(&joint),
+            None => true,
+        }
+    }
+}
+
+/// Plays skeletal animation clips on the entity's skin, the animation system writes the
+/// resulting `JointMatrices`
+/// Layers are blended bottom up, layer 0 is an override layer everything else builds on
+#[derive(Component)]
+pub struct AnimationPlayer {
+    pub skin: Handle<Skin>,
+    layers: Vec<AnimationLayer>,
+}
+
+impl AnimationPlayer {
+    pub fn new(skin: Handle<Skin>) -> AnimationPlayer {
+        AnimationPlayer {
+            skin,
+            layers: vec![AnimationLayer {
+                blend_mode: BlendMode::Override,
+                weight: 1.0,
+                mask: None,
+                clips: vec![],
+            }],
+        }
+    }
+
+    /// Add a layer on top of the others, returning its index
+    pub fn add_layer(&mut self, blend_mode: BlendMode, weight: f32) -> usize {
+        self.layers.push(AnimationLayer {
+            blend_mode,
+            weight,
+            mask: None,
+            clips: vec![],
+        });
+
+        self.layers.len() - 1
+    }
+
+    pub fn layers(&self) -> &[AnimationLayer] {
+        &self.layers
+    }
+
+    pub fn layer_mut(&mut self, layer: usize) -> &mut AnimationLayer {
+        &mut self.layers[layer]
+    }
+
+    /// Play the clip from the start, replacing what the layer was playing
+    pub fn play(&mut self, layer: usize, clip: Handle<AnimationClip>) -> &mut PlayingClip {
+        let clips = &mut self.layers[layer].clips;
+
+        clips.clear();
+        clips.push(PlayingClip {
+            clip,
+            time: 0.0,
+            speed: 1.0,
+            looping: true,
+            weight: 1.0,
+            fade_rate: 0.0,
+        });
+
+        &mut clips[0]
+    }
+
+    /// Fade the clip in over the duration in seconds while the rest of the layer fades out,
+    /// a clip that is already playing keeps its time
+    pub fn crossfade(
+        &mut self,
+        layer: usize,
+        clip: Handle<AnimationClip>,
+        duration: f32,
+    ) -> &mut PlayingClip {
+        if duration <= 0.0 {
+            return self.play(layer, clip);
+        }
+
+        let clips = &mut self.layers[layer].clips;
+
+        for playing in clips.iter_mut() {
+            playing.fade_rate = -1.0 / duration;
+        }
+
+        let index = match clips.iter().position(|playing| playing.clip == clip) {
+            Some(index) => index,
+            None => {
+                clips.push(PlayingClip {
+                    clip,
+                    time: 0.0,
+                    speed: 1.0,
+                    looping: true,
+                    weight: 0.0,
+                    fade_rate: 0.0,
+                });
+                clips.len() - 1
+            }
+        };
+
+        clips[index].fade_rate = 1.0 / duration;
+
+        &mut clips[index]
+    }
+
+    pub fn stop(&mut self, layer: usize) {
+        self.layers[layer].clips.clear();
+    }
+
+    /// Every clip the player needs, a clip can play in more than one layer
+    pub fn playing_clips(&self) -> impl Iterator<Item = Handle<AnimationClip>> + '_ {
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.clips.iter().map(|playing| playing.clip))
+    }
+
+    /// Move every clip forward by the seconds and advance crossfades,
+    /// clips that haven't loaded yet wait at their start
+    pub fn advance(
+        &mut self,
+        delta_time: f32,
+        clips: &HashMap<Handle<AnimationClip>, &AnimationClip>,
+    ) {
+        for layer in &mut self.layers {
+            for playing in &mut layer.clips {
+                playing.weight = (playing.weight + playing.fade_rate * delta_time).clamp(0.0, 1.0);
+
+                if playing.weight == 1.0 && playing.fade_rate > 0.0 {
+                    playing.fade_rate = 0.0;
+                }
+
+                let Some(clip) = clips.get(&playing.clip) else {
+                    continue;
+                };
+
+                playing.time += playing.speed * delta_time;
+
+                playing.time = match playing.looping && clip.duration > 0.0 {
+                    true => playing.time.rem_euclid(clip.duration),
+                    false => playing.time.clamp(0.0, clip.duration),
+                };
+            }
+
+            layer
+                .clips
+                .retain(|playing| playing.weight > 0.0 || playing.fade_rate >= 0.0);
+        }
+    }
+
+    /// The pose of every joint of the skin, from its rest pose and the clips playing on top
+    pub fn sample_pose(
+        &self,
+        skin: &Skin,
+        clips: &HashMap<Handle<AnimationClip>, &AnimationClip>,
+    ) -> Vec<JointPose> {
+        let rest_pose = skin.rest_pose();
+        let mut pose = rest_pose.clone();
+        let mut clip_pose = rest_pose.clone();
+
+        for layer in &self.layers {
+            let mut layer_pose = rest_pose.clone();
+            let mut total_weight = 0.0;
+
+            for playing in &layer.clips {
+                let Some(clip) = clips.get(&playing.clip) else {
+                    continue;
+                };
+
+                if playing.weight <= 0.0 {
+                    continue;
+                }
+
+                clip_pose.copy_from_slice(&rest_pose);
+                clip.sample_pose(skin, playing.time, &mut clip_pose);
+
+                match layer.blend_mode {
+                    // A running average, so crossfading clips share the layer by their weights
+                    BlendMode::Override => {
+                        total_weight += playing.weight;
+
+                        for (layer_pose, clip_pose) in layer_pose.iter_mut().zip(&clip_pose) {
+                            *layer_pose =
+                                layer_pose.blend(clip_pose, playing.weight / total_weight);
+                        }
+                    }
+                    BlendMode::Additive => {
+                        for (joint, pose) in pose.iter_mut().enumerate() {
+                            if layer.moves(joint) {
+                                *pose = pose.add_difference(
+                                    &rest_pose[joint],
+                                    &clip_pose[joint],
+                                    playing.weight * layer.weight,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+
+            if layer.blend_mode == BlendMode::Override && total_weight > 0.0 {
+                let weight = layer.weight * total_weight.min(1.0);
+
+                for (joint, pose) in pose.iter_mut().enumerate() {
+                    if layer.moves(joint) {
+                        *pose = pose.blend(&layer_pose[joint], weight);
+                    }
+                }
+            }
+        }
+
+        pose
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asset_manager::{
+        AnimationChannel, AssetInfo, AssetLoader, AssetStatus, ChannelProperty, Interpolation,
+        Joint,
+    };
+
+    fn asset_info(id: &str) -> AssetInfo {
+        AssetInfo {
+            id: id.to_owned(),
+            status: AssetStatus::Loaded,
+        }
+    }
+
+    // Two joints, the second a child of the first
+    fn skin() -> Skin {
+        let mut skin = Skin::unloaded(asset_info("skin"), ());
+        skin.joints = (0..2)
+            .map(|joint| Joint {
+                name: None,
+                node: joint,
+                parent: joint.checked_sub(1),
+                rest_pose: JointPose::default(),
+                inverse_bind_matrix: glm::Mat4::identity(),
+            })
+            .collect();
+
+        skin
+    }
+
+    // Moves the joint's translation along x from 0 to `distance` over 2 seconds
+    fn slide(joint: usize, distance: f32) -> AnimationClip {
+        let mut clip = AnimationClip::unloaded(asset_info("slide"), ());
+        clip.duration = 2.0;
+        clip.channels = vec![AnimationChannel {
+            node: joint,
+            property: ChannelProperty::Translation,
+            interpolation: Interpolation::Linear,
+            times: vec![0.0, 2.0],
+            values: vec![0.0, 0.0, 0.0, distance, 0.0, 0.0],
+            components: 3,
+        }];
+
+        clip
+    }
+
+    #[test]
+    fn test_advance_loops_and_clamps() {
+        let (walk, wave) = (Handle::new(0, 0), Handle::new(1, 0));
+        let walk_clip = slide(0, 1.0);
+        let wave_clip = slide(1, 1.0);
+        let clips = HashMap::from([(walk, &walk_clip), (wave, &wave_clip)]);
+
+        let mut player = AnimationPlayer::new(Handle::new(0, 0));
+        player.play(0, walk).speed = 2.0;
+        let layer = player.add_layer(BlendMode::Override, 1.0);
+        player.play(layer, wave).looping = false;
+
+        player.advance(1.5, &clips);
+
+        assert_eq!(player.layers()[0].clips()[0].time, 1.0);
+        assert_eq!(player.layers()[1].clips()[0].time, 1.5);
+
+        player.advance(1.5, &clips);
+
+        assert_eq!(player.layers()[1].clips()[0].time, 2.0);
+
+        // Clips that aren't loaded wait at the start
+        player.play(0, Handle::new(2, 0));
+        player.advance(1.0, &clips);
+
+        assert_eq!(player.layers()[0].clips()[0].time, 0.0);
+    }
+
+    #[test]
+    fn test_crossfade() {
+        let (walk, run) = (Handle::new(0, 0), Handle::new(1, 0));
+        let walk_clip = slide(0, 1.0);
+        let run_clip = slide(0, 3.0);
+        let clips = HashMap::from([(walk, &walk_clip), (run, &run_clip)]);
+        let skin = skin();
+
+        let mut player = AnimationPlayer::new(Handle::new(0, 0));
+        player.play(0, walk).time = 1.0;
+        player.crossfade(0, run, 1.0).time = 1.0;
+
+        // Halfway through, both clips count the same
+        player.advance(0.5, &clips);
+        let pose = player.sample_pose(&skin, &clips);
+
+        assert_eq!(player.layers()[0].clips()[0].weight, 0.5);
+        assert!((pose[0].translation.x - (0.75 + 2.25) / 2.0).abs() < 1e-5);
+
+        // Then the faded out clip is dropped
+        player.advance(0.5, &clips);
+
+        let layer = &player.layers()[0];
+        assert_eq!(layer.clips().len(), 1);
+        assert_eq!(layer.clips()[0].clip, run);
+        assert_eq!(layer.clips()[0].weight, 1.0);
+    }
+
+    #[test]
+    fn test_layers() {
+        let (walk, wave, lean) = (Handle::new(0, 0), Handle::new(1, 0), Handle::new(2, 0));
+        let walk_clip = slide(0, 2.0);
+        let wave_clip = slide(1, 2.0);
+        let lean_clip = slide(0, 4.0);
+        let clips = HashMap::from([(walk, &walk_clip), (wave, &wave_clip), (lean, &lean_clip)]);
+        let skin = skin();
+
+        let mut player = AnimationPlayer::new(Handle::new(0, 0));
+        player.play(0, walk).time = 1.0;
+
+        // Waving only moves the second joint, at half weight
+        let upper_body = player.add_layer(BlendMode::Override, 0.5);
+        player.layer_mut(upper_body).mask = Some(vec![1]);
+        player.play(upper_body, wave).time = 1.0;
+
+        let additive = player.add_layer(BlendMode::Additive, 1.0);
+        player.play(additive, lean).time = 1.0;
+
+        let pose = player.sample_pose(&skin, &clips);
+
+        // The walk moves the first joint 1 and leaning adds 2
+        assert_eq!(pose[0].translation, glm::vec3(3.0, 0.0, 0.0));
+        assert_eq!(pose[1].translation, glm::vec3(0.5, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,6 @@
+use bevy_ecs::component::Component;
+
+/// Hears spatial audio sources from the entity's transform, looking down its -z axis
+/// Without a listener, sources are heard from the `Player` camera
+#[derive(Component, Default)]
+pub struct AudioListener;
@@ -1,7 +1,227 @@
+use asset_manager::{Handle, Music, Sound};
 use bevy_ecs::component::Component;
 
+/// How a spatial source gets quieter with distance, between the attenuation's min and max
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DistanceModel {
+    /// Halves at twice the min distance with a rolloff of 1, like sound in open air
+    Inverse,
+    /// Falls off evenly until it is silent at the max distance
+    Linear,
+    /// Falls off with the distance over min distance to the power of the rolloff
+    Exponential,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attenuation {
+    pub model: DistanceModel,
+    /// Closer than this the source plays at full volume
+    pub min_distance: f32,
+    /// Further than this the source gets no quieter
+    pub max_distance: f32,
+    /// How fast the source gets quieter, 1 for the usual falloff
+    pub rolloff: f32,
+}
+
+impl Attenuation {
+    /// The volume of the source at the distance, from 0 to 1
+    pub fn gain(&self, distance: f32) -> f32 {
+        let min_distance = self.min_distance.max(f32::EPSILON);
+        let max_distance = self.max_distance.max(min_distance);
+        let distance = distance.clamp(min_distance, max_distance);
+
+        let gain = match self.model {
+            DistanceModel::Inverse => {
+                min_distance / (min_distance + self.rolloff * (distance - min_distance))
+            }
+            DistanceModel::Linear => match max_distance > min_distance {
+                true => {
+                    1.0 - self.rolloff * (distance - min_distance) / (max_distance - min_distance)
+                }
+                false => 1.0,
+            },
+            DistanceModel::Exponential => (distance / min_distance).powf(-self.rolloff),
+        };
+
+        gain.clamp(0.0, 1.0)
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Attenuation {
+            model: DistanceModel::Inverse,
+            min_distance: 1.0,
+            max_distance: 100.0,
+            rolloff: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaybackState {
+    Playing,
+    /// Keeps the position, playing again continues from it
+    Paused,
+    /// Playing again starts from the beginning
+    Stopped,
+}
+
+/// What an `AudioSource` plays
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioClip {
+    /// Decoded all at once, for short sounds
+    Sound(Handle<Sound>),
+    /// Decoded while it plays, for soundtracks, looping between the music's loop points
+    Music(Handle<Music>),
+}
+
+impl From<Handle<Sound>> for AudioClip {
+    fn from(id: Handle<Sound>) -> Self {
+        AudioClip::Sound(id)
+    }
+}
+
+impl From<Handle<Music>> for AudioClip {
+    fn from(id: Handle<Music>) -> Self {
+        AudioClip::Music(id)
+    }
+}
+
+/// Plays a sound from the entity, the audio system starts it once the sound has loaded
+/// and sets the state back to `Stopped` when a sound that doesn't loop has finished
 #[derive(Component)]
 pub struct AudioSource {
-    pub id: String,
+    pub clip: AudioClip,
+    /// Play the sound from the entity's position, as heard by the `AudioListener`
     pub spatial: bool,
+    /// How a spatial source gets quieter with its distance to the listener
+    pub attenuation: Attenuation,
+    /// Raise the pitch of a spatial source moving towards the listener and lower it
+    /// moving away
+    pub doppler: bool,
+    pub state: PlaybackState,
+    /// Start over at the end
+    pub looping: bool,
+    /// 1 plays the sound as it is
+    pub volume: f32,
+    /// Playback speed, 2 plays it twice as fast an octave higher
+    pub pitch: f32,
+    /// Despawn the entity once the sound has finished, for one-shot effects
+    pub despawn_when_finished: bool,
+    /// The mixer bus the sound plays through, see `config::AudioConfig::buses`
+    pub bus: String,
+}
+
+impl AudioSource {
+    /// Play the sound once from the start
+    pub fn new(clip: impl Into<AudioClip>) -> AudioSource {
+        AudioSource {
+            clip: clip.into(),
+            spatial: false,
+            attenuation: Attenuation::default(),
+            doppler: false,
+            state: PlaybackState::Playing,
+            looping: false,
+            volume: 1.0,
+            pitch: 1.0,
+            despawn_when_finished: false,
+            bus: "sfx".to_string(),
+        }
+    }
+
+    /// Stream the music over and over on the music bus
+    pub fn music(id: Handle<Music>) -> AudioSource {
+        AudioSource {
+            bus: "music".to_string(),
+            ..AudioSource::looping(id)
+        }
+    }
+
+    /// Play the sound over and over
+    pub fn looping(clip: impl Into<AudioClip>) -> AudioSource {
+        AudioSource {
+            looping: true,
+            ..AudioSource::new(clip)
+        }
+    }
+
+    /// Play the sound once from the entity's position
+    pub fn spatial(clip: impl Into<AudioClip>) -> AudioSource {
+        AudioSource {
+            spatial: true,
+            ..AudioSource::new(clip)
+        }
+    }
+
+    /// Play the sound once, then despawn the entity
+    pub fn once(clip: impl Into<AudioClip>) -> AudioSource {
+        AudioSource {
+            despawn_when_finished: true,
+            ..AudioSource::new(clip)
+        }
+    }
+
+    pub fn play(&mut self) {
+        self.state = PlaybackState::Playing;
+    }
+
+    pub fn pause(&mut self) {
+        if self.state == PlaybackState::Playing {
+            self.state = PlaybackState::Paused;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.state = PlaybackState::Stopped;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state == PlaybackState::Playing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_only_pauses_playing_sources() {
+        let mut source = AudioSource::once(Handle::<Sound>::new(0, 0));
+        assert!(source.is_playing());
+        assert!(source.despawn_when_finished);
+
+        source.pause();
+        assert_eq!(source.state, PlaybackState::Paused);
+
+        source.stop();
+        source.pause();
+        assert_eq!(source.state, PlaybackState::Stopped);
+
+        source.play();
+        assert!(source.is_playing());
+    }
+
+    #[test]
+    fn test_attenuation_models() {
+        let mut attenuation = Attenuation {
+            model: DistanceModel::Inverse,
+            min_distance: 2.0,
+            max_distance: 10.0,
+            rolloff: 1.0,
+        };
+
+        assert_eq!(attenuation.gain(0.0), 1.0);
+        assert_eq!(attenuation.gain(2.0), 1.0);
+        assert_eq!(attenuation.gain(4.0), 0.5);
+        assert_eq!(attenuation.gain(10.0), attenuation.gain(50.0));
+
+        attenuation.model = DistanceModel::Linear;
+        assert_eq!(attenuation.gain(6.0), 0.5);
+        assert_eq!(attenuation.gain(10.0), 0.0);
+
+        attenuation.model = DistanceModel::Exponential;
+        attenuation.rolloff = 2.0;
+        assert_eq!(attenuation.gain(4.0), 0.25);
+    }
 }
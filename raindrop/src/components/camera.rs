@@ -12,6 +12,20 @@ impl Camera {
         }
     }
 
+    /// A perspective camera without a far plane
+    pub fn infinite(aspect: f32, fovy: f32, near: f32) -> Camera {
+        Camera {
+            projection_matrix: glm::infinite_perspective_rh_no(aspect, fovy, near),
+        }
+    }
+
+    /// An orthographic camera, xmag and ymag are half the width and height of the view
+    pub fn orthographic(xmag: f32, ymag: f32, near: f32, far: f32) -> Camera {
+        Camera {
+            projection_matrix: glm::ortho(-xmag, xmag, -ymag, ymag, near, far),
+        }
+    }
+
     pub fn matrix(&self) -> glm::Mat4 {
         self.projection_matrix
     }
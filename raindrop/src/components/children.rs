@@ -0,0 +1,5 @@
+use bevy_ecs::{component::Component, entity::Entity};
+
+/// The entities whose transforms are relative to this one
+#[derive(Component, Default)]
+pub struct Children(pub Vec<Entity>);
@@ -0,0 +1,9 @@
+use bevy_ecs::component::Component;
+
+/// The skinning matrices of the entity's skin in its current pose, one per joint, for the
+/// renderer to upload for GPU skinning
+/// Written every frame by the animation system for entities with an `AnimationPlayer`
+#[derive(Component, Clone, Debug, Default)]
+pub struct JointMatrices {
+    pub matrices: Vec<glm::Mat4>,
+}
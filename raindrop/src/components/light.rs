@@ -0,0 +1,15 @@
+use bevy_ecs::component::Component;
+
+pub use asset_manager::SceneLightKind as LightKind;
+
+/// A punctual light, shining down the local -z axis of its transform
+#[derive(Component)]
+pub struct Light {
+    pub kind: LightKind,
+    /// Linear RGB color
+    pub color: glm::Vec3,
+    /// Candela for point and spot lights, lux for directional lights
+    pub intensity: f32,
+    /// Distance where the light reaches zero, unlimited when missing
+    pub range: Option<f32>,
+}
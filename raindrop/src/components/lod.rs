@@ -0,0 +1,40 @@
+use bevy_ecs::component::Component;
+
+/// Draws simpler levels of detail of the entity's mesh the further it is from the camera
+/// The mesh needs levels to pick from, see `asset_manager::MeshSettings::lod_levels`
+#[derive(Component)]
+pub struct Lod {
+    /// Camera distance where each level after the full detail one starts, in increasing order
+    distances: Vec<f32>,
+}
+
+impl Lod {
+    pub fn new(distances: Vec<f32>) -> Lod {
+        Lod { distances }
+    }
+
+    /// The level of detail to draw at the distance from the camera, 0 for the full mesh
+    pub fn level(&self, distance: f32) -> usize {
+        self.distances
+            .iter()
+            .take_while(|&&start| distance >= start)
+            .count()
+    }
+
+    pub fn get_distances(&self) -> &[f32] {
+        &self.distances
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_level() {
+        let lod = super::Lod::new(vec![10.0, 20.0]);
+
+        assert_eq!(lod.level(0.0), 0);
+        assert_eq!(lod.level(10.0), 1);
+        assert_eq!(lod.level(15.0), 1);
+        assert_eq!(lod.level(100.0), 2);
+    }
+}
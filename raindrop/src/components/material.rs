@@ -1,7 +1,7 @@
+use asset_manager::Handle;
 use bevy_ecs::component::Component;
 
 #[derive(Component)]
 pub struct Material {
-    // TODO: This needs to become a number or some cheaply comparable and copyable type
-    pub id: String,
+    pub id: Handle<renderer::Material>,
 }
@@ -1,7 +1,23 @@
+use asset_manager::Handle;
 use bevy_ecs::component::Component;
 
 #[derive(Component)]
 pub struct Mesh {
-    // TODO: This needs to become a number or some cheaply comparable and copyable type
-    pub id: String,
+    pub id: Handle<asset_manager::Mesh>,
+    /// Only draw the sub-meshes of this part, for entities that each draw one mesh of a file,
+    /// see `asset_manager::Mesh::gltf_meshes_id`
+    pub part: Option<usize>,
+}
+
+impl Mesh {
+    pub fn new(id: Handle<asset_manager::Mesh>) -> Mesh {
+        Mesh { id, part: None }
+    }
+
+    pub fn part(id: Handle<asset_manager::Mesh>, part: usize) -> Mesh {
+        Mesh {
+            id,
+            part: Some(part),
+        }
+    }
 }
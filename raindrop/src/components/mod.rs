@@ -1,13 +1,31 @@
+pub mod animation_player;
+pub mod audio_listener;
 pub mod audio_source;
 pub mod camera;
+pub mod children;
+pub mod joint_matrices;
+pub mod light;
+pub mod lod;
 pub mod material;
 pub mod mesh;
+pub mod name;
+pub mod parent;
 pub mod player;
 pub mod transform;
+pub mod world_bounds;
 
-pub use audio_source::AudioSource;
+pub use animation_player::{AnimationLayer, AnimationPlayer, BlendMode, PlayingClip};
+pub use audio_listener::AudioListener;
+pub use audio_source::{Attenuation, AudioClip, AudioSource, DistanceModel, PlaybackState};
 pub use camera::Camera;
+pub use children::Children;
+pub use joint_matrices::JointMatrices;
+pub use light::{Light, LightKind};
+pub use lod::Lod;
 pub use material::Material;
 pub use mesh::Mesh;
+pub use name::Name;
+pub use parent::Parent;
 pub use player::Player;
 pub use transform::Transform;
+pub use world_bounds::WorldBounds;
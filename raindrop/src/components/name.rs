@@ -0,0 +1,4 @@
+use bevy_ecs::component::Component;
+
+#[derive(Component)]
+pub struct Name(pub String);
@@ -0,0 +1,5 @@
+use bevy_ecs::{component::Component, entity::Entity};
+
+/// The entity this one's transform is relative to
+#[derive(Component, Clone, Copy)]
+pub struct Parent(pub Entity);
@@ -25,6 +25,17 @@ impl Transform {
         }
     }
 
+    /// Build a transform from a quaternion rotation, like the ones glTF nodes use
+    pub fn from_trs(translation: glm::Vec3, rotation: glm::Quat, scale: glm::Vec3) -> Transform {
+        let mut transform = Transform::new();
+
+        transform.set_translation(translation);
+        transform.set_rotation(Transform::euler_from_quat(&rotation));
+        transform.set_scale(scale);
+
+        transform
+    }
+
     pub fn get_translation(&self) -> glm::Vec3 {
         self.translation
     }
@@ -76,6 +87,26 @@ impl Transform {
         self.matrix
     }
 
+    // Angles for the rotation matrix rotate_x * rotate_y * rotate_z that model_matrix builds
+    fn euler_from_quat(rotation: &glm::Quat) -> glm::Vec3 {
+        let matrix = glm::quat_to_mat3(rotation);
+
+        if matrix[(0, 2)].abs() < 0.9999 {
+            glm::vec3(
+                (-matrix[(1, 2)]).atan2(matrix[(2, 2)]),
+                matrix[(0, 2)].asin(),
+                (-matrix[(0, 1)]).atan2(matrix[(0, 0)]),
+            )
+        } else {
+            // Gimbal lock, x and z rotate around the same axis so put it all in x
+            glm::vec3(
+                matrix[(2, 1)].atan2(matrix[(1, 1)]),
+                std::f32::consts::FRAC_PI_2.copysign(matrix[(0, 2)]),
+                0.0,
+            )
+        }
+    }
+
     pub fn view_matrix(&mut self) -> glm::Mat4 {
         if self.dirty {
             let translation = self.translation;
@@ -182,6 +213,25 @@ mod tests {
         assert_eq!(transform.rotation, glm::vec3(2.0, 4.0, 6.0));
     }
 
+    #[test]
+    fn test_from_trs_matches_quaternion() {
+        let translation = glm::vec3(1.0, 2.0, 3.0);
+        let scale = glm::vec3(2.0, 2.0, 2.0);
+
+        for rotation in [
+            glm::quat_angle_axis(0.7, &glm::vec3(0.3, -0.5, 0.8).normalize()),
+            glm::quat_angle_axis(std::f32::consts::FRAC_PI_2, &glm::vec3(0.0, 1.0, 0.0)),
+        ] {
+            let mut transform = Transform::from_trs(translation, rotation, scale);
+
+            let expected = glm::translation(&translation)
+                * glm::quat_to_mat4(&rotation)
+                * glm::scaling(&scale);
+
+            assert!((transform.model_matrix() - expected).abs().max() < 1e-5);
+        }
+    }
+
     #[test]
     fn test_set_scale() {
         let mut transform = Transform::new();
@@ -0,0 +1,47 @@
+use asset_manager::{Aabb, BoundingSphere};
+use bevy_ecs::component::Component;
+
+/// The world space bounds of the entity's mesh, for culling, picking, sizing colliders and
+/// framing cameras
+/// Added once the mesh has loaded, then kept current from the transform by the bounds system
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WorldBounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl WorldBounds {
+    /// Move a mesh's bounds into the world with the entity's world matrix
+    pub fn from_mesh(aabb: &Aabb, sphere: &BoundingSphere, world_matrix: &glm::Mat4) -> Self {
+        WorldBounds {
+            aabb: aabb.transformed(world_matrix),
+            sphere: sphere.transformed(world_matrix),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_mesh() {
+        let aabb = Aabb {
+            min: glm::vec3(-1.0, -1.0, -1.0),
+            max: glm::vec3(1.0, 1.0, 1.0),
+        };
+        let sphere = BoundingSphere {
+            center: glm::Vec3::zeros(),
+            radius: 3.0_f32.sqrt(),
+        };
+        let matrix =
+            glm::translation(&glm::vec3(5.0, 0.0, 0.0)) * glm::scaling(&glm::vec3(2.0, 2.0, 2.0));
+
+        let bounds = WorldBounds::from_mesh(&aabb, &sphere, &matrix);
+
+        assert_eq!(bounds.aabb.min, glm::vec3(3.0, -2.0, -2.0));
+        assert_eq!(bounds.aabb.max, glm::vec3(7.0, 2.0, 2.0));
+        assert_eq!(bounds.sphere.center, glm::vec3(5.0, 0.0, 0.0));
+        assert_eq!(bounds.sphere.radius, sphere.radius * 2.0);
+    }
+}
@@ -1,4 +1,6 @@
+use asset_manager::{Mesh, Sound, Texture};
 use bevy_ecs::{
+    event::Events,
     schedule::{IntoSystemConfigs, Schedule},
     world::World,
 };
@@ -11,7 +13,8 @@ use winit::{
 };
 
 use crate::{
-    resources::{AssetManagerResource, ControlInput, GameConfig, RendererResource},
+    events::AssetEvent,
+    resources::{AssetManagerResource, AudioOutput, ControlInput, GameConfig, RendererResource},
     systems, Time,
 };
 
@@ -144,11 +147,21 @@ impl Engine {
     fn default_world(config: &Config, window: &Window) -> World {
         let mut world = World::new();
 
-        world.insert_resource(AssetManagerResource::default());
+        world.insert_resource(AssetManagerResource::new(config));
         world.insert_resource(GameConfig::from(config.clone()));
         world.insert_resource(ControlInput::default());
         world.insert_resource(Time::new());
-        world.insert_non_send_resource(RendererResource::new(config.clone(), window));
+        world.insert_resource(Events::<AssetEvent<Mesh>>::default());
+        world.insert_resource(Events::<AssetEvent<Sound>>::default());
+        world.insert_resource(Events::<AssetEvent<Texture>>::default());
+
+        let renderer = RendererResource::new(
+            config.clone(),
+            window,
+            world.resource::<AssetManagerResource>().asset_manager.vfs(),
+        );
+        world.insert_non_send_resource(renderer);
+        world.insert_non_send_resource(AudioOutput::new(&config.audio));
 
         world
     }
@@ -160,8 +173,16 @@ impl Engine {
     fn default_update_schedule() -> Schedule {
         let mut schedule = Schedule::default();
 
+        schedule.add_systems(systems::asset_event_system);
         schedule.add_systems(systems::player_control_system);
         schedule.add_systems(systems::spin_system);
+        schedule.add_systems(systems::animation_system);
+        schedule.add_systems(systems::audio_system);
+        schedule.add_systems(
+            systems::bounds_system
+                .after(systems::spin_system)
+                .after(systems::player_control_system),
+        );
 
         schedule
     }
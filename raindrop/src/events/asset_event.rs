@@ -0,0 +1,15 @@
+use std::ops::Deref;
+
+use bevy_ecs::event::Event;
+
+/// An asset manager event forwarded into the ECS so systems can read it with an `EventReader`
+#[derive(Event)]
+pub struct AssetEvent<T>(pub asset_manager::AssetEvent<T>);
+
+impl<T> Deref for AssetEvent<T> {
+    type Target = asset_manager::AssetEvent<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
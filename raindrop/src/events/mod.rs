@@ -0,0 +1,3 @@
+pub mod asset_event;
+
+pub use asset_event::AssetEvent;
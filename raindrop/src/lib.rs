@@ -1,14 +1,20 @@
 extern crate log;
 pub extern crate nalgebra_glm as glm;
 
+mod audio;
+pub mod commands;
 pub mod components;
 mod engine;
+pub mod events;
 pub mod raindrop;
 mod resources;
 mod systems;
 
+pub use asset_manager;
+pub use asset_manager::Handle;
+pub use audio::{Bus, LowPass, Mixer, Reverb, Voice, VoiceId};
 pub use bevy_ecs;
 pub use config::Config;
 pub use engine::ScheduleType;
 pub use raindrop::Raindrop;
-pub use resources::{GameConfig, Time};
+pub use resources::{AssetManagerResource, AudioOutput, GameConfig, RendererResource, Time};
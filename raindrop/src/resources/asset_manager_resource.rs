@@ -1,7 +1,16 @@
 use asset_manager::AssetManager;
 use bevy_ecs::system::Resource;
+use config::Config;
 
 #[derive(Resource, Default)]
 pub struct AssetManagerResource {
-    pub asset_manager: AssetManager
+    pub asset_manager: AssetManager,
+}
+
+impl AssetManagerResource {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            asset_manager: AssetManager::new(config),
+        }
+    }
 }
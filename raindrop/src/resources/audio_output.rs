@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use bevy_ecs::entity::Entity;
+use config::AudioConfig;
+use log::warn;
+use rodio::OutputStream;
+
+use crate::audio::{Mixer, MixerSource, VoiceId};
+
+// Where the mix goes
+enum Backend {
+    /// Dropping the stream closes the device
+    Device(OutputStream),
+    /// Mixed as the game updates into a buffer nothing plays
+    Null,
+}
+
+// The mixer voice of an entity's `AudioSource`
+pub(crate) struct EntityVoice {
+    pub id: VoiceId,
+    /// Where a spatial voice was heard from on the last update, to find its velocity
+    pub last_position: Option<glm::Vec3>,
+}
+
+/// The audio mixer and where it plays, with a voice for every entity playing an `AudioSource`
+/// The output stream can't leave the thread that opened it, so this is a non-send resource
+pub struct AudioOutput {
+    backend: Backend,
+    pub(crate) mixer: Arc<Mutex<Mixer>>,
+    pub(crate) voices: HashMap<Entity, EntityVoice>,
+    /// Where the listener was on the last update, to find its velocity
+    pub(crate) last_listener_position: Option<glm::Vec3>,
+    // Frames the null backend is behind the game, below one
+    pending_frames: f64,
+    rendered: Vec<f32>,
+}
+
+impl AudioOutput {
+    /// Play on the default audio device, or the null backend when the config asks for it
+    /// or there is no device to open
+    pub fn new(config: &AudioConfig) -> AudioOutput {
+        let mut output = AudioOutput::null(config);
+
+        if config.null_output {
+            return output;
+        }
+
+        let played = OutputStream::try_default()
+            .map_err(|e| e.to_string())
+            .and_then(|(stream, handle)| {
+                match handle.play_raw(MixerSource::new(output.mixer.clone())) {
+                    Ok(()) => Ok(stream),
+                    Err(e) => Err(e.to_string()),
+                }
+            });
+
+        match played {
+            Ok(stream) => output.backend = Backend::Device(stream),
+            Err(e) => warn!("Failed to open audio output, mixing without it: {}", e),
+        }
+
+        output
+    }
+
+    /// Mix without a device, into a buffer the game can read with `rendered`
+    pub fn null(config: &AudioConfig) -> AudioOutput {
+        AudioOutput {
+            backend: Backend::Null,
+            mixer: Arc::new(Mutex::new(Mixer::new(config))),
+            voices: HashMap::new(),
+            last_listener_position: None,
+            pending_frames: 0.0,
+            rendered: vec![],
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.backend, Backend::Null)
+    }
+
+    /// Change bus volumes and apply snapshots, the device waits for the lock to mix
+    pub fn mixer(&self) -> MutexGuard<'_, Mixer> {
+        self.mixer.lock().unwrap()
+    }
+
+    /// With the null backend, mix the time that passed since the last update into
+    /// `rendered`, the device mixes on its own
+    pub fn advance(&mut self, delta_time: f32) {
+        if !self.is_null() {
+            return;
+        }
+
+        let mut mixer = self.mixer.lock().unwrap();
+
+        self.pending_frames += delta_time.max(0.0) as f64 * mixer.sample_rate() as f64;
+        let frames = self.pending_frames as usize;
+        self.pending_frames -= frames as f64;
+
+        self.rendered.resize(frames * 2, 0.0);
+        mixer.render(&mut self.rendered);
+    }
+
+    /// The interleaved stereo frames the null backend mixed on the last update
+    pub fn rendered(&self) -> &[f32] {
+        &self.rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use asset_manager::SoundBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_null_output_mixes_as_time_passes() {
+        let config = AudioConfig {
+            null_output: true,
+            sample_rate: 8000,
+            ..AudioConfig::default()
+        };
+        let mut output = AudioOutput::new(&config);
+        assert!(output.is_null());
+
+        let buffer = SoundBuffer {
+            samples: vec![8192; 800].into(),
+            sample_rate: 8000,
+            channels: 1,
+        };
+        let ui = output.mixer().bus("ui").unwrap();
+        output.mixer().play(buffer, ui);
+
+        // Whole frames are mixed, the rest waits for the next update
+        output.advance(1.0 / 64.0);
+        assert_eq!(output.rendered(), [0.25; 250]);
+
+        let half_frame = 1.0 / 16384.0;
+        output.advance(half_frame);
+        output.advance(half_frame);
+        assert!(output.rendered().is_empty());
+        output.advance(half_frame);
+        assert_eq!(output.rendered(), [0.25; 2]);
+    }
+}
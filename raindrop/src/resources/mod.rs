@@ -1,4 +1,5 @@
 pub mod asset_manager_resource;
+pub mod audio_output;
 pub mod control_input;
 pub mod game_config;
 pub mod physics_manager;
@@ -6,6 +7,7 @@ pub mod renderer_resource;
 pub mod time;
 
 pub use asset_manager_resource::AssetManagerResource;
+pub use audio_output::AudioOutput;
 pub use control_input::ControlInput;
 pub use game_config::GameConfig;
 pub use physics_manager::PhysicsManager;
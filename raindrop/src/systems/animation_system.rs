@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use asset_manager::{AnimationClip, AssetStatus};
+use bevy_ecs::{
+    entity::Entity,
+    system::{Commands, Query, Res},
+};
+
+use crate::{
+    components::{AnimationPlayer, JointMatrices},
+    resources::{AssetManagerResource, Time},
+};
+
+/// Advance every animation player and write the joint matrices of its pose,
+/// adding them once the player's skin has loaded
+pub fn animation_system(
+    mut commands: Commands,
+    mut players: Query<(Entity, &mut AnimationPlayer, Option<&mut JointMatrices>)>,
+    asset_manager: Res<AssetManagerResource>,
+    time: Res<Time>,
+) {
+    let asset_manager = &asset_manager.asset_manager;
+
+    for (entity, mut player, joint_matrices) in players.iter_mut() {
+        let Some(skin) = asset_manager.get(player.skin) else {
+            continue;
+        };
+        let skin = skin.lock().unwrap();
+
+        if skin.asset_info.status != AssetStatus::Loaded {
+            continue;
+        }
+
+        // A clip playing in several layers is only locked once
+        let assets: HashMap<_, _> = player
+            .playing_clips()
+            .filter_map(|clip| Some((clip, asset_manager.get(clip)?)))
+            .collect();
+        let locked: Vec<_> = assets
+            .iter()
+            .map(|(&clip, asset)| (clip, asset.lock().unwrap()))
+            .collect();
+        let clips: HashMap<_, &AnimationClip> = locked
+            .iter()
+            .filter(|(_, clip)| clip.asset_info.status == AssetStatus::Loaded)
+            .map(|(handle, clip)| (*handle, &**clip))
+            .collect();
+
+        player.advance(time.delta_time, &clips);
+
+        let matrices = skin.skinning_matrices(&player.sample_pose(&skin, &clips));
+
+        match joint_matrices {
+            Some(mut joint_matrices) => joint_matrices.matrices = matrices,
+            None => {
+                commands.entity(entity).insert(JointMatrices { matrices });
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use asset_manager::{Mesh, Sound, Texture};
+use bevy_ecs::{event::Events, system::ResMut};
+
+use crate::{events::AssetEvent, resources::AssetManagerResource};
+
+/// Let the asset manager pick up changed files, then move everything it published since the
+/// last update into the ECS events
+pub fn asset_event_system(
+    mut asset_manager: ResMut<AssetManagerResource>,
+    mut mesh_events: ResMut<Events<AssetEvent<Mesh>>>,
+    mut sound_events: ResMut<Events<AssetEvent<Sound>>>,
+    mut texture_events: ResMut<Events<AssetEvent<Texture>>>,
+) {
+    asset_manager.asset_manager.update();
+
+    mesh_events.update();
+    mesh_events.send_batch(
+        asset_manager
+            .asset_manager
+            .drain_mesh_events()
+            .into_iter()
+            .map(AssetEvent),
+    );
+
+    sound_events.update();
+    sound_events.send_batch(
+        asset_manager
+            .asset_manager
+            .drain_audio_events()
+            .into_iter()
+            .map(AssetEvent),
+    );
+
+    texture_events.update();
+    texture_events.send_batch(
+        asset_manager
+            .asset_manager
+            .drain_texture_events()
+            .into_iter()
+            .map(AssetEvent),
+    );
+}
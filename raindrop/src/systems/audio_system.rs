@@ -0,0 +1,214 @@
+use std::collections::hash_map::Entry;
+
+use asset_manager::{AssetStatus, MusicStream, SoundBuffer};
+use bevy_ecs::{
+    entity::Entity,
+    query::{With, Without},
+    system::{Commands, NonSendMut, Query, Res},
+};
+use log::warn;
+
+use crate::{
+    audio::Mixer,
+    components::{AudioClip, AudioListener, AudioSource, Parent, PlaybackState, Player, Transform},
+    resources::{audio_output::EntityVoice, AssetManagerResource, AudioOutput, Time},
+    systems::{
+        spatial_audio::{pan_gains, spatialize, Listener, Spatialized},
+        world_matrix::{local_matrices, world_matrix},
+    },
+};
+
+/// Start, update and stop a mixer voice for every entity with an `AudioSource`, starting
+/// them once their sound has loaded
+/// Spatial sources are heard from the `AudioListener`, or the `Player` camera without one
+#[allow(clippy::too_many_arguments)]
+pub fn audio_system(
+    mut commands: Commands,
+    mut sources: Query<(Entity, &mut AudioSource)>,
+    mut player_camera: Query<(Entity, &mut Transform), With<Player>>,
+    mut transforms: Query<(Entity, &mut Transform, Option<&Parent>), Without<Player>>,
+    listeners: Query<Entity, With<AudioListener>>,
+    mut output: NonSendMut<AudioOutput>,
+    asset_manager: Res<AssetManagerResource>,
+    time: Res<Time>,
+) {
+    let output = &mut *output;
+    let shared_mixer = output.mixer.clone();
+    let mut mixer = shared_mixer.lock().unwrap();
+
+    // Voices of despawned entities and removed sources stop with them
+    output.voices.retain(|&entity, voice| {
+        let keep = sources.contains(entity);
+        if !keep {
+            mixer.stop(voice.id);
+        }
+        keep
+    });
+
+    let local_matrices = local_matrices(&mut transforms);
+
+    let player_camera = player_camera
+        .iter_mut()
+        .next()
+        .map(|(entity, mut transform)| (entity, transform.view_matrix()));
+
+    // The listener's entity, and the matrix from world space to the listener's space
+    let player = player_camera.map(|(entity, _)| entity);
+    let listener = match listeners.iter().find(|&entity| Some(entity) != player) {
+        Some(entity) => Some((entity, glm::inverse(&world_matrix(entity, &local_matrices)))),
+        None => player_camera,
+    };
+
+    let listener = listener.map(|(entity, view_matrix)| {
+        let position = glm::inverse(&view_matrix).column(3).xyz();
+        let velocity = velocity(output.last_listener_position, position, time.delta_time);
+
+        output.last_listener_position = Some(position);
+
+        (
+            entity,
+            Listener {
+                view_matrix,
+                position,
+                velocity,
+            },
+        )
+    });
+
+    for (entity, mut source) in sources.iter_mut() {
+        if source.state == PlaybackState::Stopped {
+            if let Some(voice) = output.voices.remove(&entity) {
+                mixer.stop(voice.id);
+            }
+            continue;
+        }
+
+        if let Entry::Vacant(entry) = output.voices.entry(entity) {
+            // Paused before it ever started
+            if source.state == PlaybackState::Paused {
+                continue;
+            }
+
+            // Sounds that failed to load finish right away
+            let started = match source.clip {
+                AudioClip::Sound(id) => {
+                    let Some(sound) = asset_manager.asset_manager.get_audio(id) else {
+                        continue;
+                    };
+                    let sound = sound.lock().unwrap();
+
+                    match sound.asset_info.status {
+                        AssetStatus::Loaded | AssetStatus::Invalid => (),
+                        _ => continue,
+                    }
+
+                    sound.buffer.clone().map(VoiceStart::Buffer)
+                }
+                AudioClip::Music(id) => {
+                    let Some(music) = asset_manager.asset_manager.get_music(id) else {
+                        continue;
+                    };
+                    let music = music.lock().unwrap();
+
+                    match music.asset_info.status {
+                        AssetStatus::Loaded | AssetStatus::Invalid => (),
+                        _ => continue,
+                    }
+
+                    music.stream(source.looping).map(VoiceStart::Stream)
+                }
+            };
+
+            let bus = mixer.bus(&source.bus).unwrap_or_else(|| {
+                warn!(
+                    "No audio bus named {}, playing on the master bus",
+                    source.bus
+                );
+                Mixer::MASTER
+            });
+
+            if let Some(started) = started {
+                let id = match started {
+                    VoiceStart::Buffer(buffer) => mixer.play(buffer, bus),
+                    VoiceStart::Stream(stream) => mixer.play_stream(stream, bus),
+                };
+
+                entry.insert(EntityVoice {
+                    id,
+                    last_position: None,
+                });
+            }
+        }
+
+        // The mixer removes voices at the end of their sound
+        let finished = match output.voices.get_mut(&entity) {
+            Some(voice) => {
+                let spatialized = match (&listener, source.spatial) {
+                    (Some((listener_entity, listener)), true) => {
+                        // A source on the listener itself is heard from right where it is
+                        let position = match entity == *listener_entity {
+                            true => listener.position,
+                            false => world_matrix(entity, &local_matrices).column(3).xyz(),
+                        };
+                        let velocity = velocity(voice.last_position, position, time.delta_time);
+
+                        voice.last_position = Some(position);
+
+                        spatialize(
+                            &source.attenuation,
+                            source.doppler,
+                            position,
+                            velocity,
+                            listener,
+                        )
+                    }
+                    _ => Spatialized {
+                        gain: 1.0,
+                        pan: 0.0,
+                        pitch: 1.0,
+                    },
+                };
+
+                match mixer.voice_mut(voice.id) {
+                    Some(mixer_voice) => {
+                        mixer_voice.pan_gains = pan_gains(spatialized.pan);
+                        mixer_voice.volume = source.volume * spatialized.gain;
+                        mixer_voice.pitch = source.pitch * spatialized.pitch;
+                        mixer_voice.looping = source.looping;
+                        mixer_voice.paused = source.state == PlaybackState::Paused;
+
+                        false
+                    }
+                    None => true,
+                }
+            }
+            None => true,
+        };
+
+        if finished {
+            output.voices.remove(&entity);
+            source.state = PlaybackState::Stopped;
+
+            if source.despawn_when_finished {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    drop(mixer);
+    output.advance(time.delta_time);
+}
+
+// What the voice of a source plays once its sound or music has loaded
+enum VoiceStart {
+    Buffer(SoundBuffer),
+    Stream(MusicStream),
+}
+
+// How fast something moved since the last update, still on the first one
+fn velocity(last_position: Option<glm::Vec3>, position: glm::Vec3, delta_time: f32) -> glm::Vec3 {
+    match (last_position, delta_time > 0.0) {
+        (Some(last_position), true) => (position - last_position) / delta_time,
+        _ => glm::Vec3::zeros(),
+    }
+}
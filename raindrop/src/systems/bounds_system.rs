@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use bevy_ecs::{
+    entity::Entity,
+    query::Without,
+    system::{Commands, Query, Res},
+};
+
+use crate::{
+    components::{Mesh, Parent, Player, Transform, WorldBounds},
+    resources::AssetManagerResource,
+    systems::world_matrix::{local_matrices, world_matrix},
+};
+
+/// Keep the world bounds of every entity with a mesh current, adding them once the mesh has loaded
+pub fn bounds_system(
+    mut commands: Commands,
+    mut transforms: Query<(Entity, &mut Transform, Option<&Parent>), Without<Player>>,
+    mut meshes: Query<(Entity, &Mesh, Option<&mut WorldBounds>), Without<Player>>,
+    asset_manager: Res<AssetManagerResource>,
+) {
+    let local_matrices = local_matrices(&mut transforms);
+
+    // Many entities share a mesh, so each is only locked once
+    let mut mesh_bounds = HashMap::new();
+
+    for (entity, mesh, world_bounds) in meshes.iter_mut() {
+        let bounds = *mesh_bounds.entry((mesh.id, mesh.part)).or_insert_with(|| {
+            let asset = asset_manager.asset_manager.get_mesh(mesh.id)?;
+            let asset = asset.lock().unwrap();
+
+            match mesh.part {
+                Some(part) => asset.part_bounds(part),
+                None if asset.bounds.is_empty() => None,
+                None => Some((asset.bounds, asset.bounding_sphere)),
+            }
+        });
+
+        let Some((aabb, sphere)) = bounds else {
+            continue;
+        };
+
+        let bounds = WorldBounds::from_mesh(&aabb, &sphere, &world_matrix(entity, &local_matrices));
+
+        match world_bounds {
+            Some(mut world_bounds) => *world_bounds = bounds,
+            None => {
+                commands.entity(entity).insert(bounds);
+            }
+        }
+    }
+}
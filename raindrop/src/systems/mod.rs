@@ -1,9 +1,19 @@
+pub mod animation_system;
+pub mod asset_event_system;
+pub mod audio_system;
+pub mod bounds_system;
 pub mod player_control_system;
 pub mod renderer_shutdown_system;
 pub mod renderer_system;
+mod spatial_audio;
 pub mod spin_system;
+mod world_matrix;
 
+pub use animation_system::animation_system;
+pub use asset_event_system::asset_event_system;
+pub use audio_system::audio_system;
+pub use bounds_system::bounds_system;
 pub use player_control_system::player_control_system;
 pub use renderer_shutdown_system::renderer_shutdown_system;
 pub use renderer_system::renderer_system;
-pub use spin_system::spin_system;
\ No newline at end of file
+pub use spin_system::spin_system;
@@ -1,9 +1,11 @@
 use crate::{
-    components::{Camera, Material, Mesh, Player, Transform},
+    components::{Camera, Lod, Material, Mesh, Parent, Player, Transform},
     resources::{AssetManagerResource, RendererResource},
+    systems::world_matrix::{local_matrices, world_matrix},
 };
 
 use bevy_ecs::{
+    entity::Entity,
     query::{With, Without},
     system::{NonSendMut, Query, ResMut},
 };
@@ -12,26 +14,38 @@ use renderer::Renderable;
 
 pub fn renderer_system(
     mut player_camera: Query<(&mut Camera, &mut Transform), With<Player>>,
-    mut renderable_objects: Query<(&mut Transform, &Mesh, &Material), Without<Player>>,
+    mut transforms: Query<(Entity, &mut Transform, Option<&Parent>), Without<Player>>,
+    renderable_objects: Query<(Entity, &Mesh, &Material, Option<&Lod>), Without<Player>>,
     mut renderer: NonSendMut<RendererResource>,
     mut asset_manager: ResMut<AssetManagerResource>,
 ) {
     let (camera, mut transform) = player_camera.iter_mut().next().unwrap();
 
     let view_matrix = transform.view_matrix();
+    let camera_position = transform.get_translation();
     let projection_matrix = camera.matrix();
 
+    let local_matrices = local_matrices(&mut transforms);
+
     let mut renderables: Vec<Renderable> = vec![];
-    for (mut transform, mesh, material) in renderable_objects.iter_mut() {
+    for (entity, mesh, material, lod) in renderable_objects.iter() {
+        let matrix = world_matrix(entity, &local_matrices);
+
+        // Measured to the mesh origin, without any Lod the full mesh is drawn
+        let lod = lod.map_or(0, |lod| {
+            lod.level(glm::distance(&camera_position, &matrix.column(3).xyz()))
+        });
+
         renderables.push(Renderable {
-            mesh: mesh.id.clone(),
-            material: material.id.clone(),
-            matrix: transform.model_matrix(),
+            mesh: mesh.id,
+            part: mesh.part,
+            material: material.id,
+            matrix,
+            lod,
         });
     }
 
-    renderables
-        .sort_unstable_by_key(|renderable| (renderable.mesh.clone(), renderable.material.clone()));
+    renderables.sort_unstable_by_key(|renderable| (renderable.mesh, renderable.material));
 
     renderer.as_mut().renderer.render(
         projection_matrix,
@@ -39,8 +53,4 @@ pub fn renderer_system(
         &renderables,
         &mut asset_manager.as_mut().asset_manager,
     );
-
-    asset_manager
-        .asset_manager
-        .get_audio(&"assets/sounds/CantinaBand60.wav".to_string());
 }
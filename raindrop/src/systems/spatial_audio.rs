@@ -0,0 +1,165 @@
+use crate::components::Attenuation;
+
+// Meters per second, the units of the world are taken as meters
+const SPEED_OF_SOUND: f32 = 343.0;
+
+/// Where the listener is and which way it faces
+pub struct Listener {
+    /// From world space to the listener's space, where +x is to its right
+    pub view_matrix: glm::Mat4,
+    pub position: glm::Vec3,
+    pub velocity: glm::Vec3,
+}
+
+/// How a spatial source is heard by the listener
+#[derive(Debug, PartialEq)]
+pub struct Spatialized {
+    /// Volume from the attenuation, from 0 to 1
+    pub gain: f32,
+    /// -1 is fully left, 1 fully right
+    pub pan: f32,
+    /// Playback speed from the doppler effect, 1 without it
+    pub pitch: f32,
+}
+
+/// How the listener hears a source at the position, moving at the velocity
+pub fn spatialize(
+    attenuation: &Attenuation,
+    doppler: bool,
+    position: glm::Vec3,
+    velocity: glm::Vec3,
+    listener: &Listener,
+) -> Spatialized {
+    let offset = position - listener.position;
+    let distance = offset.norm();
+
+    // A source at the listener is heard in the middle
+    let local = (listener.view_matrix * glm::vec4(position.x, position.y, position.z, 1.0)).xyz();
+    let pan = match local.norm() > f32::EPSILON {
+        true => (local.x / local.norm()).clamp(-1.0, 1.0),
+        false => 0.0,
+    };
+
+    let pitch = match doppler && distance > f32::EPSILON {
+        true => {
+            // Speeds along the line from the listener to the source, kept below the speed
+            // of sound so the pitch stays finite
+            let direction = offset / distance;
+            let limit = SPEED_OF_SOUND * 0.9;
+            let listener_speed = listener.velocity.dot(&direction).clamp(-limit, limit);
+            let source_speed = velocity.dot(&direction).clamp(-limit, limit);
+
+            (SPEED_OF_SOUND + listener_speed) / (SPEED_OF_SOUND + source_speed)
+        }
+        false => 1.0,
+    };
+
+    Spatialized {
+        gain: attenuation.gain(distance),
+        pan,
+        pitch,
+    }
+}
+
+/// The volume of the left and right channel for the pan, both are full in the middle
+pub fn pan_gains(pan: f32) -> [f32; 2] {
+    [(1.0 - pan).min(1.0), (1.0 + pan).min(1.0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // At the origin looking down -z, like a camera
+    fn listener(velocity: glm::Vec3) -> Listener {
+        Listener {
+            view_matrix: glm::look_at(
+                &glm::Vec3::zeros(),
+                &glm::vec3(0.0, 0.0, -1.0),
+                &glm::vec3(0.0, 1.0, 0.0),
+            ),
+            position: glm::Vec3::zeros(),
+            velocity,
+        }
+    }
+
+    #[test]
+    fn test_pan_follows_the_listener() {
+        let attenuation = Attenuation::default();
+        let still = glm::Vec3::zeros();
+
+        let right = spatialize(
+            &attenuation,
+            false,
+            glm::vec3(2.0, 0.0, 0.0),
+            still,
+            &listener(still),
+        );
+        assert!((right.pan - 1.0).abs() < 1e-6);
+        assert!((right.gain - 0.5).abs() < 1e-6);
+        assert_eq!(right.pitch, 1.0);
+
+        let ahead = spatialize(
+            &attenuation,
+            false,
+            glm::vec3(0.0, 0.0, -5.0),
+            still,
+            &listener(still),
+        );
+        assert!(ahead.pan.abs() < 1e-6);
+
+        let left = spatialize(
+            &attenuation,
+            false,
+            glm::vec3(-1.0, 0.0, -1.0),
+            still,
+            &listener(still),
+        );
+        assert!((left.pan + std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+
+        assert_eq!(pan_gains(0.0), [1.0, 1.0]);
+        assert_eq!(pan_gains(left.pan)[0], 1.0);
+        assert!(pan_gains(left.pan)[1] < 0.3);
+    }
+
+    #[test]
+    fn test_doppler_raises_pitch_of_approaching_sources() {
+        let attenuation = Attenuation::default();
+        let position = glm::vec3(0.0, 0.0, -10.0);
+        let still = glm::Vec3::zeros();
+
+        let approaching = spatialize(
+            &attenuation,
+            true,
+            position,
+            glm::vec3(0.0, 0.0, 20.0),
+            &listener(still),
+        );
+        let leaving = spatialize(
+            &attenuation,
+            true,
+            position,
+            glm::vec3(0.0, 0.0, -20.0),
+            &listener(still),
+        );
+        let chasing = spatialize(
+            &attenuation,
+            true,
+            position,
+            still,
+            &listener(glm::vec3(0.0, 0.0, -20.0)),
+        );
+        let passing = spatialize(
+            &attenuation,
+            true,
+            position,
+            glm::vec3(20.0, 0.0, 0.0),
+            &listener(still),
+        );
+
+        assert!((approaching.pitch - 343.0 / 323.0).abs() < 1e-6);
+        assert!((leaving.pitch - 343.0 / 363.0).abs() < 1e-6);
+        assert!((chasing.pitch - 363.0 / 343.0).abs() < 1e-6);
+        assert_eq!(passing.pitch, 1.0);
+    }
+}
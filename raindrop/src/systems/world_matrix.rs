@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use bevy_ecs::{entity::Entity, query::QueryFilter, system::Query};
+
+use crate::components::{Parent, Transform};
+
+pub type LocalMatrices = HashMap<Entity, (glm::Mat4, Option<Entity>)>;
+
+/// The model matrix and parent of every entity the query finds
+pub fn local_matrices<F: QueryFilter>(
+    transforms: &mut Query<(Entity, &mut Transform, Option<&Parent>), F>,
+) -> LocalMatrices {
+    let mut local_matrices = HashMap::new();
+
+    for (entity, mut transform, parent) in transforms.iter_mut() {
+        local_matrices.insert(
+            entity,
+            (transform.model_matrix(), parent.map(|parent| parent.0)),
+        );
+    }
+
+    local_matrices
+}
+
+// Combine the transforms of the entity and all of its parents
+// Parents linked into a cycle are followed no further than once around it
+pub fn world_matrix(entity: Entity, local_matrices: &LocalMatrices) -> glm::Mat4 {
+    let mut matrix = glm::Mat4::identity();
+    let mut current = Some(entity);
+
+    // Without a cycle, no entity has more parents than there are other entities
+    for _ in 0..local_matrices.len() {
+        let Some((local_matrix, parent)) = current.and_then(|entity| local_matrices.get(&entity))
+        else {
+            break;
+        };
+
+        matrix = local_matrix * matrix;
+        current = *parent;
+    }
+
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::world::World;
+
+    use super::*;
+
+    #[test]
+    fn test_world_matrix_stops_at_parent_cycles() {
+        let mut world = World::new();
+        let (first, second) = (world.spawn_empty().id(), world.spawn_empty().id());
+        let step = glm::translation(&glm::vec3(1.0, 0.0, 0.0));
+
+        let mut local_matrices = LocalMatrices::new();
+        local_matrices.insert(first, (step, Some(second)));
+        local_matrices.insert(second, (step, None));
+        assert_eq!(world_matrix(first, &local_matrices).column(3).x, 2.0);
+
+        local_matrices.insert(second, (step, Some(first)));
+        assert_eq!(world_matrix(first, &local_matrices).column(3).x, 2.0);
+    }
+}
@@ -4,10 +4,10 @@ use ash::{
 };
 use vk_mem::{Alloc, AllocationCreateInfo};
 
-use gpu_info::Buffer;
+use gpu_info::{Buffer, Image, MeshBuffers};
 
 use crate::primitives::AllocatedImage;
-use asset_manager::Vertex;
+use asset_manager::{Indices, Vertex};
 
 pub struct Allocator {
     allocator: vk_mem::Allocator,
@@ -50,35 +50,124 @@ impl Allocator {
         }
     }
 
-    pub fn create_vertex_buffer(&self, vertices: &[Vertex]) -> Buffer {
-        let (buffer, mut allocation) = unsafe {
+    pub fn destroy_texture_image(&self, image: &mut Image) {
+        unsafe {
             self.allocator
-                .create_buffer(
-                    &BufferCreateInfo::default()
-                        .size(std::mem::size_of_val(vertices) as u64)
-                        .usage(BufferUsageFlags::VERTEX_BUFFER),
-                    &AllocationCreateInfo {
-                        required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
-                        flags: vk_mem::AllocationCreateFlags::MAPPED
-                            | vk_mem::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
-                        usage: vk_mem::MemoryUsage::Auto,
-                        ..Default::default()
-                    },
-                )
-                .unwrap()
+                .destroy_image(image.image, &mut image.allocation);
+        }
+    }
+
+    /// Fails for meshes without vertices or indices, Vulkan has no empty buffers
+    pub fn create_mesh_buffers(
+        &self,
+        vertices: &[Vertex],
+        indices: &Indices,
+    ) -> Result<MeshBuffers, String> {
+        let (mut index_buffer, index_type) = match indices {
+            Indices::U16(indices) => (self.create_index_buffer(indices)?, vk::IndexType::UINT16),
+            Indices::U32(indices) => (self.create_index_buffer(indices)?, vk::IndexType::UINT32),
         };
 
-        let memory_handle = unsafe { self.allocator.map_memory(&mut allocation).unwrap() };
+        let vertex_buffer = match self.create_vertex_buffer(vertices) {
+            Ok(vertex_buffer) => vertex_buffer,
+            Err(e) => {
+                self.destroy_buffer(&mut index_buffer);
+                return Err(e);
+            }
+        };
+
+        Ok(MeshBuffers {
+            vertex_buffer,
+            index_buffer,
+            index_type,
+            index_count: indices.len() as u32,
+        })
+    }
+
+    pub fn create_vertex_buffer(&self, vertices: &[Vertex]) -> Result<Buffer, String> {
+        self.create_filled_buffer(
+            vertices,
+            BufferUsageFlags::VERTEX_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+    }
+
+    pub fn create_index_buffer<T: Copy>(&self, indices: &[T]) -> Result<Buffer, String> {
+        self.create_filled_buffer(
+            indices,
+            BufferUsageFlags::INDEX_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+    }
+
+    /// A host visible buffer holding data to be copied into device local images
+    pub fn create_staging_buffer(&self, data: &[u8]) -> Result<Buffer, String> {
+        self.create_filled_buffer(
+            data,
+            BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )
+    }
+
+    /// A host visible buffer holding shader constants, written once at creation
+    pub fn create_uniform_buffer<T: Copy>(&self, data: &T) -> Result<Buffer, String> {
+        self.create_filled_buffer(
+            std::slice::from_ref(data),
+            BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )
+    }
+
+    fn create_filled_buffer<T: Copy>(
+        &self,
+        data: &[T],
+        usage: BufferUsageFlags,
+        required_flags: vk::MemoryPropertyFlags,
+    ) -> Result<Buffer, String> {
+        if data.is_empty() {
+            return Err("Failed to create buffer: no data to fill it with".to_owned());
+        }
+
+        let (buffer, mut allocation) = match unsafe {
+            self.allocator.create_buffer(
+                &BufferCreateInfo::default()
+                    .size(std::mem::size_of_val(data) as u64)
+                    .usage(usage),
+                &AllocationCreateInfo {
+                    required_flags,
+                    flags: vk_mem::AllocationCreateFlags::MAPPED
+                        | vk_mem::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+                    usage: vk_mem::MemoryUsage::Auto,
+                    ..Default::default()
+                },
+            )
+        } {
+            Ok(created) => created,
+            Err(e) => return Err("Failed to create buffer: ".to_owned() + &e.to_string()),
+        };
+
+        let memory_handle = match unsafe { self.allocator.map_memory(&mut allocation) } {
+            Ok(memory_handle) => memory_handle,
+            Err(e) => {
+                unsafe { self.allocator.destroy_buffer(buffer, &mut allocation) };
+                return Err("Failed to map buffer: ".to_owned() + &e.to_string());
+            }
+        };
         unsafe {
             std::ptr::copy_nonoverlapping(
-                vertices.as_ptr() as *const u8,
+                data.as_ptr() as *const u8,
                 memory_handle,
-                std::mem::size_of_val(vertices),
+                std::mem::size_of_val(data),
             );
         }
         unsafe { self.allocator.unmap_memory(&mut allocation) };
 
-        Buffer { buffer, allocation }
+        Ok(Buffer { buffer, allocation })
+    }
+
+    pub fn destroy_mesh_buffers(&self, mesh_buffers: &mut MeshBuffers) {
+        self.destroy_buffer(&mut mesh_buffers.vertex_buffer);
+        self.destroy_buffer(&mut mesh_buffers.index_buffer);
     }
 
     pub fn destroy_buffer(&self, buffer: &mut Buffer) {
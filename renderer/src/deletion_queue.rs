@@ -0,0 +1,77 @@
+use ash::Device;
+use gpu_info::{Buffer, MeshBuffers, TextureImage};
+
+use crate::{
+    boilerplate::allocator::Allocator, material_data::MaterialData, primitives::Pipeline, texture,
+};
+
+/// A GPU resource that has been replaced but may still be used by frames in flight
+pub enum Retired {
+    MeshBuffers(MeshBuffers),
+    Pipeline(Box<Pipeline>),
+    TextureImage(TextureImage),
+    /// Staging buffers are retired as soon as the copy out of them is recorded
+    Buffer(Buffer),
+    MaterialData(MaterialData),
+}
+
+/// Holds on to retired resources until every frame that could use them has finished
+pub struct DeletionQueue {
+    retired: Vec<(u64, Retired)>,
+}
+
+impl DeletionQueue {
+    pub fn new() -> DeletionQueue {
+        DeletionQueue { retired: vec![] }
+    }
+
+    pub fn push(&mut self, framenumber: u64, resource: Retired) {
+        self.retired.push((framenumber, resource));
+    }
+
+    /// Free everything retired at least `frame_overlap` frames before `framenumber`
+    /// Only valid once the fence for `framenumber` has been waited on
+    pub fn flush(
+        &mut self,
+        framenumber: u64,
+        frame_overlap: u64,
+        device: &Device,
+        allocator: &Allocator,
+    ) {
+        let (expired, retained) = std::mem::take(&mut self.retired)
+            .into_iter()
+            .partition(|(retired_at, _)| retired_at + frame_overlap <= framenumber);
+
+        self.retired = retained;
+
+        DeletionQueue::free(expired, device, allocator);
+    }
+
+    /// Free everything, only valid once the device is idle
+    pub fn flush_all(&mut self, device: &Device, allocator: &Allocator) {
+        DeletionQueue::free(std::mem::take(&mut self.retired), device, allocator);
+    }
+
+    fn free(resources: Vec<(u64, Retired)>, device: &Device, allocator: &Allocator) {
+        for (_, resource) in resources {
+            match resource {
+                Retired::MeshBuffers(mut mesh_buffers) => {
+                    allocator.destroy_mesh_buffers(&mut mesh_buffers)
+                }
+                // Pipelines clean themselves up when dropped
+                Retired::Pipeline(pipeline) => drop(pipeline),
+                Retired::TextureImage(mut texture_image) => {
+                    texture::destroy_texture_image(device, allocator, &mut texture_image)
+                }
+                Retired::Buffer(mut buffer) => allocator.destroy_buffer(&mut buffer),
+                Retired::MaterialData(material_data) => material_data.free(device, allocator),
+            }
+        }
+    }
+}
+
+impl Default for DeletionQueue {
+    fn default() -> Self {
+        DeletionQueue::new()
+    }
+}
@@ -6,13 +6,16 @@ extern crate lazy_static;
 
 mod boilerplate;
 mod debug;
+mod deletion_queue;
 mod material;
+mod material_data;
 mod mesh;
 mod primitives;
 pub mod renderable;
 pub mod renderer;
+mod texture;
 
 use boilerplate::Boilerplate;
-use material::Material;
+pub use material::Material;
 pub use renderable::Renderable;
 pub use renderer::Renderer;
\ No newline at end of file
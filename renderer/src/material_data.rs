@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+
+use ash::{vk, Device};
+use asset_manager::{AlphaMode, AssetManager, Handle, Mesh, PbrMaterial, Texture, TextureRef};
+use log::warn;
+
+use gpu_info::{Buffer, TextureImage};
+
+use crate::{
+    boilerplate::allocator::Allocator,
+    deletion_queue::{DeletionQueue, Retired},
+    primitives::CommandManager,
+    texture,
+};
+
+const TEXTURE_COUNT: usize = 5;
+const MAX_MATERIALS: u32 = 1024;
+
+// Which fallback each texture slot uses: white sRGB, white linear or a flat normal
+const FALLBACK_SLOTS: [usize; TEXTURE_COUNT] = [0, 1, 2, 1, 0];
+
+/// A material slot of a mesh, `None` for primitives without a material
+pub type MaterialKey = (Handle<Mesh>, Option<usize>);
+
+/// The material uniform block of the PBR shaders, laid out to match std140
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MaterialUniforms {
+    pub base_color_factor: glm::Vec4,
+    /// The w component is unused
+    pub emissive_factor: glm::Vec4,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub alpha_cutoff: f32,
+    /// 0 opaque, 1 mask, 2 blend
+    pub alpha_mode: u32,
+    pub double_sided: u32,
+}
+
+impl MaterialUniforms {
+    pub fn new(material: &PbrMaterial) -> MaterialUniforms {
+        MaterialUniforms {
+            base_color_factor: material.base_color_factor,
+            emissive_factor: material.emissive_factor.push(0.0),
+            metallic_factor: material.metallic_factor,
+            roughness_factor: material.roughness_factor,
+            normal_scale: material.normal_scale,
+            occlusion_strength: material.occlusion_strength,
+            alpha_cutoff: material.alpha_cutoff,
+            alpha_mode: match material.alpha_mode {
+                AlphaMode::Opaque => 0,
+                AlphaMode::Mask => 1,
+                AlphaMode::Blend => 2,
+            },
+            double_sided: material.double_sided as u32,
+        }
+    }
+}
+
+/// The descriptor set of one material, and the images it was written with
+pub struct MaterialData {
+    pool: vk::DescriptorPool,
+    pub descriptor_set: vk::DescriptorSet,
+    uniform_buffer: Buffer,
+    textures: [Option<Handle<Texture>>; TEXTURE_COUNT],
+    images: [(vk::ImageView, vk::Sampler); TEXTURE_COUNT],
+    checked_frame: u64,
+}
+
+impl MaterialData {
+    pub fn free(mut self, device: &Device, allocator: &Allocator) {
+        unsafe {
+            let _ = device.free_descriptor_sets(self.pool, &[self.descriptor_set]);
+        }
+
+        allocator.destroy_buffer(&mut self.uniform_buffer);
+    }
+}
+
+/// Turns glTF materials into descriptor sets for the PBR shaders
+///
+/// Set 0 holds the material uniforms at binding 0, then the base color, metallic-roughness,
+/// normal, occlusion and emissive textures. Textures that are missing or still loading are
+/// replaced by neutral fallbacks, and the set is rewritten once the real ones are uploaded.
+pub struct MaterialDescriptors {
+    device: Device,
+    pub set_layout: vk::DescriptorSetLayout,
+    pool: vk::DescriptorPool,
+    fallbacks: Vec<TextureImage>,
+    default_data: Option<MaterialData>,
+    materials: HashMap<MaterialKey, MaterialData>,
+}
+
+impl MaterialDescriptors {
+    pub fn new(device: &Device) -> Result<MaterialDescriptors, String> {
+        let mut bindings = vec![vk::DescriptorSetLayoutBinding::default()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)];
+
+        for binding in 1..=TEXTURE_COUNT as u32 {
+            bindings.push(
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+            );
+        }
+
+        let set_layout_create_info =
+            vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+        let set_layout =
+            match unsafe { device.create_descriptor_set_layout(&set_layout_create_info, None) } {
+                Ok(set_layout) => set_layout,
+                Err(e) => {
+                    return Err("Failed to create material set layout: ".to_owned() + &e.to_string())
+                }
+            };
+
+        let pool_sizes = [
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_count: MAX_MATERIALS,
+            },
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: MAX_MATERIALS * TEXTURE_COUNT as u32,
+            },
+        ];
+
+        let pool_create_info = vk::DescriptorPoolCreateInfo::default()
+            .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .max_sets(MAX_MATERIALS)
+            .pool_sizes(&pool_sizes);
+
+        let pool = match unsafe { device.create_descriptor_pool(&pool_create_info, None) } {
+            Ok(pool) => pool,
+            Err(e) => {
+                unsafe { device.destroy_descriptor_set_layout(set_layout, None) };
+                return Err(
+                    "Failed to create material descriptor pool: ".to_owned() + &e.to_string()
+                );
+            }
+        };
+
+        Ok(MaterialDescriptors {
+            device: device.clone(),
+            set_layout,
+            pool,
+            fallbacks: vec![],
+            default_data: None,
+            materials: HashMap::new(),
+        })
+    }
+
+    /// Record the upload of the fallback textures and create the default material
+    /// Only does anything the first time it is called
+    pub fn upload_fallbacks(
+        &mut self,
+        allocator: &Allocator,
+        command_manager: &CommandManager,
+        deletion_queue: &mut DeletionQueue,
+        framenumber: u64,
+    ) -> Result<(), String> {
+        if self.default_data.is_some() {
+            return Ok(());
+        }
+
+        for (format, pixel) in [
+            (vk::Format::R8G8B8A8_SRGB, [255, 255, 255, 255]),
+            (vk::Format::R8G8B8A8_UNORM, [255, 255, 255, 255]),
+            (vk::Format::R8G8B8A8_UNORM, [128, 128, 255, 255]),
+        ] {
+            let (texture_image, staging_buffer) = texture::upload_pixels(
+                &self.device,
+                allocator,
+                command_manager,
+                (1, 1),
+                format,
+                &[pixel.to_vec()],
+            )?;
+
+            deletion_queue.push(framenumber, Retired::Buffer(staging_buffer));
+            self.fallbacks.push(texture_image);
+        }
+
+        let images = self.fallback_images();
+        self.default_data = Some(self.create_data(
+            &PbrMaterial::default(),
+            [None; TEXTURE_COUNT],
+            images,
+            allocator,
+        )?);
+
+        Ok(())
+    }
+
+    /// The descriptor set of a mesh's material slot, the default material's without a material
+    /// Rewritten in a new set when one of its textures finished uploading or was reloaded
+    pub fn descriptor_set(
+        &mut self,
+        allocator: &Allocator,
+        deletion_queue: &mut DeletionQueue,
+        framenumber: u64,
+        asset_manager: &mut AssetManager,
+        key: MaterialKey,
+        material: Option<&PbrMaterial>,
+    ) -> vk::DescriptorSet {
+        let default_set = self.default_data.as_ref().unwrap().descriptor_set;
+
+        let material = match material {
+            Some(material) => material,
+            None => return default_set,
+        };
+
+        let textures = match self.materials.get(&key) {
+            Some(data) if data.checked_frame == framenumber => return data.descriptor_set,
+            Some(data) => data.textures,
+            None => MaterialDescriptors::load_textures(material, asset_manager),
+        };
+
+        let images = self.images(&textures, asset_manager);
+
+        if let Some(data) = self.materials.get_mut(&key) {
+            if data.images == images {
+                data.checked_frame = framenumber;
+                return data.descriptor_set;
+            }
+        }
+
+        // Frames in flight may still be using the old set
+        if let Some(previous) = self.materials.remove(&key) {
+            deletion_queue.push(framenumber, Retired::MaterialData(previous));
+        }
+
+        match self.create_data(material, textures, images, allocator) {
+            Ok(mut data) => {
+                data.checked_frame = framenumber;
+
+                let descriptor_set = data.descriptor_set;
+                self.materials.insert(key, data);
+
+                descriptor_set
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to create material descriptors, using the default: {}",
+                    e
+                );
+                default_set
+            }
+        }
+    }
+
+    /// Retire the descriptor sets of every material of a mesh, after it was reloaded
+    pub fn retire_mesh(
+        &mut self,
+        mesh: Handle<Mesh>,
+        deletion_queue: &mut DeletionQueue,
+        framenumber: u64,
+    ) {
+        let keys: Vec<MaterialKey> = self
+            .materials
+            .keys()
+            .filter(|(material_mesh, _)| *material_mesh == mesh)
+            .copied()
+            .collect();
+
+        for key in keys {
+            let data = self.materials.remove(&key).unwrap();
+            deletion_queue.push(framenumber, Retired::MaterialData(data));
+        }
+    }
+
+    /// Free everything, only valid once the device is idle
+    pub fn destroy(&mut self, allocator: &Allocator) {
+        for (_, data) in self.materials.drain() {
+            data.free(&self.device, allocator);
+        }
+
+        if let Some(data) = self.default_data.take() {
+            data.free(&self.device, allocator);
+        }
+
+        for mut fallback in self.fallbacks.drain(..) {
+            texture::destroy_texture_image(&self.device, allocator, &mut fallback);
+        }
+
+        unsafe {
+            self.device.destroy_descriptor_pool(self.pool, None);
+            self.device
+                .destroy_descriptor_set_layout(self.set_layout, None);
+        }
+    }
+
+    fn load_textures(
+        material: &PbrMaterial,
+        asset_manager: &mut AssetManager,
+    ) -> [Option<Handle<Texture>>; TEXTURE_COUNT] {
+        let mut load = |texture: &Option<TextureRef>| {
+            texture
+                .as_ref()
+                .map(|texture| asset_manager.load_texture(&texture.id, texture.color_space))
+        };
+
+        [
+            load(&material.base_color_texture),
+            load(&material.metallic_roughness_texture),
+            load(&material.normal_texture),
+            load(&material.occlusion_texture),
+            load(&material.emissive_texture),
+        ]
+    }
+
+    fn fallback_images(&self) -> [(vk::ImageView, vk::Sampler); TEXTURE_COUNT] {
+        FALLBACK_SLOTS.map(|fallback| {
+            let fallback = &self.fallbacks[fallback];
+            (fallback.view, fallback.sampler)
+        })
+    }
+
+    // The image to bind in each slot, the uploaded texture or the slot's fallback
+    fn images(
+        &self,
+        textures: &[Option<Handle<Texture>>; TEXTURE_COUNT],
+        asset_manager: &AssetManager,
+    ) -> [(vk::ImageView, vk::Sampler); TEXTURE_COUNT] {
+        let mut images = self.fallback_images();
+
+        for (image, texture) in images.iter_mut().zip(textures) {
+            let texture = match texture.and_then(|texture| asset_manager.get_texture(texture)) {
+                Some(texture) => texture,
+                None => continue,
+            };
+
+            let texture = texture.lock().unwrap();
+
+            if let Some(gpu_info) = &texture.gpu_info {
+                *image = (gpu_info.view, gpu_info.sampler);
+            }
+        }
+
+        images
+    }
+
+    fn create_data(
+        &self,
+        material: &PbrMaterial,
+        textures: [Option<Handle<Texture>>; TEXTURE_COUNT],
+        images: [(vk::ImageView, vk::Sampler); TEXTURE_COUNT],
+        allocator: &Allocator,
+    ) -> Result<MaterialData, String> {
+        let set_layouts = [self.set_layout];
+        let allocate_info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(self.pool)
+            .set_layouts(&set_layouts);
+
+        let descriptor_set = match unsafe { self.device.allocate_descriptor_sets(&allocate_info) } {
+            Ok(descriptor_sets) => descriptor_sets[0],
+            Err(e) => return Err("Failed to allocate material set: ".to_owned() + &e.to_string()),
+        };
+
+        let uniform_buffer = match allocator.create_uniform_buffer(&MaterialUniforms::new(material))
+        {
+            Ok(uniform_buffer) => uniform_buffer,
+            Err(e) => {
+                let _ = unsafe {
+                    self.device
+                        .free_descriptor_sets(self.pool, &[descriptor_set])
+                };
+                return Err(e);
+            }
+        };
+
+        let buffer_infos = [vk::DescriptorBufferInfo::default()
+            .buffer(uniform_buffer.buffer)
+            .offset(0)
+            .range(std::mem::size_of::<MaterialUniforms>() as u64)];
+
+        let image_infos = images.map(|(view, sampler)| {
+            [vk::DescriptorImageInfo::default()
+                .sampler(sampler)
+                .image_view(view)
+                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)]
+        });
+
+        let mut writes = vec![vk::WriteDescriptorSet::default()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .buffer_info(&buffer_infos)];
+
+        for (slot, image_info) in image_infos.iter().enumerate() {
+            writes.push(
+                vk::WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(slot as u32 + 1)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(image_info),
+            );
+        }
+
+        unsafe { self.device.update_descriptor_sets(&writes, &[]) };
+
+        Ok(MaterialData {
+            pool: self.pool,
+            descriptor_set,
+            uniform_buffer,
+            textures,
+            images,
+            checked_frame: 0,
+        })
+    }
+}
@@ -1,11 +1,11 @@
 use std::mem::size_of;
 
 use ash::vk::{
-    PipelineVertexInputStateCreateFlags, VertexInputAttributeDescription,
+    Format, PipelineVertexInputStateCreateFlags, VertexInputAttributeDescription,
     VertexInputBindingDescription, VertexInputRate,
 };
+use asset_manager::{Vertex, VertexFormat};
 
-use memoffset::offset_of;
 use serde_derive::Serialize;
 
 pub struct VertexInputDescription {
@@ -14,54 +14,32 @@ pub struct VertexInputDescription {
     pub flags: PipelineVertexInputStateCreateFlags,
 }
 
-#[repr(C, align(16))]
-#[derive(Clone, Copy)]
-pub struct Vertex {
-    pub position: glm::Vec3,
-    pub normal: glm::Vec3,
-    pub color: glm::Vec3,
-}
-
-impl Vertex {
-    pub fn get_vertex_input_description() -> VertexInputDescription {
-        let mut vertex_input_description = VertexInputDescription {
-            binding_descriptions: vec![],
-            attribute_descriptions: vec![],
-            flags: PipelineVertexInputStateCreateFlags::empty(),
-        };
-
-        vertex_input_description.binding_descriptions.push(
-            VertexInputBindingDescription::default()
-                .binding(0)
-                .stride(size_of::<Vertex>() as u32)
-                .input_rate(VertexInputRate::VERTEX),
-        );
-
-        vertex_input_description.attribute_descriptions.push(
-            VertexInputAttributeDescription::default()
-                .binding(0)
-                .location(0)
-                .format(ash::vk::Format::R32G32B32_SFLOAT)
-                .offset(offset_of!(Self, position) as u32),
-        );
-
-        vertex_input_description.attribute_descriptions.push(
-            VertexInputAttributeDescription::default()
-                .binding(0)
-                .location(1)
-                .format(ash::vk::Format::R32G32B32_SFLOAT)
-                .offset(offset_of!(Self, normal) as u32),
-        );
+/// The vertex input of every mesh pipeline, built from the layout of `asset_manager::Vertex`
+pub fn get_vertex_input_description() -> VertexInputDescription {
+    let binding_descriptions = vec![VertexInputBindingDescription::default()
+        .binding(0)
+        .stride(size_of::<Vertex>() as u32)
+        .input_rate(VertexInputRate::VERTEX)];
 
-        vertex_input_description.attribute_descriptions.push(
+    let attribute_descriptions = Vertex::ATTRIBUTES
+        .iter()
+        .map(|attribute| {
             VertexInputAttributeDescription::default()
                 .binding(0)
-                .location(2)
-                .format(ash::vk::Format::R32G32B32_SFLOAT)
-                .offset(offset_of!(Self, color) as u32),
-        );
-
-        vertex_input_description
+                .location(attribute.location)
+                .format(match attribute.format {
+                    VertexFormat::Float32x2 => Format::R32G32_SFLOAT,
+                    VertexFormat::Float32x3 => Format::R32G32B32_SFLOAT,
+                    VertexFormat::Float32x4 => Format::R32G32B32A32_SFLOAT,
+                })
+                .offset(attribute.offset)
+        })
+        .collect();
+
+    VertexInputDescription {
+        binding_descriptions,
+        attribute_descriptions,
+        flags: PipelineVertexInputStateCreateFlags::empty(),
     }
 }
 
@@ -9,7 +9,7 @@ use ash::{
 use log::error;
 use serde::Serialize;
 
-use super::{Pipeline, Queue};
+use super::Queue;
 
 pub struct CommandManager {
     device: Device,
@@ -144,12 +144,12 @@ impl CommandManager {
         }
     }
 
-    pub fn bind_pipeline(&self, pipeline: &Pipeline) {
+    pub fn bind_pipeline(&self, pipeline: vk::Pipeline) {
         unsafe {
             self.device.cmd_bind_pipeline(
                 self.main_command_buffer,
                 PipelineBindPoint::GRAPHICS,
-                pipeline.pipeline,
+                pipeline,
             )
         };
     }
@@ -165,6 +165,99 @@ impl CommandManager {
         };
     }
 
+    pub fn bind_index_buffer(&self, buffer: vk::Buffer, offset: u64, index_type: vk::IndexType) {
+        unsafe {
+            self.device
+                .cmd_bind_index_buffer(self.main_command_buffer, buffer, offset, index_type)
+        };
+    }
+
+    pub fn bind_descriptor_sets(
+        &self,
+        layout: PipelineLayout,
+        first_set: u32,
+        descriptor_sets: &[vk::DescriptorSet],
+    ) {
+        unsafe {
+            self.device.cmd_bind_descriptor_sets(
+                self.main_command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                layout,
+                first_set,
+                descriptor_sets,
+                &[],
+            )
+        };
+    }
+
+    pub fn transition_image_layout(
+        &self,
+        image: vk::Image,
+        subresource_range: vk::ImageSubresourceRange,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+    ) {
+        let (src_access_mask, src_stage, dst_access_mask, dst_stage) = match new_layout {
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL => (
+                vk::AccessFlags::empty(),
+                PipelineStageFlags::TOP_OF_PIPE,
+                vk::AccessFlags::TRANSFER_WRITE,
+                PipelineStageFlags::TRANSFER,
+            ),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => (
+                vk::AccessFlags::TRANSFER_WRITE,
+                PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::SHADER_READ,
+                PipelineStageFlags::FRAGMENT_SHADER,
+            ),
+            _ => (
+                vk::AccessFlags::MEMORY_WRITE,
+                PipelineStageFlags::ALL_COMMANDS,
+                vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+                PipelineStageFlags::ALL_COMMANDS,
+            ),
+        };
+
+        let barrier = vk::ImageMemoryBarrier::default()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range)
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask);
+
+        unsafe {
+            self.device.cmd_pipeline_barrier(
+                self.main_command_buffer,
+                src_stage,
+                dst_stage,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            )
+        };
+    }
+
+    pub fn copy_buffer_to_image(
+        &self,
+        buffer: vk::Buffer,
+        image: vk::Image,
+        regions: &[vk::BufferImageCopy],
+    ) {
+        unsafe {
+            self.device.cmd_copy_buffer_to_image(
+                self.main_command_buffer,
+                buffer,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                regions,
+            )
+        };
+    }
+
     pub fn push_constants<T: Serialize>(&self, layout: PipelineLayout, constants: T) {
         let bytes = bincode::serialize(&constants).unwrap();
 
@@ -179,19 +272,21 @@ impl CommandManager {
         }
     }
 
-    pub fn draw(
+    pub fn draw_indexed(
         &self,
-        vertex_count: u32,
+        index_count: u32,
         instance_count: u32,
-        first_vertex: u32,
+        first_index: u32,
+        vertex_offset: i32,
         first_instance: u32,
     ) {
         unsafe {
-            self.device.cmd_draw(
+            self.device.cmd_draw_indexed(
                 self.main_command_buffer,
-                vertex_count,
+                index_count,
                 instance_count,
-                first_vertex,
+                first_index,
+                vertex_offset,
                 first_instance,
             )
         };
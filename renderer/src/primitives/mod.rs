@@ -8,7 +8,7 @@ pub mod swapchain;
 
 pub use allocated_image::AllocatedImage;
 pub use command_manager::CommandManager;
-pub use pipeline::Pipeline;
+pub use pipeline::{Pipeline, PipelineVariant};
 pub use queue::Queue;
 pub use shader::Shader;
 pub use surface::Surface;
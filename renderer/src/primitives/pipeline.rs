@@ -2,28 +2,73 @@ use std::mem::size_of;
 
 use ash::{
     vk::{
-        self, ColorComponentFlags, CullModeFlags, Extent2D, FrontFace, GraphicsPipelineCreateInfo,
-        LogicOp, Offset2D, PipelineCache, PipelineColorBlendAttachmentState,
-        PipelineColorBlendStateCreateInfo, PipelineDepthStencilStateCreateInfo,
-        PipelineInputAssemblyStateCreateInfo, PipelineLayoutCreateFlags,
-        PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo,
-        PipelineShaderStageCreateInfo, PipelineVertexInputStateCreateInfo,
-        PipelineViewportStateCreateInfo, PolygonMode, PrimitiveTopology, Rect2D, RenderPass,
-        SampleCountFlags, Viewport,
+        self, BlendFactor, BlendOp, ColorComponentFlags, CullModeFlags, Extent2D, FrontFace,
+        GraphicsPipelineCreateInfo, LogicOp, Offset2D, PipelineCache,
+        PipelineColorBlendAttachmentState, PipelineColorBlendStateCreateInfo,
+        PipelineDepthStencilStateCreateInfo, PipelineInputAssemblyStateCreateInfo,
+        PipelineLayoutCreateFlags, PipelineMultisampleStateCreateInfo,
+        PipelineRasterizationStateCreateInfo, PipelineShaderStageCreateInfo,
+        PipelineVertexInputStateCreateInfo, PipelineViewportStateCreateInfo, PolygonMode,
+        PrimitiveTopology, Rect2D, RenderPass, SampleCountFlags, Viewport,
     },
     Device,
 };
 
+use asset_manager::{AlphaMode, PbrMaterial};
+
 use crate::mesh::{MeshPushConstants, VertexInputDescription};
 
 use super::Shader;
 
+/// The fixed function state that differs between the materials drawn with one set of shaders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineVariant {
+    /// Blend over what is already drawn without writing depth, for `AlphaMode::Blend`
+    pub blend: bool,
+    /// Draw back faces too instead of culling them
+    pub double_sided: bool,
+}
+
+impl PipelineVariant {
+    const ALL: [PipelineVariant; 4] = [
+        PipelineVariant {
+            blend: false,
+            double_sided: false,
+        },
+        PipelineVariant {
+            blend: false,
+            double_sided: true,
+        },
+        PipelineVariant {
+            blend: true,
+            double_sided: false,
+        },
+        PipelineVariant {
+            blend: true,
+            double_sided: true,
+        },
+    ];
+
+    pub fn for_material(material: &PbrMaterial) -> PipelineVariant {
+        PipelineVariant {
+            blend: material.alpha_mode == AlphaMode::Blend,
+            double_sided: material.double_sided,
+        }
+    }
+
+    // Where the variant's pipeline is in `Pipeline::pipelines`
+    fn index(&self) -> usize {
+        self.blend as usize * 2 + self.double_sided as usize
+    }
+}
+
 #[derive(Clone)]
 pub struct Pipeline {
     device: Device,
     // TODO: Eventually we should have a pipeline cache that reuses pipeline layouts if they already exist
     pub pipeline_layout: ash::vk::PipelineLayout,
-    pub pipeline: ash::vk::Pipeline,
+    /// One for every `PipelineVariant`, all sharing the layout
+    pipelines: Vec<ash::vk::Pipeline>,
 }
 
 impl Pipeline {
@@ -34,6 +79,7 @@ impl Pipeline {
         width: u32,
         height: u32,
         vertex_input_description: &VertexInputDescription,
+        set_layouts: &[vk::DescriptorSetLayout],
     ) -> Result<Pipeline, String> {
         let push_constant_ranges = [ash::vk::PushConstantRange::default()
             .stage_flags(ash::vk::ShaderStageFlags::VERTEX)
@@ -43,7 +89,7 @@ impl Pipeline {
         let pipeline_layout_create_info = ash::vk::PipelineLayoutCreateInfo::default()
             .flags(PipelineLayoutCreateFlags::empty())
             .push_constant_ranges(&push_constant_ranges)
-            .set_layouts(&[]);
+            .set_layouts(set_layouts);
 
         let pipeline_layout =
             match unsafe { device.create_pipeline_layout(&pipeline_layout_create_info, None) } {
@@ -53,14 +99,27 @@ impl Pipeline {
                 }
             };
 
-        let color_blend_attachment_states = [PipelineColorBlendAttachmentState::default()
+        let opaque_attachment_states = [PipelineColorBlendAttachmentState::default()
             .blend_enable(false)
             .color_write_mask(ColorComponentFlags::RGBA)];
 
-        let color_blend_state = PipelineColorBlendStateCreateInfo::default()
-            .attachments(&color_blend_attachment_states)
-            .logic_op_enable(false)
-            .logic_op(LogicOp::COPY);
+        let blend_attachment_states = [PipelineColorBlendAttachmentState::default()
+            .blend_enable(true)
+            .src_color_blend_factor(BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(BlendOp::ADD)
+            .src_alpha_blend_factor(BlendFactor::ONE)
+            .dst_alpha_blend_factor(BlendFactor::ZERO)
+            .alpha_blend_op(BlendOp::ADD)
+            .color_write_mask(ColorComponentFlags::RGBA)];
+
+        let color_blend_states =
+            [&opaque_attachment_states, &blend_attachment_states].map(|attachment_states| {
+                PipelineColorBlendStateCreateInfo::default()
+                    .attachments(attachment_states)
+                    .logic_op_enable(false)
+                    .logic_op(LogicOp::COPY)
+            });
 
         let input_assembly_state_create_info = PipelineInputAssemblyStateCreateInfo::default()
             .topology(PrimitiveTopology::TRIANGLE_LIST)
@@ -73,17 +132,20 @@ impl Pipeline {
             .rasterization_samples(SampleCountFlags::TYPE_1)
             .min_sample_shading(1.0);
 
-        let rasterization_state_create_info = PipelineRasterizationStateCreateInfo::default()
-            .cull_mode(CullModeFlags::NONE)
-            .depth_clamp_enable(false)
-            .depth_bias_enable(false)
-            .depth_bias_constant_factor(0.0)
-            .depth_bias_clamp(0.0)
-            .depth_bias_slope_factor(0.0)
-            .front_face(FrontFace::CLOCKWISE)
-            .line_width(1.0)
-            .polygon_mode(PolygonMode::FILL)
-            .rasterizer_discard_enable(false);
+        // glTF front faces wind counter-clockwise, and still do with the projection's y flipped
+        let rasterization_states = [CullModeFlags::BACK, CullModeFlags::NONE].map(|cull_mode| {
+            PipelineRasterizationStateCreateInfo::default()
+                .cull_mode(cull_mode)
+                .depth_clamp_enable(false)
+                .depth_bias_enable(false)
+                .depth_bias_constant_factor(0.0)
+                .depth_bias_clamp(0.0)
+                .depth_bias_slope_factor(0.0)
+                .front_face(FrontFace::COUNTER_CLOCKWISE)
+                .line_width(1.0)
+                .polygon_mode(PolygonMode::FILL)
+                .rasterizer_discard_enable(false)
+        });
 
         let vertex_input_state_create_info = PipelineVertexInputStateCreateInfo::default()
             .vertex_attribute_descriptions(&vertex_input_description.attribute_descriptions)
@@ -112,33 +174,45 @@ impl Pipeline {
             .map(|shader| shader.stage_create_info())
             .collect::<Vec<PipelineShaderStageCreateInfo>>();
 
-        let depth_stencil_state = PipelineDepthStencilStateCreateInfo::default()
-            .depth_test_enable(true)
-            .depth_write_enable(true)
-            .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
-            .depth_bounds_test_enable(false)
-            .min_depth_bounds(0.0)
-            .max_depth_bounds(1.0)
-            .stencil_test_enable(false);
-
-        let pipeline_create_info = GraphicsPipelineCreateInfo::default()
-            .color_blend_state(&color_blend_state)
-            .depth_stencil_state(&depth_stencil_state)
-            .input_assembly_state(&input_assembly_state_create_info)
-            .layout(pipeline_layout)
-            .multisample_state(&multisample_state_create_info)
-            .rasterization_state(&rasterization_state_create_info)
-            .render_pass(*render_pass)
-            .stages(&shader_stage_create_infos)
-            .subpass(0)
-            .vertex_input_state(&vertex_input_state_create_info)
-            .viewport_state(&viewport_state_create_info);
-
-        let pipeline = match unsafe {
-            device.create_graphics_pipelines(PipelineCache::null(), &[pipeline_create_info], None)
+        // Blended surfaces are drawn last, they are tested against the depth but don't hide
+        // what is behind them
+        let depth_stencil_states = [true, false].map(|depth_write| {
+            PipelineDepthStencilStateCreateInfo::default()
+                .depth_test_enable(true)
+                .depth_write_enable(depth_write)
+                .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+                .depth_bounds_test_enable(false)
+                .min_depth_bounds(0.0)
+                .max_depth_bounds(1.0)
+                .stencil_test_enable(false)
+        });
+
+        let pipeline_create_infos = PipelineVariant::ALL.map(|variant| {
+            GraphicsPipelineCreateInfo::default()
+                .color_blend_state(&color_blend_states[variant.blend as usize])
+                .depth_stencil_state(&depth_stencil_states[variant.blend as usize])
+                .input_assembly_state(&input_assembly_state_create_info)
+                .layout(pipeline_layout)
+                .multisample_state(&multisample_state_create_info)
+                .rasterization_state(&rasterization_states[variant.double_sided as usize])
+                .render_pass(*render_pass)
+                .stages(&shader_stage_create_infos)
+                .subpass(0)
+                .vertex_input_state(&vertex_input_state_create_info)
+                .viewport_state(&viewport_state_create_info)
+        });
+
+        let pipelines = match unsafe {
+            device.create_graphics_pipelines(PipelineCache::null(), &pipeline_create_infos, None)
         } {
-            Ok(pipelines) => pipelines[0],
+            Ok(pipelines) => pipelines,
             Err(err) => {
+                unsafe {
+                    for pipeline in err.0 {
+                        device.destroy_pipeline(pipeline, None);
+                    }
+                    device.destroy_pipeline_layout(pipeline_layout, None);
+                }
                 return Err(format!("Failed to create pipeline: {}", err.1));
             }
         };
@@ -146,15 +220,21 @@ impl Pipeline {
         Ok(Pipeline {
             device: device.clone(),
             pipeline_layout,
-            pipeline,
+            pipelines,
         })
     }
+
+    pub fn pipeline(&self, variant: PipelineVariant) -> ash::vk::Pipeline {
+        self.pipelines[variant.index()]
+    }
 }
 
 impl Drop for Pipeline {
     fn drop(&mut self) {
         unsafe {
-            self.device.destroy_pipeline(self.pipeline, None);
+            for pipeline in &self.pipelines {
+                self.device.destroy_pipeline(*pipeline, None);
+            }
             self.device
                 .destroy_pipeline_layout(self.pipeline_layout, None);
         }
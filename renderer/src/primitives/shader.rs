@@ -1,10 +1,12 @@
-use std::{ffi::CString, fs, path::Path};
+use std::{ffi::CString, path::Path};
 
 use ash::{
     vk::{PipelineShaderStageCreateInfo, ShaderModule, ShaderModuleCreateInfo, ShaderStageFlags},
     Device,
 };
 
+use asset_manager::Vfs;
+
 pub struct Shader {
     device: Device,
     stage: ShaderStageFlags,
@@ -16,7 +18,7 @@ lazy_static! {
 }
 
 impl Shader {
-    pub fn from_path(device: &Device, path: &str) -> Result<Shader, String> {
+    pub fn from_path(device: &Device, vfs: &Vfs, path: &str) -> Result<Shader, String> {
         let file_ending = match Path::new(path).extension() {
             Some(file_ending) => file_ending.to_str().unwrap(),
             None => "vert",
@@ -30,28 +32,38 @@ impl Shader {
             }
         };
 
-        let shader_text = fs::read_to_string(path).expect("Failed to load vertex shader");
+        let shader_text = match vfs.read_to_string(path) {
+            Ok(shader_text) => shader_text,
+            Err(err) => {
+                return Err(format!("Failed to read shader: {}", err));
+            }
+        };
 
         let compiler = shaderc::Compiler::new().unwrap();
         let mut compiler_options = shaderc::CompileOptions::new().unwrap();
 
         compiler_options.add_macro_definition("EP", Some("main"));
 
-        let spirv_binary_data = compiler
-            .compile_into_spirv(
-                &shader_text,
-                match stage {
-                    ShaderStageFlags::VERTEX => shaderc::ShaderKind::Vertex,
-                    ShaderStageFlags::FRAGMENT => shaderc::ShaderKind::Fragment,
-                    _ => {
-                        return Err(format!("Unknown shader type: {}", file_ending));
-                    }
-                },
-                path,
-                "main",
-                Some(&compiler_options),
-            )
-            .unwrap();
+        let shader_kind = match stage {
+            ShaderStageFlags::VERTEX => shaderc::ShaderKind::Vertex,
+            ShaderStageFlags::FRAGMENT => shaderc::ShaderKind::Fragment,
+            _ => {
+                return Err(format!("Unknown shader type: {}", file_ending));
+            }
+        };
+
+        let spirv_binary_data = match compiler.compile_into_spirv(
+            &shader_text,
+            shader_kind,
+            path,
+            "main",
+            Some(&compiler_options),
+        ) {
+            Ok(spirv_binary_data) => spirv_binary_data,
+            Err(err) => {
+                return Err(format!("Failed to compile shader {}: {}", path, err));
+            }
+        };
 
         let shader_module_create_info = ShaderModuleCreateInfo::default()
             .code(spirv_binary_data.as_binary());
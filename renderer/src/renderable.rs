@@ -1,5 +1,13 @@
+use asset_manager::{Handle, Mesh};
+
+use crate::Material;
+
 pub struct Renderable {
-    pub mesh: String,
-    pub material: String,
+    pub mesh: Handle<Mesh>,
+    /// Only draw the sub-meshes of this part of the mesh, see `SubMesh::part`
+    pub part: Option<usize>,
+    pub material: Handle<Material>,
     pub matrix: glm::Mat4,
+    /// The level of detail to draw, 0 for the full mesh, see `Mesh::lod`
+    pub lod: usize,
 }
@@ -1,4 +1,9 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
 
 use ash::{
     vk::{
@@ -9,18 +14,21 @@ use ash::{
     },
     Device,
 };
-use asset_manager::AssetManager;
-use log::trace;
+use asset_manager::{AssetManager, Handle, Mesh, PbrMaterial, SubMesh, Vfs};
+use log::{info, trace, warn};
 
 use config::Config;
 
+use crate::deletion_queue::{DeletionQueue, Retired};
+use crate::material_data::MaterialDescriptors;
+use crate::texture;
 use crate::Boilerplate;
 use crate::Material;
 use crate::Renderable;
 use crate::{boilerplate::frame_data::FrameData, mesh::MeshPushConstants};
 use crate::{
-    mesh::Vertex,
-    primitives::{Pipeline, Shader, Swapchain},
+    mesh,
+    primitives::{Pipeline, PipelineVariant, Shader, Swapchain},
 };
 
 pub struct Renderer {
@@ -29,14 +37,23 @@ pub struct Renderer {
     render_pass: RenderPass,
     framebuffers: Vec<Framebuffer>,
     pipelines: HashMap<String, Rc<RefCell<Pipeline>>>,
-    materials: HashMap<String, Rc<RefCell<Material>>>,
+    pipeline_shaders: HashMap<String, Vec<String>>,
+    materials: HashMap<Handle<Material>, Rc<RefCell<Material>>>,
+    material_ids: HashMap<String, Handle<Material>>,
+    material_descriptors: MaterialDescriptors,
+    deletion_queue: DeletionQueue,
     framenumber: u64,
     mesh_binds: u64,
     material_binds: u64,
 }
 
 impl Renderer {
-    pub fn new(config: &Config, window: &winit::window::Window) -> Result<Renderer, String> {
+    /// Shaders are read through the virtual filesystem, like every other asset
+    pub fn new(
+        config: &Config,
+        window: &winit::window::Window,
+        vfs: &Vfs,
+    ) -> Result<Renderer, String> {
         trace!("Initializing: Renderer");
 
         let boilerplate = match Boilerplate::new(config, window) {
@@ -59,33 +76,23 @@ impl Renderer {
             Err(e) => return Err("Failed to init renderer: framebuffers: ".to_owned() + &e),
         };
 
-        let vertex_shader =
-            match Shader::from_path(&boilerplate.device, "assets/shaders/tri_mesh.vert") {
-                Ok(shader) => shader,
-                Err(e) => {
-                    return Err("Failed to create vertex shader: ".to_owned() + &e.to_string())
-                }
-            };
+        let material_descriptors = match MaterialDescriptors::new(&boilerplate.device) {
+            Ok(material_descriptors) => material_descriptors,
+            Err(e) => return Err("Failed to init renderer: materials: ".to_owned() + &e),
+        };
 
-        let color_fragment_shader =
-            match Shader::from_path(&boilerplate.device, "assets/shaders/colored_triangle.frag") {
-                Ok(shader) => shader,
-                Err(e) => {
-                    return Err("Failed to create fragment shader: ".to_owned() + &e.to_string())
-                }
-            };
+        let mesh_pipeline_shaders = vec![
+            "assets://shaders/pbr.vert".to_string(),
+            "assets://shaders/pbr.frag".to_string(),
+        ];
 
-        let mesh_pipeline = match Pipeline::new(
-            &boilerplate.device,
-            &[&vertex_shader, &color_fragment_shader],
+        let mesh_pipeline = Self::create_pipeline(
+            &boilerplate,
             &render_pass,
-            boilerplate.swapchain.extent.width,
-            boilerplate.swapchain.extent.height,
-            &Vertex::get_vertex_input_description(),
-        ) {
-            Ok(pipeline) => pipeline,
-            Err(e) => return Err("Failed to create pipeline: ".to_owned() + &e.to_string()),
-        };
+            vfs,
+            &mesh_pipeline_shaders,
+            &[material_descriptors.set_layout],
+        )?;
 
         let mut pipelines = HashMap::new();
         pipelines.insert(
@@ -93,25 +100,127 @@ impl Renderer {
             Rc::new(RefCell::new(mesh_pipeline)),
         );
 
-        let mut materials = HashMap::new();
-        materials.insert(
-            "defaultmesh".to_string(),
-            Rc::new(RefCell::new(Material {
-                pipeline: Rc::clone(pipelines.get("meshpipeline").unwrap()),
-            })),
-        );
+        let mut pipeline_shaders = HashMap::new();
+        pipeline_shaders.insert("meshpipeline".to_string(), mesh_pipeline_shaders);
 
-        Ok(Renderer {
+        let mut renderer = Renderer {
             config: config.clone(),
             boilerplate,
             render_pass,
             framebuffers,
             pipelines,
-            materials,
+            pipeline_shaders,
+            materials: HashMap::new(),
+            material_ids: HashMap::new(),
+            material_descriptors,
+            deletion_queue: DeletionQueue::new(),
             framenumber: 0,
             mesh_binds: 0,
             material_binds: 0,
-        })
+        };
+
+        renderer.insert_material(
+            "defaultmesh",
+            Material {
+                pipeline: Rc::clone(renderer.pipelines.get("meshpipeline").unwrap()),
+            },
+        );
+
+        Ok(renderer)
+    }
+
+    /// Get the handle of a material by its name
+    pub fn get_material(&self, name: &str) -> Option<Handle<Material>> {
+        self.material_ids.get(name).copied()
+    }
+
+    fn insert_material(&mut self, name: &str, material: Material) -> Handle<Material> {
+        let handle = Handle::new(self.materials.len() as u32, 0);
+
+        self.materials
+            .insert(handle, Rc::new(RefCell::new(material)));
+        self.material_ids.insert(name.to_owned(), handle);
+
+        handle
+    }
+
+    fn create_pipeline(
+        boilerplate: &Boilerplate,
+        render_pass: &RenderPass,
+        vfs: &Vfs,
+        shader_paths: &[String],
+        set_layouts: &[vk::DescriptorSetLayout],
+    ) -> Result<Pipeline, String> {
+        let mut shaders = vec![];
+        for shader_path in shader_paths {
+            match Shader::from_path(&boilerplate.device, vfs, shader_path) {
+                Ok(shader) => shaders.push(shader),
+                Err(e) => return Err("Failed to create shader: ".to_owned() + &e),
+            }
+        }
+
+        let shader_refs = shaders.iter().collect::<Vec<&Shader>>();
+
+        match Pipeline::new(
+            &boilerplate.device,
+            &shader_refs,
+            render_pass,
+            boilerplate.swapchain.extent.width,
+            boilerplate.swapchain.extent.height,
+            &mesh::get_vertex_input_description(),
+            set_layouts,
+        ) {
+            Ok(pipeline) => Ok(pipeline),
+            Err(e) => Err("Failed to create pipeline: ".to_owned() + &e),
+        }
+    }
+
+    /// Rebuild every pipeline using a shader that changed on disk
+    /// A pipeline that fails to rebuild keeps its last good version
+    fn reload_changed_shaders(&mut self, asset_manager: &mut AssetManager) {
+        let changed_files = asset_manager.take_changed_files();
+
+        if changed_files.is_empty() {
+            return;
+        }
+
+        for (name, shader_paths) in &self.pipeline_shaders {
+            let uses_changed_shader = shader_paths.iter().any(|shader_path| {
+                changed_files
+                    .iter()
+                    .any(|changed_file| asset_manager.vfs().is_same_file(shader_path, changed_file))
+            });
+
+            if !uses_changed_shader {
+                continue;
+            }
+
+            match Self::create_pipeline(
+                &self.boilerplate,
+                &self.render_pass,
+                asset_manager.vfs(),
+                shader_paths,
+                &[self.material_descriptors.set_layout],
+            ) {
+                Ok(pipeline) => {
+                    let previous = std::mem::replace(
+                        &mut *self.pipelines.get(name).unwrap().borrow_mut(),
+                        pipeline,
+                    );
+
+                    self.deletion_queue
+                        .push(self.framenumber, Retired::Pipeline(Box::new(previous)));
+
+                    info!("Reloaded pipeline: {}", name);
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to reload pipeline {}, keeping last version: {}",
+                        name, e
+                    );
+                }
+            }
+        }
     }
 
     fn init_render_pass(device: &Device, swapchain: &Swapchain) -> Result<RenderPass, String> {
@@ -221,54 +330,171 @@ impl Renderer {
             [(self.framenumber % self.config.renderer.frame_overlap as u64) as usize]
     }
 
+    // Uploads the mesh if needed and binds its buffers, the mesh is returned if it can be drawn
     fn bind_renderable_mesh(
         &mut self,
         renderable: &Renderable,
         asset_manager: &mut AssetManager,
-    ) -> (bool, String, u32) {
-        let mesh_handle = asset_manager.get_mesh(&renderable.mesh);
+    ) -> Option<Arc<Mutex<Mesh>>> {
+        let mesh_handle = asset_manager.get_mesh(renderable.mesh)?;
         let lock = mesh_handle.lock();
         let mut mesh = lock.unwrap();
 
         if mesh.needs_uploaded() {
-            let vertices = mesh.vertices.clone();
+            let mesh_buffers = self
+                .boilerplate
+                .allocator
+                .create_mesh_buffers(&mesh.vertices, &mesh.indices);
+
+            match mesh_buffers {
+                Ok(mesh_buffers) => {
+                    // A reloaded mesh may still be in use by frames in flight
+                    if let Some(previous) = mesh.add_gpu_info(mesh_buffers) {
+                        self.deletion_queue
+                            .push(self.framenumber, Retired::MeshBuffers(previous));
+                    }
+
+                    // Its materials may have changed too
+                    self.material_descriptors.retire_mesh(
+                        renderable.mesh,
+                        &mut self.deletion_queue,
+                        self.framenumber,
+                    );
+                }
+                Err(e) => {
+                    warn!("Failed to upload mesh {}: {}", mesh.asset_info.id, e);
+                    mesh.upload_failed();
+                }
+            }
+        }
 
-            mesh.add_gpu_info(self.boilerplate.allocator.create_vertex_buffer(&vertices));
+        if !self.bind_mesh_buffers(&mesh) {
+            return None;
         }
 
-        let mut can_be_drawn = false;
+        drop(mesh);
+        Some(mesh_handle)
+    }
 
-        if mesh.gpu_info.is_some() {
-            let offset = 0;
-            let buffer = mesh.gpu_info.as_mut().unwrap().buffer;
+    // Every level of detail draws from the same buffers
+    fn bind_mesh_buffers(&mut self, mesh: &Mesh) -> bool {
+        let Some(gpu_info) = &mesh.gpu_info else {
+            return false;
+        };
+        let offset = 0;
 
-            self.current_frame_data()
-                .command_manager
-                .bind_vertex_buffers(0, &[buffer], &[offset]);
+        self.current_frame_data()
+            .command_manager
+            .bind_vertex_buffers(0, &[gpu_info.vertex_buffer.buffer], &[offset]);
+        self.current_frame_data().command_manager.bind_index_buffer(
+            gpu_info.index_buffer.buffer,
+            offset,
+            gpu_info.index_type,
+        );
+
+        self.mesh_binds += 1;
+        true
+    }
+
+    fn upload_textures(&mut self, asset_manager: &mut AssetManager) {
+        for texture_clone in asset_manager.textures() {
+            let mut texture = texture_clone.lock().unwrap();
+
+            if !texture.needs_uploaded() {
+                continue;
+            }
 
-            can_be_drawn = true;
-            self.mesh_binds += 1;
+            let upload = texture::upload_texture(
+                &self.boilerplate.device,
+                &self.boilerplate.allocator,
+                &self.current_frame_data().command_manager,
+                &texture,
+            );
+
+            match upload {
+                Ok((texture_image, staging_buffer)) => {
+                    self.deletion_queue
+                        .push(self.framenumber, Retired::Buffer(staging_buffer));
+
+                    // A reloaded texture may still be in use by frames in flight
+                    if let Some(previous) = texture.add_gpu_info(texture_image) {
+                        self.deletion_queue
+                            .push(self.framenumber, Retired::TextureImage(previous));
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to upload texture {}: {}", texture.asset_info.id, e);
+                    texture.upload_failed();
+                }
+            }
         }
+    }
+
+    // The layout and the pipeline of the material's variant for drawing the sub-mesh
+    fn material_pipeline(
+        &self,
+        material: Handle<Material>,
+        variant: PipelineVariant,
+    ) -> (vk::PipelineLayout, vk::Pipeline) {
+        let material = self.materials.get(&material).unwrap().borrow();
+        let pipeline = material.pipeline.borrow();
+
+        (pipeline.pipeline_layout, pipeline.pipeline(variant))
+    }
+
+    fn bind_pipeline(&mut self, pipeline: vk::Pipeline, bound_pipeline: &mut Option<vk::Pipeline>) {
+        if *bound_pipeline == Some(pipeline) {
+            return;
+        }
+
+        self.current_frame_data()
+            .command_manager
+            .bind_pipeline(pipeline);
 
-        (can_be_drawn, renderable.mesh.clone(), mesh.vertex_count)
+        *bound_pipeline = Some(pipeline);
+        self.material_binds += 1;
     }
 
-    fn bind_renderable_material(
+    fn draw_sub_mesh(
         &mut self,
+        pipeline_layout: vk::PipelineLayout,
+        view_proj_mat: glm::Mat4,
         renderable: &Renderable,
-    ) -> (Option<Rc<RefCell<Material>>>, String) {
-        let material = self.materials.get(&renderable.material).unwrap();
+        sub_mesh: &SubMesh,
+        material: Option<&PbrMaterial>,
+        asset_manager: &mut AssetManager,
+    ) {
+        let descriptor_set = self.material_descriptors.descriptor_set(
+            &self.boilerplate.allocator,
+            &mut self.deletion_queue,
+            self.framenumber,
+            asset_manager,
+            (renderable.mesh, sub_mesh.material),
+            material,
+        );
 
         self.current_frame_data()
             .command_manager
-            .bind_pipeline(&material.borrow().pipeline.borrow());
+            .bind_descriptor_sets(pipeline_layout, 0, &[descriptor_set]);
 
-        let last_material = Some(Rc::clone(self.materials.get(&renderable.material).unwrap()));
-        let last_material_id = renderable.material.clone();
+        let mvp = view_proj_mat * renderable.matrix * sub_mesh.transform;
 
-        self.material_binds += 1;
+        let push_constants = MeshPushConstants {
+            data: glm::vec4(0.0, 0.0, 0.0, 0.0),
+            render_matrix: mvp,
+        };
 
-        (last_material, last_material_id)
+        self.current_frame_data()
+            .command_manager
+            .push_constants(pipeline_layout, push_constants);
+
+        self.current_frame_data().command_manager.draw_indexed(
+            sub_mesh.index_count,
+            1,
+            sub_mesh.first_index,
+            sub_mesh.vertex_offset,
+            0,
+        );
     }
 
     fn render_objects(
@@ -285,50 +511,88 @@ impl Renderer {
         projection_matrix[(1, 1)] *= -1.0;
         let view_proj_mat = projection_matrix * view_matrix;
 
-        let mut last_mesh_id: String = "".to_string();
-        let mut last_mesh_vertex_count = 0;
+        let default_material = PbrMaterial::default();
 
-        let mut last_material: Option<Rc<RefCell<Material>>> = None;
-        let mut last_material_id: String = "".to_string();
+        let mut bound_mesh: Option<(Handle<Mesh>, Arc<Mutex<Mesh>>)> = None;
+
+        let mut bound_pipeline: Option<vk::Pipeline> = None;
+
+        // Blended sub-meshes are drawn after everything opaque, so they can blend over it
+        let mut blended = vec![];
 
         for renderable in renderables {
-            if renderable.mesh != last_mesh_id {
-                let (can_be_drawn, last_bound_mesh_id, last_bound_mesh_vertex_count) =
-                    self.bind_renderable_mesh(renderable, asset_manager);
+            if bound_mesh.as_ref().map(|(handle, _)| *handle) != Some(renderable.mesh) {
+                match self.bind_renderable_mesh(renderable, asset_manager) {
+                    Some(mesh) => bound_mesh = Some((renderable.mesh, mesh)),
+                    None => continue,
+                }
+            }
 
-                if !can_be_drawn {
+            let mesh_clone = &bound_mesh.as_ref().unwrap().1;
+            let mesh = mesh_clone.lock().unwrap();
+            let lod = renderable.lod.min(mesh.lods.len());
+
+            let sub_meshes = mesh
+                .lod(lod)
+                .iter()
+                .filter(|sub_mesh| renderable.part.is_none_or(|part| sub_mesh.part == part));
+
+            for sub_mesh in sub_meshes {
+                let material = sub_mesh
+                    .material
+                    .and_then(|material| mesh.materials.get(material));
+                let variant = PipelineVariant::for_material(material.unwrap_or(&default_material));
+
+                if variant.blend {
+                    let center =
+                        view_matrix * renderable.matrix * sub_mesh.bounding_sphere.center.push(1.0);
+
+                    blended.push((center.z, renderable, mesh_clone.clone(), *sub_mesh));
                     continue;
-                } else {
-                    last_mesh_id = last_bound_mesh_id;
-                    last_mesh_vertex_count = last_bound_mesh_vertex_count;
                 }
-            }
 
-            if renderable.material != last_material_id {
-                (last_material, last_material_id) = self.bind_renderable_material(renderable);
+                let (pipeline_layout, pipeline) =
+                    self.material_pipeline(renderable.material, variant);
+                self.bind_pipeline(pipeline, &mut bound_pipeline);
+
+                self.draw_sub_mesh(
+                    pipeline_layout,
+                    view_proj_mat,
+                    renderable,
+                    sub_mesh,
+                    material,
+                    asset_manager,
+                );
             }
+        }
 
-            let mvp = view_proj_mat * renderable.matrix;
+        // Back to front, the camera looks down -z so the farthest has the lowest z
+        blended.sort_by(|a, b| a.0.total_cmp(&b.0));
 
-            let push_constants = MeshPushConstants {
-                data: glm::vec4(0.0, 0.0, 0.0, 0.0),
-                render_matrix: mvp,
-            };
+        for (_, renderable, mesh_clone, sub_mesh) in &blended {
+            let mesh = mesh_clone.lock().unwrap();
 
-            self.current_frame_data().command_manager.push_constants(
-                last_material
-                    .as_ref()
-                    .unwrap()
-                    .borrow()
-                    .pipeline
-                    .borrow()
-                    .pipeline_layout,
-                push_constants,
-            );
+            // Already uploaded by the opaque pass, only switching buffers is left
+            if bound_mesh.as_ref().map(|(handle, _)| *handle) != Some(renderable.mesh) {
+                self.bind_mesh_buffers(&mesh);
+                bound_mesh = Some((renderable.mesh, mesh_clone.clone()));
+            }
 
-            self.current_frame_data()
-                .command_manager
-                .draw(last_mesh_vertex_count, 1, 0, 0);
+            let material = sub_mesh
+                .material
+                .and_then(|material| mesh.materials.get(material));
+            let variant = PipelineVariant::for_material(material.unwrap_or(&default_material));
+            let (pipeline_layout, pipeline) = self.material_pipeline(renderable.material, variant);
+            self.bind_pipeline(pipeline, &mut bound_pipeline);
+
+            self.draw_sub_mesh(
+                pipeline_layout,
+                view_proj_mat,
+                renderable,
+                sub_mesh,
+                material,
+                asset_manager,
+            );
         }
 
         trace!(
@@ -364,6 +628,15 @@ impl Renderer {
         }
         .expect("Failed to reset fence");
 
+        self.deletion_queue.flush(
+            self.framenumber,
+            self.config.renderer.frame_overlap as u64,
+            &self.boilerplate.device,
+            &self.boilerplate.allocator,
+        );
+
+        self.reload_changed_shaders(asset_manager);
+
         let (image_index, _) = self
             .boilerplate
             .swapchain
@@ -374,6 +647,20 @@ impl Renderer {
             .command_manager
             .begin_main_command_buffer();
 
+        // Copies are recorded before the render pass so this frame can already sample them
+        self.upload_textures(asset_manager);
+
+        self.material_descriptors
+            .upload_fallbacks(
+                &self.boilerplate.allocator,
+                &self.boilerplate.frame_data
+                    [(self.framenumber % self.config.renderer.frame_overlap as u64) as usize]
+                    .command_manager,
+                &mut self.deletion_queue,
+                self.framenumber,
+            )
+            .expect("Failed to upload fallback textures");
+
         let flash = 0.0;
 
         let clear_values = [
@@ -435,16 +722,35 @@ impl Renderer {
         unsafe {
             self.boilerplate.wait_for_fences();
 
+            self.deletion_queue
+                .flush_all(&self.boilerplate.device, &self.boilerplate.allocator);
+
+            self.material_descriptors
+                .destroy(&self.boilerplate.allocator);
+
             self.materials = HashMap::new();
+            self.material_ids = HashMap::new();
             self.pipelines = HashMap::new();
 
-            for (_, mesh_clone) in asset_manager.iter_meshes_mut().lock().unwrap().iter_mut() {
+            for mesh_clone in asset_manager.meshes() {
                 let mesh_handle = mesh_clone.lock();
 
                 let mut mesh = mesh_handle.unwrap();
 
                 if let Some(gpu_info) = &mut mesh.gpu_info {
-                    self.boilerplate.allocator.destroy_buffer(gpu_info)
+                    self.boilerplate.allocator.destroy_mesh_buffers(gpu_info)
+                };
+            }
+
+            for texture_clone in asset_manager.textures() {
+                let mut texture = texture_clone.lock().unwrap();
+
+                if let Some(gpu_info) = &mut texture.gpu_info {
+                    texture::destroy_texture_image(
+                        &self.boilerplate.device,
+                        &self.boilerplate.allocator,
+                        gpu_info,
+                    )
                 };
             }
 
@@ -0,0 +1,187 @@
+use ash::{vk, Device};
+use asset_manager::{ColorSpace, Texture, TextureFormat};
+use vk_mem::AllocationCreateInfo;
+
+use gpu_info::{Buffer, Image, TextureImage};
+
+use crate::{boilerplate::allocator::Allocator, primitives::CommandManager};
+
+/// Create a sampled image for the texture and record copying every mip level into it
+/// Returns the staging buffer the copy reads from, it must live until the commands have run
+pub fn upload_texture(
+    device: &Device,
+    allocator: &Allocator,
+    command_manager: &CommandManager,
+    texture: &Texture,
+) -> Result<(TextureImage, Buffer), String> {
+    upload_pixels(
+        device,
+        allocator,
+        command_manager,
+        (texture.width, texture.height),
+        vk_format(texture.format, texture.color_space),
+        &texture.mips,
+    )
+}
+
+/// Like `upload_texture`, for pixels that don't come from a texture asset
+pub fn upload_pixels(
+    device: &Device,
+    allocator: &Allocator,
+    command_manager: &CommandManager,
+    (width, height): (u32, u32),
+    format: vk::Format,
+    mips: &[Vec<u8>],
+) -> Result<(TextureImage, Buffer), String> {
+    let mip_levels = mips.len().max(1) as u32;
+
+    let image_create_info = vk::ImageCreateInfo::default()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(format)
+        .extent(vk::Extent3D {
+            width,
+            height,
+            depth: 1,
+        })
+        .mip_levels(mip_levels)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST);
+
+    let allocation_create_info = AllocationCreateInfo {
+        usage: vk_mem::MemoryUsage::AutoPreferDevice,
+        required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        ..Default::default()
+    };
+
+    let allocated_image = allocator.create_image(&image_create_info, &allocation_create_info)?;
+    let mut image = Image {
+        image: allocated_image.image,
+        allocation: allocated_image.allocation,
+    };
+
+    let view_create_info = vk::ImageViewCreateInfo::default()
+        .image(image.image)
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .format(format)
+        .subresource_range(color_subresource_range(mip_levels));
+
+    let view = match unsafe { device.create_image_view(&view_create_info, None) } {
+        Ok(view) => view,
+        Err(e) => {
+            allocator.destroy_texture_image(&mut image);
+            return Err("Failed to create texture image view: ".to_owned() + &e.to_string());
+        }
+    };
+
+    let sampler_create_info = vk::SamplerCreateInfo::default()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+        .address_mode_u(vk::SamplerAddressMode::REPEAT)
+        .address_mode_v(vk::SamplerAddressMode::REPEAT)
+        .address_mode_w(vk::SamplerAddressMode::REPEAT)
+        .max_lod(mip_levels as f32);
+
+    let sampler = match unsafe { device.create_sampler(&sampler_create_info, None) } {
+        Ok(sampler) => sampler,
+        Err(e) => {
+            unsafe { device.destroy_image_view(view, None) };
+            allocator.destroy_texture_image(&mut image);
+            return Err("Failed to create texture sampler: ".to_owned() + &e.to_string());
+        }
+    };
+
+    let staging_buffer = match allocator.create_staging_buffer(&mips.concat()) {
+        Ok(staging_buffer) => staging_buffer,
+        Err(e) => {
+            unsafe {
+                device.destroy_sampler(sampler, None);
+                device.destroy_image_view(view, None);
+            }
+            allocator.destroy_texture_image(&mut image);
+            return Err(e);
+        }
+    };
+
+    let mut buffer_offset = 0;
+    let regions: Vec<vk::BufferImageCopy> = mips
+        .iter()
+        .enumerate()
+        .map(|(level, mip)| {
+            let region = vk::BufferImageCopy::default()
+                .buffer_offset(buffer_offset)
+                .image_subresource(vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: level as u32,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .image_extent(vk::Extent3D {
+                    width: (width >> level).max(1),
+                    height: (height >> level).max(1),
+                    depth: 1,
+                });
+
+            buffer_offset += mip.len() as u64;
+
+            region
+        })
+        .collect();
+
+    command_manager.transition_image_layout(
+        image.image,
+        color_subresource_range(mip_levels),
+        vk::ImageLayout::UNDEFINED,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+    );
+    command_manager.copy_buffer_to_image(staging_buffer.buffer, image.image, &regions);
+    command_manager.transition_image_layout(
+        image.image,
+        color_subresource_range(mip_levels),
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    );
+
+    let texture_image = TextureImage {
+        image,
+        view,
+        sampler,
+        mip_levels,
+    };
+
+    Ok((texture_image, staging_buffer))
+}
+
+pub fn destroy_texture_image(
+    device: &Device,
+    allocator: &Allocator,
+    texture_image: &mut TextureImage,
+) {
+    unsafe {
+        device.destroy_sampler(texture_image.sampler, None);
+        device.destroy_image_view(texture_image.view, None);
+    }
+
+    allocator.destroy_texture_image(&mut texture_image.image);
+}
+
+fn vk_format(format: TextureFormat, color_space: ColorSpace) -> vk::Format {
+    match (format, color_space) {
+        (TextureFormat::Rgba8, ColorSpace::Srgb) => vk::Format::R8G8B8A8_SRGB,
+        (TextureFormat::Rgba8, ColorSpace::Linear) => vk::Format::R8G8B8A8_UNORM,
+        // KTX2 formats already say whether they are sRGB
+        (TextureFormat::Vulkan(format), _) => vk::Format::from_raw(format as i32),
+    }
+}
+
+fn color_subresource_range(mip_levels: u32) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: mip_levels,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}